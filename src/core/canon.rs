@@ -0,0 +1,287 @@
+use crate::core::{AtomIndex, Bond, Chirality, Molecule};
+
+impl Molecule {
+    /// A rank for each atom, by atom index, that doesn't depend on the order of the atoms. Every
+    /// atom has a different rank from 0 up.
+    ///
+    /// Atoms are first ranked by their own properties, then repeatedly by their neighbours'
+    /// ranks until no more atoms can be told apart. While atoms are still tied, each of the
+    /// first tied atoms in turn is given the lower rank and the ranks refined again. Of the
+    /// rankings this ends in, the one that writes the molecule, including the chirality of each
+    /// stereocentre relative to its neighbours' ranks, in the smallest form is chosen. Choices
+    /// that a symmetry of the molecule maps onto one already tried are skipped.
+    ///
+    /// Each component is ranked on its own, and the components are ordered by the form they're
+    /// written in, so that identical ones don't have to be told apart.
+    pub fn canonical_ranks(&self) -> Vec<usize> {
+        let mut components: Vec<(Certificate, Vec<AtomIndex>, Vec<usize>)> = self
+            .components()
+            .into_iter()
+            .map(|atoms| {
+                let (certificate, ranks) = self.subgraph(&atoms).canonical_form();
+                (certificate, atoms, ranks)
+            })
+            .collect();
+        components.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut ranks = vec![0; self.atom_count()];
+        let mut offset = 0;
+        for (_, atoms, component_ranks) in components {
+            for (atom, rank) in atoms.iter().zip(&component_ranks) {
+                ranks[atom.index()] = offset + rank;
+            }
+            offset += atoms.len();
+        }
+        ranks
+    }
+
+    /// The smallest certificate of a connected molecule, and the ranks that give it.
+    fn canonical_form(&self) -> (Certificate, Vec<usize>) {
+        let invariants: Vec<Invariant> = self.atoms().map(|atom| self.invariant(atom)).collect();
+        let mut search = Search {
+            mol: self,
+            invariants: &invariants,
+            first: None,
+            best: None,
+            automorphisms: vec![],
+        };
+        let ranks = self.refine(dense_ranks(&invariants));
+        search.visit(ranks, &mut vec![]);
+        search.best.unwrap()
+    }
+
+    fn invariant(&self, atom: AtomIndex) -> Invariant {
+        let a = self.atom(atom);
+        (
+            a.element.atomic_number(),
+            a.isotope,
+            a.formal_charge,
+            a.hydrogens,
+            a.aromatic,
+            self.degree(atom),
+            a.chirality.is_some(),
+            a.map,
+        )
+    }
+
+    /// Splits ranks by the ranks of each atom's neighbours until they stop changing.
+    fn refine(&self, mut ranks: Vec<usize>) -> Vec<usize> {
+        let mut classes = count_distinct(&ranks);
+        loop {
+            let keys: Vec<(usize, Vec<(usize, Bond)>)> = self
+                .atoms()
+                .map(|atom| {
+                    let mut neighbors: Vec<(usize, Bond)> = self
+                        .neighbors(atom)
+                        .map(|n| (ranks[n.index()], self.bond(atom, n).unwrap()))
+                        .collect();
+                    neighbors.sort_unstable();
+                    (ranks[atom.index()], neighbors)
+                })
+                .collect();
+            let refined = dense_ranks(&keys);
+            let refined_classes = count_distinct(&refined);
+            if refined_classes == classes {
+                return ranks;
+            }
+            ranks = refined;
+            classes = refined_classes;
+        }
+    }
+
+    /// The molecule written out with atoms in the order of their ranks, which must all differ.
+    fn certificate(&self, invariants: &[Invariant], ranks: &[usize]) -> Certificate {
+        let mut atoms: Vec<(usize, Invariant, Option<bool>)> = self
+            .atoms()
+            .map(|atom| {
+                let chirality = self.ranked_chirality(atom, ranks);
+                let clockwise = chirality.map(|c| c == Chirality::Clockwise);
+                (ranks[atom.index()], invariants[atom.index()], clockwise)
+            })
+            .collect();
+        atoms.sort_unstable();
+        let mut bonds: Vec<(usize, usize, Bond)> = self
+            .bonds()
+            .map(|(a, b, bond)| {
+                let (a, b) = (ranks[a.index()], ranks[b.index()]);
+                (a.min(b), a.max(b), bond)
+            })
+            .collect();
+        bonds.sort_unstable();
+        let atoms = atoms.into_iter().map(|(_, i, c)| (i, c)).collect();
+        (atoms, bonds)
+    }
+
+    /// The atom's chirality with its neighbours listed in order of rank instead of index.
+    fn ranked_chirality(&self, atom: AtomIndex, ranks: &[usize]) -> Option<Chirality> {
+        let chirality = self.atom(atom).chirality?;
+        let mut neighbors: Vec<AtomIndex> = self.neighbors(atom).collect();
+        neighbors.sort_unstable();
+        let mut order: Vec<usize> = (0..neighbors.len()).collect();
+        order.sort_by_key(|i| ranks[neighbors[*i].index()]);
+        Some(chirality.permuted(&order))
+    }
+}
+
+type Invariant = (u8, Option<u16>, i8, u8, bool, usize, bool, Option<usize>);
+
+type Certificate = (Vec<(Invariant, Option<bool>)>, Vec<(usize, usize, Bond)>);
+
+/// A depth first search over the ways of breaking ties, each ending in a ranking of every atom.
+struct Search<'m> {
+    mol: &'m Molecule,
+    invariants: &'m [Invariant],
+    /// The first ranking found, with the atoms picked on the way to it.
+    first: Option<(Certificate, Vec<usize>, Vec<usize>)>,
+    /// The ranking with the smallest certificate so far.
+    best: Option<(Certificate, Vec<usize>)>,
+    /// Permutations of atom indices found to preserve the molecule.
+    automorphisms: Vec<Vec<usize>>,
+}
+
+impl<'m> Search<'m> {
+    /// Searches below the refined `ranks` reached by picking the atoms in `path`. Returns the
+    /// depth to go back to when the rest of the branch is a symmetric copy of one already searched.
+    fn visit(&mut self, ranks: Vec<usize>, path: &mut Vec<usize>) -> Option<usize> {
+        let cell = first_tied(&ranks);
+        if cell.is_empty() {
+            return self.leaf(ranks, path);
+        }
+        let mut tried: Vec<usize> = vec![];
+        let mut orbits = Orbits::new(ranks.len());
+        for atom in cell {
+            // Skip atoms that a symmetry fixing the path maps onto one already tried
+            if !tried.is_empty() {
+                orbits.update(&self.automorphisms, path);
+                let orbit = orbits.find(atom);
+                if tried.iter().any(|t| orbits.find(*t) == orbit) {
+                    continue;
+                }
+            }
+            let keys: Vec<(usize, bool)> = ranks
+                .iter()
+                .enumerate()
+                .map(|(i, r)| (*r, i != atom))
+                .collect();
+            let refined = self.mol.refine(dense_ranks(&keys));
+            path.push(atom);
+            let back = self.visit(refined, path);
+            path.pop();
+            tried.push(atom);
+            if let Some(depth) = back {
+                if depth < path.len() {
+                    return Some(depth);
+                }
+            }
+        }
+        None
+    }
+
+    fn leaf(&mut self, ranks: Vec<usize>, path: &[usize]) -> Option<usize> {
+        let certificate = self.mol.certificate(self.invariants, &ranks);
+        let (first, first_ranks, first_path) = match &self.first {
+            Some(first) => first,
+            None => {
+                self.best = Some((certificate.clone(), ranks.clone()));
+                self.first = Some((certificate, ranks, path.to_vec()));
+                return None;
+            }
+        };
+        // The same molecule from a different ranking is a symmetry, mapping the first ranking's
+        // branch onto this one, so the rest of this branch can be skipped
+        if certificate == *first {
+            let automorphism = permutation(first_ranks, &ranks);
+            self.automorphisms.push(automorphism);
+            let common = path
+                .iter()
+                .zip(first_path)
+                .take_while(|(a, b)| a == b)
+                .count();
+            return Some(common);
+        }
+        let (best, best_ranks) = self.best.as_ref().unwrap();
+        if certificate == *best {
+            let automorphism = permutation(best_ranks, &ranks);
+            self.automorphisms.push(automorphism);
+        } else if certificate < *best {
+            self.best = Some((certificate, ranks));
+        }
+        None
+    }
+}
+
+/// The orbits of atoms under the symmetries that fix the atoms of a path, as a disjoint set
+/// forest.
+struct Orbits {
+    parents: Vec<usize>,
+    /// How many of the symmetries found have been taken into account.
+    seen: usize,
+}
+
+impl Orbits {
+    fn new(atoms: usize) -> Self {
+        Orbits {
+            parents: (0..atoms).collect(),
+            seen: 0,
+        }
+    }
+
+    /// Merges the orbits joined by symmetries found since the last update.
+    fn update(&mut self, automorphisms: &[Vec<usize>], path: &[usize]) {
+        for automorphism in &automorphisms[self.seen..] {
+            if path.iter().all(|a| automorphism[*a] == *a) {
+                for (a, b) in automorphism.iter().enumerate() {
+                    let (a, b) = (self.find(a), self.find(*b));
+                    self.parents[a.max(b)] = a.min(b);
+                }
+            }
+        }
+        self.seen = automorphisms.len();
+    }
+
+    fn find(&mut self, mut a: usize) -> usize {
+        while self.parents[a] != a {
+            self.parents[a] = self.parents[self.parents[a]];
+            a = self.parents[a];
+        }
+        a
+    }
+}
+
+/// The atom each atom is mapped to, going from one ranking to another of the same molecule.
+fn permutation(from: &[usize], to: &[usize]) -> Vec<usize> {
+    let mut by_rank = vec![0; to.len()];
+    for (atom, rank) in to.iter().enumerate() {
+        by_rank[*rank] = atom;
+    }
+    from.iter().map(|rank| by_rank[*rank]).collect()
+}
+
+/// The atoms sharing the lowest rank that more than one atom has, in index order.
+fn first_tied(ranks: &[usize]) -> Vec<usize> {
+    let mut sizes = vec![0; ranks.len()];
+    for rank in ranks {
+        sizes[*rank] += 1;
+    }
+    match sizes.iter().position(|size| *size > 1) {
+        Some(rank) => (0..ranks.len()).filter(|i| ranks[*i] == rank).collect(),
+        None => vec![],
+    }
+}
+
+/// The position of each key among the distinct keys in ascending order.
+fn dense_ranks<T: Ord>(keys: &[T]) -> Vec<usize> {
+    let mut sorted: Vec<&T> = keys.iter().collect();
+    sorted.sort();
+    sorted.dedup();
+    keys.iter()
+        .map(|key| sorted.binary_search(&key).unwrap())
+        .collect()
+}
+
+fn count_distinct(ranks: &[usize]) -> usize {
+    let mut sorted = ranks.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    sorted.len()
+}
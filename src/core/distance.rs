@@ -0,0 +1,25 @@
+use crate::core::Molecule;
+use std::collections::VecDeque;
+
+impl Molecule {
+    /// The number of bonds on a shortest path between each pair of atoms, by atom index, or
+    /// `None` for atoms in different components.
+    pub fn distance_matrix(&self) -> Vec<Vec<Option<usize>>> {
+        self.atoms()
+            .map(|start| {
+                let mut distances = vec![None; self.atom_count()];
+                distances[start.index()] = Some(0);
+                let mut queue = VecDeque::from(vec![(start, 0)]);
+                while let Some((atom, distance)) = queue.pop_front() {
+                    for n in self.neighbors(atom) {
+                        if distances[n.index()].is_none() {
+                            distances[n.index()] = Some(distance + 1);
+                            queue.push_back((n, distance + 1));
+                        }
+                    }
+                }
+                distances
+            })
+            .collect()
+    }
+}
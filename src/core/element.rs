@@ -1,5 +1,5 @@
 #[rustfmt::skip]
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, enum_utils::FromStr)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, enum_utils::FromStr)]
 pub enum Element {
     //  0   1   2   3   4   5   6   7   8   9
     H = 1,  He, Li, Be, B,  C,  N,  O,  F,  //  0
@@ -16,8 +16,120 @@ pub enum Element {
     Ds, Rg, Cn, Nh, Fl, Mc, Lv, Ts, Og      // 11
 }
 
+#[rustfmt::skip]
+const SYMBOLS: [&str; 118] = [
+         "H",  "He", "Li", "Be", "B",  "C",  "N",  "O",  "F",
+    "Ne", "Na", "Mg", "Al", "Si", "P",  "S",  "Cl", "Ar", "K",
+    "Ca", "Sc", "Ti", "V",  "Cr", "Mn", "Fe", "Co", "Ni", "Cu",
+    "Zn", "Ga", "Ge", "As", "Se", "Br", "Kr", "Rb", "Sr", "Y",
+    "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+    "Sn", "Sb", "Te", "I",  "Xe", "Cs", "Ba", "La", "Ce", "Pr",
+    "Nd", "Pm", "Sm", "Eu", "Gd", "Tb", "Dy", "Ho", "Er", "Tm",
+    "Yb", "Lu", "Hf", "Ta", "W",  "Re", "Os", "Ir", "Pt", "Au",
+    "Hg", "Tl", "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac",
+    "Th", "Pa", "U",  "Np", "Pu", "Am", "Cm", "Bk", "Cf", "Es",
+    "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt",
+    "Ds", "Rg", "Cn", "Nh", "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+/// IUPAC standard atomic weights, abridged. Elements without a stable isotope use the mass number
+/// of their longest-lived isotope.
+#[rustfmt::skip]
+const ATOMIC_MASSES: [f64; 118] = [
+                 1.008,        4.002_602,    6.94,         9.012_183,    10.81,
+    12.011,      14.007,       15.999,       18.998_403,   20.1797,      22.989_769,
+    24.305,      26.981_538,   28.085,       30.973_762,   32.06,        35.45,
+    39.948,      39.0983,      40.078,       44.955_908,   47.867,       50.9415,
+    51.9961,     54.938_044,   55.845,       58.933_194,   58.6934,      63.546,
+    65.38,       69.723,       72.630,       74.921_595,   78.971,       79.904,
+    83.798,      85.4678,      87.62,        88.905_84,    91.224,       92.906_37,
+    95.95,       98.0,         101.07,       102.905_50,   106.42,       107.8682,
+    112.414,     114.818,      118.710,      121.760,      127.60,       126.904_47,
+    131.293,     132.905_452,  137.327,      138.905_47,   140.116,      140.907_66,
+    144.242,     145.0,        150.36,       151.964,      157.25,       158.925_35,
+    162.500,     164.930_33,   167.259,      168.934_22,   173.045,      174.9668,
+    178.49,      180.947_88,   183.84,       186.207,      190.23,       192.217,
+    195.084,     196.966_569,  200.592,      204.38,       207.2,        208.980_40,
+    209.0,       210.0,        222.0,        223.0,        226.0,        227.0,
+    232.0377,    231.035_88,   238.028_91,   237.0,        244.0,        243.0,
+    247.0,       247.0,        251.0,        252.0,        257.0,        258.0,
+    259.0,       262.0,        267.0,        268.0,        269.0,        270.0,
+    269.0,       278.0,        281.0,        282.0,        285.0,        286.0,
+    289.0,       290.0,        293.0,        294.0,        294.0,
+];
+
+/// Pauling electronegativities, or zero for the lighter noble gases and the superheavy elements,
+/// which have none.
+#[rustfmt::skip]
+const ELECTRONEGATIVITIES: [f64; 118] = [
+    2.20,  0.0,   0.98,  1.57,  2.04,  2.55,  3.04,  3.44,  3.98,  0.0,
+    0.93,  1.31,  1.61,  1.90,  2.19,  2.58,  3.16,  0.0,   0.82,  1.00,
+    1.36,  1.54,  1.63,  1.66,  1.55,  1.83,  1.88,  1.91,  1.90,  1.65,
+    1.81,  2.01,  2.18,  2.55,  2.96,  3.00,  0.82,  0.95,  1.22,  1.33,
+    1.6,   2.16,  1.9,   2.2,   2.28,  2.20,  1.93,  1.69,  1.78,  1.96,
+    2.05,  2.1,   2.66,  2.6,   0.79,  0.89,  1.10,  1.12,  1.13,  1.14,
+    1.13,  1.17,  1.2,   1.2,   1.1,   1.22,  1.23,  1.24,  1.25,  1.1,
+    1.27,  1.3,   1.5,   2.36,  1.9,   2.2,   2.20,  2.28,  2.54,  2.00,
+    1.62,  2.33,  2.02,  2.0,   2.2,   2.2,   0.7,   0.9,   1.1,   1.3,
+    1.5,   1.38,  1.36,  1.28,  1.3,   1.3,   1.3,   1.3,   1.3,   1.3,
+    1.3,   1.3,   1.3,   0.0,   0.0,   0.0,   0.0,   0.0,   0.0,   0.0,
+    0.0,   0.0,   0.0,   0.0,   0.0,   0.0,   0.0,   0.0,
+];
+
 impl Element {
+    pub fn from_atomic_number(atomic_number: u8) -> Option<Element> {
+        match atomic_number {
+            1..=118 => SYMBOLS[atomic_number as usize - 1].parse().ok(),
+            _ => None,
+        }
+    }
+
     pub fn atomic_number(&self) -> u8 {
         *self as u8
     }
+
+    pub fn symbol(&self) -> &'static str {
+        SYMBOLS[self.atomic_number() as usize - 1]
+    }
+
+    /// Standard atomic weight in g/mol.
+    pub fn atomic_mass(&self) -> f64 {
+        ATOMIC_MASSES[self.atomic_number() as usize - 1]
+    }
+
+    /// Pauling electronegativity, where one has been measured or estimated.
+    pub fn electronegativity(&self) -> Option<f64> {
+        let electronegativity = ELECTRONEGATIVITIES[self.atomic_number() as usize - 1];
+        if electronegativity > 0.0 {
+            Some(electronegativity)
+        } else {
+            None
+        }
+    }
+
+    /// The lowest common valence of the main group elements. Transition metals, lanthanides and
+    /// actinides have no meaningful default and return `None`.
+    pub fn default_valence(&self) -> Option<u8> {
+        use Element::*;
+        match self {
+            He | Ne | Ar | Kr | Xe | Rn | Og => Some(0),
+            H | Li | Na | K | Rb | Cs | Fr | F | Cl | Br | I | At | Ts => Some(1),
+            Be | Mg | Ca | Sr | Ba | Ra | O | S | Se | Te | Po | Lv => Some(2),
+            B | Al | Ga | In | Tl | Nh | N | P | As | Sb | Bi | Mc => Some(3),
+            C | Si | Ge | Sn | Pb | Fl => Some(4),
+            _ => None,
+        }
+    }
+}
+
+/// How much a formal charge changes an element's usual valence, as in ammonium or carbocations.
+pub(crate) fn charge_valence(element: Element, charge: i8) -> i16 {
+    use Element::*;
+    let charge = charge as i16;
+    match element {
+        N | P | As | O | S | Se | F | Cl | Br | I => charge,
+        B | Al => -charge,
+        C | Si | Ge => -charge.abs(),
+        _ => 0,
+    }
 }
@@ -0,0 +1,380 @@
+use crate::core::formula::charged_formula;
+use crate::core::{Element, MolecularFormula};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1, space0, space1},
+    combinator::{all_consuming, map, map_res, opt, value},
+    multi::separated_list1,
+    sequence::{delimited, separated_pair, terminated},
+    IResult,
+};
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum EquationError {
+    /// The equation could not be parsed, at the given offset.
+    Syntax(usize),
+    /// No positive coefficients balance the equation.
+    Impossible,
+    /// More than one independent set of coefficients balances the equation, as when it combines
+    /// two reactions.
+    Underdetermined,
+    /// A balanced coefficient is too large to represent.
+    Overflow,
+}
+
+impl fmt::Display for EquationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EquationError::Syntax(offset) => write!(f, "syntax error at offset {}", offset),
+            EquationError::Impossible => write!(f, "equation can't be balanced"),
+            EquationError::Underdetermined => {
+                write!(f, "equation has no unique balanced coefficients")
+            }
+            EquationError::Overflow => write!(f, "balanced coefficients are too large"),
+        }
+    }
+}
+
+impl std::error::Error for EquationError {}
+
+/// A chemical equation between species given by their formulas, each with a stoichiometric
+/// coefficient. An electron is a species with no elements and a charge of -1.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ChemicalEquation {
+    reactants: Vec<(u32, MolecularFormula)>,
+    products: Vec<(u32, MolecularFormula)>,
+    /// How each reactant and then each product is written, as parsed.
+    spellings: Vec<String>,
+}
+
+impl ChemicalEquation {
+    /// An equation with a coefficient of one for each species.
+    pub fn new(reactants: Vec<MolecularFormula>, products: Vec<MolecularFormula>) -> Self {
+        let ones = |species: Vec<MolecularFormula>| species.into_iter().map(|f| (1, f)).collect();
+        let spellings = reactants.iter().chain(&products).map(spelling).collect();
+        ChemicalEquation {
+            reactants: ones(reactants),
+            products: ones(products),
+            spellings,
+        }
+    }
+
+    pub fn reactants(&self) -> &[(u32, MolecularFormula)] {
+        &self.reactants
+    }
+
+    pub fn products(&self) -> &[(u32, MolecularFormula)] {
+        &self.products
+    }
+
+    /// Whether every element and the charge are conserved with the current coefficients.
+    pub fn is_balanced(&self) -> bool {
+        let total = |side: &[(u32, MolecularFormula)],
+                     property: &dyn Fn(&MolecularFormula) -> i64| {
+            side.iter()
+                .map(|(n, f)| *n as i64 * property(f))
+                .sum::<i64>()
+        };
+        self.properties()
+            .iter()
+            .all(|property| total(&self.reactants, property) == total(&self.products, property))
+    }
+
+    /// The equation with the smallest whole coefficients that conserve every element and the
+    /// charge, found from the nullspace of the composition matrix. Existing coefficients are
+    /// ignored.
+    pub fn balance(&self) -> Result<ChemicalEquation, EquationError> {
+        let species: Vec<&MolecularFormula> = self
+            .reactants
+            .iter()
+            .chain(&self.products)
+            .map(|(_, f)| f)
+            .collect();
+        let matrix: Vec<Vec<Fraction>> = self
+            .properties()
+            .iter()
+            .map(|property| {
+                species
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        let n = property(f);
+                        Fraction::from(if i < self.reactants.len() { n } else { -n })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut nullspace = nullspace(matrix, species.len());
+        if nullspace.len() > 1 {
+            return Err(EquationError::Underdetermined);
+        }
+        let vector = nullspace.pop().ok_or(EquationError::Impossible)?;
+
+        // Scale to the smallest whole numbers, which must all have the same sign
+        let denominators = vector.iter().fold(1, |l, x| lcm(l, x.den));
+        let mut coefficients: Vec<i128> = vector
+            .iter()
+            .map(|x| x.num * (denominators / x.den))
+            .collect();
+        let divisor = coefficients.iter().fold(0, |g, x| gcd(g, x.abs()));
+        if coefficients.iter().all(|x| *x <= 0) {
+            coefficients.iter_mut().for_each(|x| *x = -*x);
+        }
+        if coefficients.iter().any(|x| *x <= 0) {
+            return Err(EquationError::Impossible);
+        }
+        let coefficients = coefficients
+            .into_iter()
+            .map(|x| u32::try_from(x / divisor).map_err(|_| EquationError::Overflow))
+            .collect::<Result<Vec<u32>, EquationError>>()?;
+
+        let mut balanced = self.clone();
+        for ((n, _), coefficient) in balanced
+            .reactants
+            .iter_mut()
+            .chain(&mut balanced.products)
+            .zip(coefficients)
+        {
+            *n = coefficient;
+        }
+        Ok(balanced)
+    }
+
+    /// The count of each element present, and the charge, as conserved quantities.
+    fn properties(&self) -> Vec<Property> {
+        let elements: BTreeSet<Element> = self
+            .reactants
+            .iter()
+            .chain(&self.products)
+            .flat_map(|(_, f)| f.elements().map(|(el, _)| el))
+            .collect();
+        let mut properties: Vec<Property> = elements
+            .into_iter()
+            .map(|el| Box::new(move |f: &MolecularFormula| f.count(el) as i64) as Box<_>)
+            .collect();
+        properties.push(Box::new(|f: &MolecularFormula| f.charge() as i64));
+        properties
+    }
+}
+
+type Property = Box<dyn Fn(&MolecularFormula) -> i64>;
+
+/// A basis of the nullspace of a matrix with `columns` columns, from its reduced row echelon
+/// form. Each basis vector has a one for a different free column.
+fn nullspace(mut matrix: Vec<Vec<Fraction>>, columns: usize) -> Vec<Vec<Fraction>> {
+    let mut pivots = vec![];
+    let mut row = 0;
+    for column in 0..columns {
+        let pivot = match (row..matrix.len()).find(|r| !matrix[*r][column].is_zero()) {
+            Some(pivot) => pivot,
+            None => continue,
+        };
+        matrix.swap(row, pivot);
+        let scale = matrix[row][column];
+        for x in matrix[row].iter_mut() {
+            *x = *x / scale;
+        }
+        let pivot_row = matrix[row].clone();
+        for (r, other) in matrix.iter_mut().enumerate() {
+            let factor = other[column];
+            if r != row && !factor.is_zero() {
+                for (x, p) in other.iter_mut().zip(&pivot_row) {
+                    *x = *x - factor * *p;
+                }
+            }
+        }
+        pivots.push(column);
+        row += 1;
+    }
+
+    (0..columns)
+        .filter(|c| !pivots.contains(c))
+        .map(|free| {
+            let mut vector = vec![Fraction::from(0); columns];
+            vector[free] = Fraction::from(1);
+            for (row, pivot) in pivots.iter().enumerate() {
+                vector[*pivot] = -matrix[row][free];
+            }
+            vector
+        })
+        .collect()
+}
+
+/// An exact rational number in lowest terms with a positive denominator.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Fraction {
+    num: i128,
+    den: i128,
+}
+
+impl Fraction {
+    fn new(num: i128, den: i128) -> Self {
+        let divisor = gcd(num.abs(), den.abs()).max(1) * den.signum();
+        Fraction {
+            num: num / divisor,
+            den: den / divisor,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+}
+
+impl From<i64> for Fraction {
+    fn from(n: i64) -> Self {
+        Fraction::new(n as i128, 1)
+    }
+}
+
+impl std::ops::Sub for Fraction {
+    type Output = Fraction;
+
+    fn sub(self, other: Fraction) -> Fraction {
+        Fraction::new(
+            self.num * other.den - other.num * self.den,
+            self.den * other.den,
+        )
+    }
+}
+
+impl std::ops::Mul for Fraction {
+    type Output = Fraction;
+
+    fn mul(self, other: Fraction) -> Fraction {
+        Fraction::new(self.num * other.num, self.den * other.den)
+    }
+}
+
+impl std::ops::Div for Fraction {
+    type Output = Fraction;
+
+    fn div(self, other: Fraction) -> Fraction {
+        Fraction::new(self.num * other.den, self.den * other.num)
+    }
+}
+
+impl std::ops::Neg for Fraction {
+    type Output = Fraction;
+
+    fn neg(self) -> Fraction {
+        Fraction::new(-self.num, self.den)
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lcm(a: i128, b: i128) -> i128 {
+    a / gcd(a, b) * b
+}
+
+/// The formula in Hill order, or `e-` for an electron.
+fn spelling(formula: &MolecularFormula) -> String {
+    if formula.is_empty() && formula.charge() == -1 {
+        "e-".to_string()
+    } else {
+        formula.to_string()
+    }
+}
+
+/// Writes each species as it was parsed.
+impl fmt::Display for ChemicalEquation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (reactants, products) = self.spellings.split_at(self.reactants.len());
+        let side = |species: &[(u32, MolecularFormula)], spellings: &[String]| {
+            species
+                .iter()
+                .zip(spellings)
+                .map(|((n, _), spelling)| match n {
+                    1 => spelling.clone(),
+                    n => format!("{}{}", n, spelling),
+                })
+                .collect::<Vec<String>>()
+                .join(" + ")
+        };
+        write!(
+            f,
+            "{} -> {}",
+            side(&self.reactants, reactants),
+            side(&self.products, products)
+        )
+    }
+}
+
+type Species = ((u32, MolecularFormula), String);
+
+fn species(input: &str) -> IResult<&str, Species> {
+    let (input, n) = map(
+        opt(terminated(
+            map_res(digit1, |num_str: &str| num_str.parse::<u32>()),
+            space0,
+        )),
+        |n| n.unwrap_or(1),
+    )(input)?;
+    let (rest, formula) = alt((
+        value(
+            MolecularFormula::new().with_charge(-1),
+            alt((tag("e-"), tag("e^-"))),
+        ),
+        charged_formula,
+    ))(input)?;
+    let spelling = input[..input.len() - rest.len()].to_string();
+    Ok((rest, ((n, formula), spelling)))
+}
+
+/// Species are separated by a `+` with space on either side, to tell it apart from a charge.
+fn side(input: &str) -> IResult<&str, Vec<Species>> {
+    separated_list1(delimited(space1, char('+'), space1), species)(input)
+}
+
+fn arrow(input: &str) -> IResult<&str, &str> {
+    alt((
+        tag("<=>"),
+        tag("<->"),
+        tag("->"),
+        tag("=>"),
+        tag("→"),
+        tag("⇌"),
+        tag("="),
+    ))(input)
+}
+
+/// Parses an equation such as `2H2 + O2 -> 2H2O` or `Fe^3+ + e- = Fe^2+`. Coefficients are
+/// optional, and reversible arrows are accepted but not distinguished.
+impl FromStr for ChemicalEquation {
+    type Err = EquationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let start = s.len() - s.trim_start().len();
+        all_consuming(separated_pair(side, delimited(space0, arrow, space0), side))(trimmed)
+            .map(|(_, (reactants, products))| {
+                let (reactants, mut spellings): (Vec<_>, Vec<_>) = reactants.into_iter().unzip();
+                let (products, product_spellings): (Vec<_>, Vec<_>) = products.into_iter().unzip();
+                spellings.extend(product_spellings);
+                ChemicalEquation {
+                    reactants,
+                    products,
+                    spellings,
+                }
+            })
+            .map_err(|e| match e {
+                nom::Err::Error(e) | nom::Err::Failure(e) => {
+                    EquationError::Syntax(start + trimmed.len() - e.input.len())
+                }
+                nom::Err::Incomplete(_) => EquationError::Syntax(s.len()),
+            })
+    }
+}
@@ -0,0 +1,258 @@
+use crate::core::{Element, Molecule};
+use nom::{
+    branch::alt,
+    character::complete::{char, digit1, satisfy},
+    combinator::{all_consuming, map, map_res, opt, recognize, value},
+    multi::many1,
+    sequence::{delimited, pair, preceded},
+    IResult,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The number of atoms of each element in a species, along with its net charge.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct MolecularFormula {
+    counts: BTreeMap<Element, u32>,
+    charge: i32,
+}
+
+impl MolecularFormula {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_charge(mut self, charge: i32) -> Self {
+        self.charge = charge;
+        self
+    }
+
+    pub fn add(&mut self, element: Element, count: u32) {
+        if count > 0 {
+            *self.counts.entry(element).or_insert(0) += count;
+        }
+    }
+
+    pub fn count(&self, element: Element) -> u32 {
+        self.counts.get(&element).copied().unwrap_or(0)
+    }
+
+    pub fn charge(&self) -> i32 {
+        self.charge
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Iterates over the elements present in order of atomic number.
+    pub fn elements(&self) -> impl Iterator<Item = (Element, u32)> + '_ {
+        self.counts.iter().map(|(el, count)| (*el, *count))
+    }
+
+    /// Average molecular weight in g/mol.
+    pub fn molecular_weight(&self) -> f64 {
+        self.elements()
+            .map(|(el, count)| el.atomic_mass() * count as f64)
+            .sum()
+    }
+
+    /// Degree of unsaturation, i.e. the number of rings plus pi bonds, computed as
+    /// `1 + Σ nᵢ(vᵢ - 2) / 2` over the default valence of each element. Returns `None` if an
+    /// element has no default valence. The charge is ignored, so ions may give half-integers.
+    pub fn double_bond_equivalents(&self) -> Option<f64> {
+        let mut sum = 0i64;
+        for (el, count) in self.elements() {
+            sum += (el.default_valence()? as i64 - 2) * count as i64;
+        }
+        Some(1.0 + sum as f64 / 2.0)
+    }
+
+    /// The percentage by mass of each element, in order of atomic number.
+    pub fn mass_percentages(&self) -> Vec<(Element, f64)> {
+        let total = self.molecular_weight();
+        self.elements()
+            .map(|(el, count)| (el, 100.0 * el.atomic_mass() * count as f64 / total))
+            .collect()
+    }
+
+    /// The formula with all counts divided by their greatest common divisor.
+    pub fn empirical_formula(&self) -> Self {
+        let divisor = self.counts.values().fold(0, |a, b| gcd(a, *b)).max(1);
+        MolecularFormula {
+            counts: self
+                .elements()
+                .map(|(el, count)| (el, count / divisor))
+                .collect(),
+            charge: self.charge,
+        }
+    }
+
+    /// Finds the empirical formula that best explains an elemental analysis. Each element's
+    /// percentage is converted to a mole ratio relative to the least abundant element, and the
+    /// ratios are scaled by the smallest multiplier (up to 12) that brings every one within
+    /// `tolerance` of a whole number.
+    pub fn from_mass_percentages(percentages: &[(Element, f64)], tolerance: f64) -> Option<Self> {
+        const MAX_MULTIPLIER: u32 = 12;
+
+        let moles: Vec<(Element, f64)> = percentages
+            .iter()
+            .filter(|(_, pct)| *pct > 0.0)
+            .map(|(el, pct)| (*el, pct / el.atomic_mass()))
+            .collect();
+        let least = moles.iter().map(|(_, n)| *n).fold(f64::INFINITY, f64::min);
+        if !least.is_finite() {
+            return None;
+        }
+
+        (1..=MAX_MULTIPLIER).find_map(|multiplier| {
+            let mut formula = MolecularFormula::new();
+            for (el, n) in &moles {
+                let ratio = n / least * multiplier as f64;
+                if (ratio - ratio.round()).abs() > tolerance {
+                    return None;
+                }
+                formula.add(*el, ratio.round() as u32);
+            }
+            Some(formula)
+        })
+    }
+}
+
+impl Molecule {
+    /// The molecule's formula, counting implicit hydrogens, with the sum of its formal charges.
+    pub fn formula(&self) -> MolecularFormula {
+        let mut formula = MolecularFormula::new();
+        let mut charge = 0;
+        for atom in self.atoms() {
+            let a = self.atom(atom);
+            formula.add(a.element, 1);
+            formula.add(Element::H, a.hydrogens as u32);
+            charge += a.formal_charge as i32;
+        }
+        formula.with_charge(charge)
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Writes the formula in Hill order: carbon, then hydrogen, then everything else alphabetically.
+/// Without carbon, all elements are alphabetical.
+impl fmt::Display for MolecularFormula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut elements: Vec<(Element, u32)> = self.elements().collect();
+        let has_carbon = self.count(Element::C) > 0;
+        elements.sort_by_key(|(el, _)| match el {
+            Element::C if has_carbon => (0, ""),
+            Element::H if has_carbon => (1, ""),
+            el => (2, el.symbol()),
+        });
+
+        for (el, count) in elements {
+            match count {
+                1 => write!(f, "{}", el.symbol())?,
+                _ => write!(f, "{}{}", el.symbol(), count)?,
+            }
+        }
+
+        match self.charge {
+            0 => Ok(()),
+            1 => write!(f, "+"),
+            -1 => write!(f, "-"),
+            c if c > 0 => write!(f, "+{}", c),
+            c => write!(f, "-{}", -c),
+        }
+    }
+}
+
+fn count(input: &str) -> IResult<&str, u32> {
+    map(
+        opt(map_res(digit1, |num_str: &str| num_str.parse::<u32>())),
+        |n| n.unwrap_or(1),
+    )(input)
+}
+
+fn element(input: &str) -> IResult<&str, Element> {
+    map_res(
+        recognize(pair(
+            satisfy(|c| c.is_ascii_uppercase()),
+            opt(satisfy(|c| c.is_ascii_lowercase())),
+        )),
+        |el: &str| el.parse::<Element>(),
+    )(input)
+}
+
+fn group(input: &str) -> IResult<&str, MolecularFormula> {
+    alt((
+        map(pair(element, count), |(el, n)| {
+            let mut formula = MolecularFormula::new();
+            formula.add(el, n);
+            formula
+        }),
+        map(
+            pair(delimited(char('('), formula, char(')')), count),
+            |(inner, n)| {
+                let mut formula = MolecularFormula::new();
+                for (el, count) in inner.elements() {
+                    formula.add(el, count * n);
+                }
+                formula
+            },
+        ),
+    ))(input)
+}
+
+fn sign(input: &str) -> IResult<&str, i32> {
+    alt((value(1, char('+')), value(-1, char('-'))))(input)
+}
+
+/// A charge such as `+2`, `++` or `^2+`. Digits before the sign need the caret, since they would
+/// otherwise be read as a count.
+fn charge(input: &str) -> IResult<&str, i32> {
+    let number = |input| map_res(digit1, |num_str: &str| num_str.parse::<i32>())(input);
+    preceded(
+        opt(char('^')),
+        alt((
+            map(pair(sign, number), |(sign, n)| sign * n),
+            map(pair(number, sign), |(n, sign)| sign * n),
+            map(many1(char('+')), |signs| signs.len() as i32),
+            map(many1(char('-')), |signs| -(signs.len() as i32)),
+        )),
+    )(input)
+}
+
+fn formula(input: &str) -> IResult<&str, MolecularFormula> {
+    map(many1(group), |groups| {
+        let mut formula = MolecularFormula::new();
+        for (el, count) in groups.iter().flat_map(|g| g.elements()) {
+            formula.add(el, count);
+        }
+        formula
+    })(input)
+}
+
+pub(crate) fn charged_formula(input: &str) -> IResult<&str, MolecularFormula> {
+    map(pair(formula, opt(charge)), |(formula, charge)| {
+        formula.with_charge(charge.unwrap_or(0))
+    })(input)
+}
+
+/// Parses a condensed formula such as `C6H12O6` or `Ca(OH)2`, optionally followed by a charge
+/// such as `SO4-2`, `NH4+` or `Fe^3+`.
+impl FromStr for MolecularFormula {
+    // TODO: Proper error
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        all_consuming(charged_formula)(s)
+            .map(|(_, formula)| formula)
+            .map_err(|_: nom::Err<nom::error::Error<&str>>| ())
+    }
+}
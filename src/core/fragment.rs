@@ -0,0 +1,111 @@
+use crate::core::{AtomIndex, Element, Molecule};
+use std::collections::HashMap;
+
+impl Molecule {
+    pub fn heavy_atom_count(&self) -> usize {
+        self.atoms()
+            .filter(|a| self.atom(*a).element != Element::H)
+            .count()
+    }
+
+    /// The atoms of each connected component. Components are ordered by their lowest atom index,
+    /// and the atoms within each component are in ascending order.
+    pub fn components(&self) -> Vec<Vec<AtomIndex>> {
+        let mut visited = vec![false; self.atom_count()];
+        let mut components = vec![];
+        for start in self.atoms() {
+            if visited[start.index()] {
+                continue;
+            }
+            visited[start.index()] = true;
+            let mut component = vec![start];
+            let mut stack = vec![start];
+            while let Some(atom) = stack.pop() {
+                for neighbor in self.neighbors(atom) {
+                    if !visited[neighbor.index()] {
+                        visited[neighbor.index()] = true;
+                        component.push(neighbor);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            component.sort();
+            components.push(component);
+        }
+        components
+    }
+
+    /// A new molecule made of the given atoms and the bonds between them. Atom `i` of the result
+    /// corresponds to `atoms[i]` in this molecule.
+    pub fn subgraph(&self, atoms: &[AtomIndex]) -> Molecule {
+        let mut mol = Molecule::new();
+        let mapping: HashMap<AtomIndex, AtomIndex> = atoms
+            .iter()
+            .map(|a| (*a, mol.add_atom(*self.atom(*a))))
+            .collect();
+        for (a, b, bond) in self.bonds() {
+            if let (Some(a), Some(b)) = (mapping.get(&a), mapping.get(&b)) {
+                mol.add_bond(*a, *b, bond).unwrap();
+            }
+        }
+
+        // Chirality is relative to neighbour order, which changes if the atoms were reordered
+        for (old, new) in &mapping {
+            if let Some(chirality) = self.atom(*old).chirality {
+                let mut neighbors: Vec<AtomIndex> = self
+                    .neighbors(*old)
+                    .filter(|n| mapping.contains_key(n))
+                    .collect();
+                neighbors.sort();
+                let mut order: Vec<usize> = (0..neighbors.len()).collect();
+                order.sort_by_key(|i| mapping[&neighbors[*i]]);
+                mol.atom_mut(*new).chirality = Some(chirality.permuted(&order));
+            }
+        }
+        mol
+    }
+
+    /// Splits the molecule into its connected components, in the order of [`Molecule::components`].
+    pub fn fragments(&self) -> Vec<Molecule> {
+        self.fragments_with_atoms()
+            .into_iter()
+            .map(|(_, fragment)| fragment)
+            .collect()
+    }
+
+    /// The fragments of [`Molecule::fragments`], each with the atoms of this molecule it was made
+    /// from. Atom `i` of a fragment is the `i`th of its atoms.
+    pub fn fragments_with_atoms(&self) -> Vec<(Vec<AtomIndex>, Molecule)> {
+        self.components()
+            .into_iter()
+            .map(|atoms| {
+                let fragment = self.subgraph(&atoms);
+                (atoms, fragment)
+            })
+            .collect()
+    }
+
+    /// The atoms of the fragment with the most heavy atoms. Ties go to the fragment with the most
+    /// atoms overall, then to the one containing the lowest atom index.
+    pub fn largest_component(&self) -> Option<Vec<AtomIndex>> {
+        let heavy = |atoms: &Vec<AtomIndex>| {
+            atoms
+                .iter()
+                .filter(|a| self.atom(**a).element != Element::H)
+                .count()
+        };
+        self.components()
+            .into_iter()
+            .enumerate()
+            .max_by_key(|(i, atoms)| (heavy(atoms), atoms.len(), std::cmp::Reverse(*i)))
+            .map(|(_, atoms)| atoms)
+    }
+
+    /// The fragment chosen by [`Molecule::largest_component`], or an empty molecule.
+    pub fn largest_fragment(&self) -> Molecule {
+        match self.largest_component() {
+            Some(atoms) => self.subgraph(&atoms),
+            None => Molecule::new(),
+        }
+    }
+}
@@ -1,5 +1,689 @@
+mod canon;
+mod distance;
 mod element;
+mod equation;
+mod formula;
+mod fragment;
 mod molecule;
+mod oxidation;
+mod reaction;
+mod ring;
+mod stoichiometry;
 
 pub use element::*;
+pub use equation::*;
+pub use formula::*;
 pub use molecule::*;
+pub use oxidation::*;
+pub use reaction::*;
+pub use ring::*;
+pub use stoichiometry::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::core::*;
+
+    mod formula {
+        use super::*;
+        use crate::daylight::molecule_from_smiles;
+
+        fn formula(s: &str) -> MolecularFormula {
+            s.parse().expect("Invalid formula")
+        }
+
+        fn assert_close(a: f64, b: f64) {
+            assert!((a - b).abs() < 1e-2, "{} != {}", a, b);
+        }
+
+        #[test]
+        fn can_parse_formula() {
+            let glucose = formula("C6H12O6");
+            assert_eq!(glucose.count(Element::C), 6);
+            assert_eq!(glucose.count(Element::H), 12);
+            assert_eq!(glucose.count(Element::O), 6);
+            assert_eq!(glucose.count(Element::N), 0);
+
+            let hydroxide = formula("Ca(OH)2");
+            assert_eq!(hydroxide.count(Element::Ca), 1);
+            assert_eq!(hydroxide.count(Element::O), 2);
+            assert_eq!(hydroxide.count(Element::H), 2);
+
+            assert_eq!(formula("CH3COOH"), formula("C2H4O2"));
+
+            // Charges follow the formula
+            assert_eq!(formula("SO4-2"), formula("SO4").with_charge(-2));
+            assert_eq!(formula("NH4+"), formula("NH4").with_charge(1));
+            assert_eq!(formula("Fe^3+"), formula("Fe").with_charge(3));
+            assert_eq!(formula("Fe^+3"), formula("Fe").with_charge(3));
+            assert_eq!(formula("PO4---"), formula("PO4").with_charge(-3));
+            // Without a caret, digits before the sign are a count
+            assert_eq!(formula("Fe3+"), formula("Fe3").with_charge(1));
+            assert_eq!(formula("SO4-2").to_string().parse(), Ok(formula("SO4-2")));
+            assert_eq!("Fe+".parse::<MolecularFormula>().map(|f| f.charge()), Ok(1));
+            assert_eq!("Fe+-".parse::<MolecularFormula>(), Err(()));
+            assert_eq!("C6H12O6x".parse::<MolecularFormula>(), Err(()));
+            assert_eq!("Xy2".parse::<MolecularFormula>(), Err(()));
+        }
+
+        #[test]
+        fn formulas_are_written_in_hill_order() {
+            assert_eq!(formula("OHCH3").to_string(), "CH4O");
+            assert_eq!(formula("H2SO4").to_string(), "H2O4S");
+            assert_eq!(formula("NaCl").to_string(), "ClNa");
+            assert_eq!(formula("NH4").with_charge(1).to_string(), "H4N+");
+            assert_eq!(formula("SO4").with_charge(-2).to_string(), "O4S-2");
+        }
+
+        #[test]
+        fn formulas_have_molecular_weight() {
+            assert_close(formula("H2O").molecular_weight(), 18.015);
+            assert_close(formula("C6H12O6").molecular_weight(), 180.156);
+        }
+
+        #[test]
+        fn formulas_have_double_bond_equivalents() {
+            assert_eq!(formula("C6H14").double_bond_equivalents(), Some(0.0));
+            assert_eq!(formula("C6H6").double_bond_equivalents(), Some(4.0));
+            assert_eq!(formula("C5H5N").double_bond_equivalents(), Some(4.0));
+            assert_eq!(formula("C2H3Cl").double_bond_equivalents(), Some(1.0));
+            assert_eq!(formula("C8H10N4O2").double_bond_equivalents(), Some(6.0));
+            assert_eq!(formula("FeCl3").double_bond_equivalents(), None);
+        }
+
+        #[test]
+        fn formulas_have_mass_percentages() {
+            let percentages = formula("H2O").mass_percentages();
+            assert_eq!(percentages.len(), 2);
+            assert_eq!(percentages[0].0, Element::H);
+            assert_close(percentages[0].1, 11.19);
+            assert_eq!(percentages[1].0, Element::O);
+            assert_close(percentages[1].1, 88.81);
+        }
+
+        #[test]
+        fn formulas_have_empirical_formula() {
+            assert_eq!(formula("C6H12O6").empirical_formula(), formula("CH2O"));
+            assert_eq!(formula("C2H6O").empirical_formula(), formula("C2H6O"));
+        }
+
+        #[test]
+        fn empirical_formula_from_mass_percentages() {
+            let find = |pcts: &[(Element, f64)]| MolecularFormula::from_mass_percentages(pcts, 0.1);
+
+            // Glucose
+            assert_eq!(
+                find(&[(Element::C, 40.00), (Element::H, 6.71), (Element::O, 53.29)]),
+                Some(formula("CH2O"))
+            );
+            // Fe2O3 needs a multiplier of two
+            assert_eq!(
+                find(&[(Element::Fe, 69.94), (Element::O, 30.06)]),
+                Some(formula("Fe2O3"))
+            );
+            // Round trip through the percentages of caffeine
+            assert_eq!(
+                find(&formula("C8H10N4O2").mass_percentages()),
+                Some(formula("C4H5N2O"))
+            );
+            assert_eq!(find(&[]), None);
+        }
+
+        #[test]
+        fn molecules_have_formulas() {
+            let caffeine = molecule_from_smiles("Cn1cnc2c1c(=O)n(C)c(=O)n2C").unwrap();
+            assert_eq!(caffeine.formula(), formula("C8H10N4O2"));
+            let acetate = molecule_from_smiles("CC(=O)[O-]").unwrap();
+            assert_eq!(acetate.formula(), formula("C2H3O2").with_charge(-1));
+            assert_close(caffeine.formula().molecular_weight(), 194.19);
+        }
+    }
+
+    mod equation {
+        use super::*;
+
+        fn equation(s: &str) -> ChemicalEquation {
+            s.parse().expect("Invalid equation")
+        }
+
+        fn balanced(s: &str) -> String {
+            equation(s).balance().unwrap().to_string()
+        }
+
+        #[test]
+        fn can_parse_equations() {
+            let eq = equation("2H2 + O2 -> 2H2O");
+            assert_eq!(eq.reactants().len(), 2);
+            assert_eq!(eq.reactants()[0].0, 2);
+            assert_eq!(eq.products()[0], (2, "H2O".parse().unwrap()));
+            assert!(eq.is_balanced());
+            assert_eq!(eq.to_string(), "2H2 + O2 -> 2H2O");
+
+            // Charges, electrons and other arrows
+            let eq = equation(" Fe+3 + e- <=> Fe+2 ");
+            assert_eq!(eq.reactants()[1].1, MolecularFormula::new().with_charge(-1));
+            assert!(eq.is_balanced());
+            assert_eq!(eq.to_string(), "Fe+3 + e- -> Fe+2");
+            assert_eq!(equation("Na+ + Cl- = NaCl").reactants().len(), 2);
+            assert!(!equation("H2 + O2 → H2O").is_balanced());
+
+            assert_eq!(
+                "H2 + O2".parse::<ChemicalEquation>(),
+                Err(EquationError::Syntax(7))
+            );
+            assert!("H2 + -> H2O".parse::<ChemicalEquation>().is_err());
+            assert!("H2+O2 -> H2O".parse::<ChemicalEquation>().is_err());
+        }
+
+        #[test]
+        fn can_balance_equations() {
+            assert_eq!(balanced("Fe + O2 -> Fe2O3"), "4Fe + 3O2 -> 2Fe2O3");
+            assert_eq!(balanced("H2 + O2 -> H2O"), "2H2 + O2 -> 2H2O");
+            assert_eq!(
+                balanced("C3H8 + O2 -> CO2 + H2O"),
+                "C3H8 + 5O2 -> 3CO2 + 4H2O"
+            );
+            assert_eq!(
+                balanced("KMnO4 + HCl -> KCl + MnCl2 + H2O + Cl2"),
+                "2KMnO4 + 16HCl -> 2KCl + 2MnCl2 + 8H2O + 5Cl2"
+            );
+            // Existing coefficients are replaced
+            assert_eq!(balanced("3H2 + 7O2 -> H2O"), "2H2 + O2 -> 2H2O");
+            // Charge is balanced too
+            assert_eq!(
+                balanced("MnO4- + H+ + e- -> Mn+2 + H2O"),
+                "MnO4- + 8H+ + 5e- -> Mn+2 + 4H2O"
+            );
+            assert_eq!(balanced("Cu + Ag+ -> Cu+2 + Ag"), "Cu + 2Ag+ -> Cu+2 + 2Ag");
+        }
+
+        #[test]
+        fn can_reject_unbalanceable_equations() {
+            let balance = |s: &str| equation(s).balance();
+            assert_eq!(balance("H2 -> O2"), Err(EquationError::Impossible));
+            assert_eq!(balance("NaCl -> Na"), Err(EquationError::Impossible));
+            // Nitrogen can't take part
+            assert_eq!(
+                balance("H2 + O2 + N2 -> H2O"),
+                Err(EquationError::Impossible)
+            );
+            // Two independent reactions
+            assert_eq!(
+                balance("H2 + O2 -> H2O + H2O2"),
+                Err(EquationError::Underdetermined)
+            );
+            assert_eq!(
+                balance("C + O2 -> CO + CO2"),
+                Err(EquationError::Underdetermined)
+            );
+            // Coefficients that don't fit
+            assert_eq!(
+                balance("H65537 + O65539 -> H65536O65538"),
+                Err(EquationError::Overflow)
+            );
+        }
+    }
+
+    mod stoichiometry {
+        use super::*;
+
+        fn assert_close(a: f64, b: f64) {
+            assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+        }
+
+        #[test]
+        fn can_find_limiting_reagent() {
+            // Esterification of acetic acid with ethanol
+            let equation: ChemicalEquation = "C2H4O2 + C2H6O -> C4H8O2 + H2O".parse().unwrap();
+            let report = Stoichiometry::new(&equation)
+                .reactant(0, Quantity::Mass(Mass::grams(6.0052)))
+                .reactant(
+                    1,
+                    Quantity::Liquid {
+                        volume: Volume::milliliters(11.68),
+                        density: Density::grams_per_milliliter(0.789),
+                    },
+                )
+                .calculate()
+                .unwrap();
+            assert_eq!(report.limiting_reagent, Some(0));
+            assert_close(report.extent.as_moles(), 0.1);
+            assert_close(report.reactants[1].equivalents, 2.0);
+            assert_close(report.reactants[0].equivalents, 1.0);
+            assert_close(report.reactants[1].required.amount.as_moles(), 0.1);
+            assert_close(report.reactants[1].given.unwrap().mass.as_grams(), 9.215);
+            // Ethyl acetate, 88.11 g/mol
+            assert_close(report.products[0].mass.as_grams(), 8.811);
+            assert_close(report.products[1].amount.as_moles(), 0.1);
+        }
+
+        #[test]
+        fn can_use_coefficients_and_solutions() {
+            let equation: ChemicalEquation = "2NaOH + H2SO4 -> Na2SO4 + 2H2O".parse().unwrap();
+            let report = Stoichiometry::new(&equation)
+                .reactant(
+                    0,
+                    Quantity::Solution {
+                        volume: Volume::milliliters(50.0),
+                        molarity: Molarity::molar(1.0),
+                    },
+                )
+                .reactant(1, Quantity::Amount(Amount::millimoles(30.0)))
+                .calculate()
+                .unwrap();
+            // 50 mmol of base only neutralise 25 mmol of acid
+            assert_eq!(report.limiting_reagent, Some(0));
+            assert_close(report.extent.as_millimoles(), 25.0);
+            assert_close(report.reactants[1].equivalents, 0.6);
+            assert_close(report.products[1].amount.as_millimoles(), 50.0);
+        }
+
+        #[test]
+        fn can_plan_from_product() {
+            let equation: ChemicalEquation = "2H2 + O2 -> 2H2O".parse().unwrap();
+            let report = Stoichiometry::new(&equation)
+                .product(0, Quantity::Mass(Mass::kilograms(0.018015)))
+                .calculate()
+                .unwrap();
+            assert_eq!(report.limiting_reagent, None);
+            assert_close(report.reactants[0].required.amount.as_moles(), 1.0);
+            assert_close(report.reactants[1].required.mass.as_grams(), 15.999);
+            assert_close(report.reactants[1].equivalents, 0.5);
+            assert!(report.reactants[0].given.is_none());
+
+            assert_eq!(
+                Stoichiometry::new(&equation).calculate(),
+                Err(StoichiometryError::NoQuantities)
+            );
+            let unbalanced: ChemicalEquation = "H2 + O2 -> H2O".parse().unwrap();
+            assert_eq!(
+                Stoichiometry::new(&unbalanced)
+                    .reactant(0, Quantity::Mass(Mass::grams(1.0)))
+                    .calculate(),
+                Err(StoichiometryError::Unbalanced)
+            );
+            assert_eq!(
+                Stoichiometry::new(&equation)
+                    .reactant(0, Quantity::Mass(Mass::grams(1.0)))
+                    .product(1, Quantity::Mass(Mass::grams(1.0)))
+                    .calculate(),
+                Err(StoichiometryError::NoSuchProduct(1))
+            );
+        }
+
+        #[test]
+        fn can_display_quantities() {
+            assert_eq!(Mass::milligrams(250.0).to_string(), "0.25 g");
+            assert_eq!(Amount::millimoles(5.0).to_string(), "0.005 mol");
+            assert_eq!(Volume::milliliters(500.0).to_string(), "0.5 L");
+            assert_eq!(
+                Density::grams_per_milliliter(0.789).to_string(),
+                "0.789 g/mL"
+            );
+            assert_eq!(Molarity::millimolar(100.0).to_string(), "0.1 M");
+        }
+    }
+
+    mod oxidation {
+        use super::*;
+        use crate::daylight::{molecule_from_smiles, reaction_from_smiles};
+
+        fn states(s: &str) -> Vec<i8> {
+            molecule_from_smiles(s)
+                .expect("Invalid SMILES")
+                .oxidation_states()
+        }
+
+        #[test]
+        fn can_assign_oxidation_states() {
+            assert_eq!(states("C"), vec![-4]);
+            assert_eq!(states("O=C=O"), vec![-2, 4, -2]);
+            // Methanol, formaldehyde and formic acid
+            assert_eq!(states("CO"), vec![-2, -2]);
+            assert_eq!(states("C=O"), vec![0, -2]);
+            assert_eq!(states("OC=O"), vec![-2, 2, -2]);
+            // Charges are kept, as in ammonium and nitro groups
+            assert_eq!(states("[NH4+]"), vec![-3]);
+            assert_eq!(states("C[N+](=O)[O-]"), vec![-2, 3, -2, -2]);
+            assert_eq!(states("[Fe+3]"), vec![3]);
+            // Hydrogens bonded to less electronegative atoms are hydrides
+            assert_eq!(states("[Na+].[BH4-]"), vec![1, 3]);
+            assert_eq!(states("[H][H]"), vec![0, 0]);
+        }
+
+        #[test]
+        fn can_assign_aromatic_oxidation_states() {
+            // The nitrogen of pyridine has a double bond to carbon but pyrrole's doesn't
+            assert_eq!(states("n1ccccc1")[0], -3);
+            assert_eq!(states("[nH]1cccc1")[0], -3);
+            assert_eq!(states("c1ccccc1"), vec![-1; 6]);
+            // With their six hydrogens, the states sum to the charge
+            let pyridinium = states("c1cc[nH+]cc1");
+            assert_eq!(pyridinium[3], -3);
+            assert_eq!(pyridinium.iter().sum::<i8>() + 6, 1);
+            assert_eq!(states("c1ccoc1")[3], -2);
+
+            // Pairing fullerene's atoms is quick even when a leftover atom leaves some unpaired
+            let fullerene = states(
+                "c12c3c4c5c1c1c6c7c2c2c8c3c3c9c4c4c%10c5c5c1c1c6c6c%11c7c2c2c7c8c3c3c8c9c4c4c9\
+                 c%10c5c5c1c1c6c6c%11c2c2c7c3c3c8c4c4c9c5c1c1c6c2c3c41.c1cccc1",
+            );
+            assert_eq!(fullerene[..60], [0; 60]);
+            assert_eq!(fullerene[60..], [-1; 5]);
+        }
+
+        #[test]
+        fn can_summarize_redox() {
+            // Oxidation of ethanol to acetaldehyde
+            let reaction =
+                reaction_from_smiles("[CH3:1][CH2:2][OH:3]>>[CH3:1][CH:2]=[O:3]").unwrap();
+            let redox = reaction.redox();
+            assert!(redox.is_redox());
+            assert_eq!(
+                redox.oxidized,
+                vec![OxidationChange {
+                    map: 2,
+                    element: Element::C,
+                    before: -1,
+                    after: 1,
+                }]
+            );
+            assert!(redox.reduced.is_empty());
+            assert_eq!(redox.electrons_transferred(), 2);
+
+            // Reduction of a nitro group, with the hydrogen source unmapped
+            let reaction = reaction_from_smiles(
+                "[cH:1]1[cH:2][cH:3][cH:4][cH:5][c:6]1[N+:7](=[O:8])[O-:9]>>\
+                 [cH:1]1[cH:2][cH:3][cH:4][cH:5][c:6]1[NH2:7].[OH2:8].[OH2:9]",
+            )
+            .unwrap();
+            let redox = reaction.redox();
+            assert!(redox.oxidized.is_empty());
+            assert_eq!(redox.reduced.len(), 1);
+            assert_eq!((redox.reduced[0].before, redox.reduced[0].after), (3, -3));
+
+            // Amide formation isn't a redox reaction
+            let reaction =
+                reaction_from_smiles("[CH3:1][C:2](=[O:3])O.[NH3:4]>>[CH3:1][C:2](=[O:3])[NH2:4]")
+                    .unwrap();
+            assert!(!reaction.redox().is_redox());
+        }
+    }
+
+    mod ring {
+        use super::*;
+
+        /// An all carbon skeleton from a list of bonds
+        fn skeleton(n: usize, bonds: &[(usize, usize)]) -> Molecule {
+            let mut mol = Molecule::new();
+            let atoms: Vec<AtomIndex> = (0..n)
+                .map(|_| mol.add_atom(Atom::new(Element::C)))
+                .collect();
+            for (a, b) in bonds {
+                mol.add_bond(atoms[*a], atoms[*b], Bond::Single).unwrap();
+            }
+            mol
+        }
+
+        fn cycle(offset: usize, n: usize) -> Vec<(usize, usize)> {
+            (0..n).map(|i| (offset + i, offset + (i + 1) % n)).collect()
+        }
+
+        fn atom(i: usize) -> AtomIndex {
+            AtomIndex::new(i)
+        }
+
+        #[test]
+        fn chains_have_no_rings() {
+            let rings = skeleton(6, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)]).rings();
+            assert_eq!(rings.num_rings(), 0);
+            assert!(rings.ring_systems().is_empty());
+            assert!(!rings.is_in_ring(atom(2)));
+        }
+
+        #[test]
+        fn can_perceive_single_ring() {
+            // Methylcyclohexane
+            let mut bonds = cycle(0, 6);
+            bonds.push((0, 6));
+            let rings = skeleton(7, &bonds).rings();
+
+            assert_eq!(rings.num_rings(), 1);
+            assert_eq!(rings.sssr()[0].len(), 6);
+            assert!(rings.is_in_ring(atom(0)));
+            assert!(!rings.is_in_ring(atom(6)));
+            assert_eq!(rings.ring_sizes(atom(3)), vec![6]);
+            assert!(rings.is_in_ring_of_size(atom(3), 6));
+            assert!(!rings.is_in_ring_of_size(atom(3), 5));
+            assert!(rings.bond_in_ring(atom(1), atom(0)));
+            assert!(!rings.bond_in_ring(atom(0), atom(6)));
+            assert!(rings.ring_systems()[0].is_isolated());
+        }
+
+        #[test]
+        fn can_perceive_fused_rings() {
+            // Naphthalene skeleton, fused over the 0-5 bond
+            let mut bonds = cycle(0, 6);
+            bonds.extend(&[(5, 6), (6, 7), (7, 8), (8, 9), (9, 0)]);
+            let rings = skeleton(10, &bonds).rings();
+
+            assert_eq!(rings.num_rings(), 2);
+            assert_eq!(rings.ring_sizes(atom(0)), vec![6, 6]);
+            assert_eq!(rings.ring_sizes(atom(7)), vec![6]);
+            assert!(rings.bond_in_ring(atom(0), atom(5)));
+
+            let systems = rings.ring_systems();
+            assert_eq!(systems.len(), 1);
+            assert_eq!(systems[0].atoms().len(), 10);
+            assert!(systems[0].is_fused());
+            assert!(!systems[0].is_spiro());
+            assert!(!systems[0].is_bridged());
+            // The 10 membered envelope is the sum of two smaller rings
+            assert_eq!(rings.relevant_cycles().len(), 2);
+        }
+
+        #[test]
+        fn can_perceive_spiro_rings() {
+            // Spiro[4.4]nonane
+            let mut bonds = cycle(0, 5);
+            bonds.extend(&[(0, 5), (5, 6), (6, 7), (7, 8), (8, 0)]);
+            let rings = skeleton(9, &bonds).rings();
+
+            assert_eq!(rings.num_rings(), 2);
+            assert_eq!(rings.ring_sizes(atom(0)), vec![5, 5]);
+            let systems = rings.ring_systems();
+            assert_eq!(systems.len(), 1);
+            assert!(systems[0].is_spiro());
+            assert!(!systems[0].is_fused());
+        }
+
+        #[test]
+        fn can_perceive_bridged_rings() {
+            // Norbornane
+            let mut bonds = cycle(0, 6);
+            bonds.extend(&[(0, 6), (6, 3)]);
+            let rings = skeleton(7, &bonds).rings();
+
+            assert_eq!(rings.num_rings(), 2);
+            assert!(rings.sssr().iter().all(|r| r.len() == 5));
+            assert_eq!(rings.relevant_cycles().len(), 2);
+            let systems = rings.ring_systems();
+            assert_eq!(systems.len(), 1);
+            assert!(systems[0].is_bridged());
+        }
+
+        #[test]
+        fn can_perceive_relevant_cycles() {
+            // Cubane
+            let mut bonds = cycle(0, 4);
+            bonds.extend(cycle(4, 4));
+            bonds.extend(&[(0, 4), (1, 5), (2, 6), (3, 7)]);
+            let rings = skeleton(8, &bonds).rings();
+
+            assert_eq!(rings.num_rings(), 5);
+            assert_eq!(rings.relevant_cycles().len(), 6);
+            assert!(rings.relevant_cycles().iter().all(|r| r.len() == 4));
+            assert_eq!(rings.ring_systems().len(), 1);
+
+            // A square grid, whose opposite corners are joined by tens of thousands of shortest
+            // paths
+            let side = 10;
+            let mut bonds = vec![];
+            for i in 0..side * side {
+                if i % side + 1 < side {
+                    bonds.push((i, i + 1));
+                }
+                if i + side < side * side {
+                    bonds.push((i, i + side));
+                }
+            }
+            let rings = skeleton(side * side, &bonds).rings();
+            assert_eq!(rings.num_rings(), 81);
+            assert_eq!(rings.relevant_cycles().len(), 81);
+        }
+
+        #[test]
+        fn can_perceive_separate_ring_systems() {
+            // Bicyclohexyl
+            let mut bonds = cycle(0, 6);
+            bonds.extend(cycle(6, 6));
+            bonds.push((0, 6));
+            let rings = skeleton(12, &bonds).rings();
+
+            assert_eq!(rings.num_rings(), 2);
+            assert!(!rings.bond_in_ring(atom(0), atom(6)));
+            let systems = rings.ring_systems();
+            assert_eq!(systems.len(), 2);
+            assert!(systems.iter().all(|s| s.is_isolated()));
+        }
+    }
+
+    mod canon {
+        use crate::core::AtomIndex;
+        use crate::daylight::{molecule_from_smiles, molecule_to_canonical_smiles};
+
+        #[test]
+        fn can_rank_atoms_canonically() {
+            let ranks = |s: &str| molecule_from_smiles(s).unwrap().canonical_ranks();
+            let ethanol = ranks("CCO");
+            assert_eq!(
+                ranks("OCC"),
+                ethanol.iter().rev().copied().collect::<Vec<usize>>()
+            );
+            // Every atom gets its own rank, even symmetric ones
+            let mut benzene = ranks("c1ccccc1");
+            benzene.sort();
+            assert_eq!(benzene, (0..6).collect::<Vec<usize>>());
+        }
+
+        #[test]
+        fn ranks_dont_depend_on_atom_order() {
+            for s in &[
+                // cis and trans, where the stereocentres are symmetric
+                "C[C@H]1CC[C@@H](C)CC1",
+                "C[C@H]1CC[C@H](C)CC1",
+                // myo-Inositol
+                "O[C@H]1[C@H](O)[C@@H](O)[C@H](O)[C@@H](O)[C@@H]1O",
+                "CC.O.CC.O",
+            ] {
+                let mol = molecule_from_smiles(s).unwrap();
+                let expected = molecule_to_canonical_smiles(&mol);
+                let mut atoms: Vec<AtomIndex> = mol.atoms().collect();
+                for _ in 0..atoms.len() {
+                    atoms.rotate_left(1);
+                    let rotated = mol.subgraph(&atoms);
+                    assert_eq!(molecule_to_canonical_smiles(&rotated), expected);
+                    let reversed: Vec<AtomIndex> = atoms.iter().rev().copied().collect();
+                    let reversed = mol.subgraph(&reversed);
+                    assert_eq!(molecule_to_canonical_smiles(&reversed), expected);
+                }
+            }
+        }
+    }
+
+    mod distance {
+        use super::*;
+
+        #[test]
+        fn can_compute_distance_matrix() {
+            // Propane and a separate methane
+            let mut mol = Molecule::new();
+            let atoms: Vec<AtomIndex> = (0..4)
+                .map(|_| mol.add_atom(Atom::new(Element::C)))
+                .collect();
+            mol.add_bond(atoms[0], atoms[1], Bond::Single).unwrap();
+            mol.add_bond(atoms[1], atoms[2], Bond::Single).unwrap();
+
+            let distances = mol.distance_matrix();
+            assert_eq!(distances[0], vec![Some(0), Some(1), Some(2), None]);
+            assert_eq!(distances[2][0], Some(2));
+            assert_eq!(distances[3], vec![None, None, None, Some(0)]);
+        }
+    }
+
+    mod fragment {
+        use super::*;
+
+        fn mol(elements: &[Element], bonds: &[(usize, usize)]) -> Molecule {
+            let mut mol = Molecule::new();
+            let atoms: Vec<AtomIndex> = elements
+                .iter()
+                .map(|el| mol.add_atom(Atom::new(*el)))
+                .collect();
+            for (a, b) in bonds {
+                mol.add_bond(atoms[*a], atoms[*b], Bond::Single).unwrap();
+            }
+            mol
+        }
+
+        #[test]
+        fn can_split_fragments() {
+            use Element::*;
+            // Methylamine hydrochloride with a stray water molecule, written interleaved
+            let salt = mol(&[Cl, C, H, O, N, H], &[(1, 4), (0, 2), (3, 5)]);
+
+            let components = salt.components();
+            assert_eq!(
+                components,
+                vec![
+                    vec![AtomIndex::new(0), AtomIndex::new(2)],
+                    vec![AtomIndex::new(1), AtomIndex::new(4)],
+                    vec![AtomIndex::new(3), AtomIndex::new(5)],
+                ]
+            );
+
+            let fragments = salt.fragments();
+            assert_eq!(fragments.len(), 3);
+            assert_eq!(fragments[0], mol(&[H, Cl], &[(0, 1)]));
+            assert_eq!(fragments[1], mol(&[N, C], &[(0, 1)]));
+            assert_eq!(fragments[2], mol(&[O, H], &[(0, 1)]));
+            // Fragment atoms map back to the parent in order
+            let with_atoms = salt.fragments_with_atoms();
+            assert_eq!(with_atoms.len(), 3);
+            for ((atoms, fragment), component) in with_atoms.iter().zip(&components) {
+                assert_eq!(atoms, component);
+                for (i, parent) in atoms.iter().enumerate() {
+                    assert_eq!(fragment.atom(AtomIndex::new(i)), salt.atom(*parent));
+                }
+            }
+        }
+
+        #[test]
+        fn can_pick_largest_fragment() {
+            use Element::*;
+            let salt = mol(&[Cl, C, H, O, N, H], &[(1, 4), (0, 2), (3, 5)]);
+
+            // All three have two atoms but HCl and water have one heavy atom each
+            assert_eq!(salt.heavy_atom_count(), 4);
+            assert_eq!(salt.largest_fragment(), mol(&[C, N], &[(0, 1)]));
+
+            // Ties on heavy atoms go to the larger fragment, then to the first one
+            let tied = mol(&[O, C, H, C], &[(1, 2)]);
+            assert_eq!(tied.largest_fragment(), mol(&[C, H], &[(0, 1)]));
+            let tied = mol(&[O, C], &[]);
+            assert_eq!(tied.largest_component(), Some(vec![AtomIndex::new(0)]));
+
+            assert_eq!(Molecule::new().largest_component(), None);
+            assert_eq!(Molecule::new().largest_fragment(), Molecule::new());
+        }
+    }
+}
@@ -1,16 +1,67 @@
 use crate::core::Element;
 use petgraph::prelude::*;
+use std::collections::HashMap;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Atom {
     pub element: Element,
-    //pub formal_charge: i8,
-    //pub explicit_hydrogens: u8,
-    //pub map: Option<i8>,
+    pub isotope: Option<u16>,
+    pub formal_charge: i8,
+    /// Hydrogens attached to this atom that are not part of the graph.
+    pub hydrogens: u8,
+    pub aromatic: bool,
+    pub chirality: Option<Chirality>,
+    pub map: Option<usize>,
     //pub electrons: u8
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+/// Tetrahedral chirality. Looking from the first neighbour towards the atom, the others are
+/// arranged counterclockwise or clockwise, like `@` and `@@` in SMILES. Neighbours are ordered
+/// with an implicit hydrogen first, then by ascending atom index.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Chirality {
+    CounterClockwise,
+    Clockwise,
+}
+
+impl Chirality {
+    pub fn inverted(&self) -> Self {
+        match self {
+            Chirality::CounterClockwise => Chirality::Clockwise,
+            Chirality::Clockwise => Chirality::CounterClockwise,
+        }
+    }
+
+    /// The chirality after the neighbours are listed in a different order, where `order` is a
+    /// permutation giving the old position of each neighbour in the new list.
+    pub fn permuted(&self, order: &[usize]) -> Self {
+        let inversions = (0..order.len())
+            .flat_map(|i| (i + 1..order.len()).map(move |j| (i, j)))
+            .filter(|(i, j)| order[*i] > order[*j])
+            .count();
+        if inversions % 2 == 0 {
+            *self
+        } else {
+            self.inverted()
+        }
+    }
+}
+
+impl Atom {
+    pub fn new(element: Element) -> Self {
+        Atom {
+            element,
+            isotope: None,
+            formal_charge: 0,
+            hydrogens: 0,
+            aromatic: false,
+            chirality: None,
+            map: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum Bond {
     Single,
     Double,
@@ -18,14 +69,41 @@ pub enum Bond {
     Aromatic,
 }
 
+impl Bond {
+    /// The valence the bond contributes to each of its atoms, counting aromatic bonds as single.
+    pub fn valence(&self) -> u8 {
+        match self {
+            Bond::Single | Bond::Aromatic => 1,
+            Bond::Double => 2,
+            Bond::Triple => 3,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Molecule {
     graph: petgraph::graph::UnGraph<Atom, Bond>,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+impl Default for Molecule {
+    fn default() -> Self {
+        Molecule::new()
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct AtomIndex(NodeIndex);
 
+impl AtomIndex {
+    pub fn new(index: usize) -> Self {
+        AtomIndex(NodeIndex::new(index))
+    }
+
+    pub fn index(&self) -> usize {
+        self.0.index()
+    }
+}
+
 // TODO: Proper error handling
 impl Molecule {
     pub fn new() -> Self {
@@ -51,18 +129,240 @@ impl Molecule {
         &self.graph[atom.0]
     }
 
-    //pub fn atom_mut(&mut self, atom: AtomIndex) -> &mut Atom {
-    //&mut self.graph[atom.0]
-    //}
+    pub fn atom_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn bond_count(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    pub fn atoms(&self) -> impl Iterator<Item = AtomIndex> + '_ {
+        self.graph.node_indices().map(AtomIndex)
+    }
+
+    /// Iterates over every bond once as `(a, b, bond)`.
+    pub fn bonds(&self) -> impl Iterator<Item = (AtomIndex, AtomIndex, Bond)> + '_ {
+        self.graph.edge_references().map(|edge| {
+            (
+                AtomIndex(edge.source()),
+                AtomIndex(edge.target()),
+                *edge.weight(),
+            )
+        })
+    }
+
+    pub fn bond(&self, a: AtomIndex, b: AtomIndex) -> Option<Bond> {
+        self.graph.find_edge(a.0, b.0).map(|edge| self.graph[edge])
+    }
+
+    pub fn neighbors(&self, atom: AtomIndex) -> impl Iterator<Item = AtomIndex> + '_ {
+        self.graph.neighbors(atom.0).map(AtomIndex)
+    }
+
+    pub fn degree(&self, atom: AtomIndex) -> usize {
+        self.graph.neighbors(atom.0).count()
+    }
+
+    /// The total bond order of an atom including its hydrogens. An aromatic atom has one more
+    /// than its bonds account for, as in the implicit hydrogen rules of SMILES.
+    pub fn valence(&self, atom: AtomIndex) -> u8 {
+        let bonds: u8 = self
+            .graph
+            .edges(atom.0)
+            .map(|edge| edge.weight().valence())
+            .sum();
+        let aromatic = self.atom(atom).aromatic as u8;
+        bonds + aromatic + self.atom(atom).hydrogens
+    }
+
+    /// Changes the order of an existing bond.
+    pub fn set_bond(&mut self, a: AtomIndex, b: AtomIndex, bond: Bond) -> Option<Bond> {
+        let edge = self.graph.find_edge(a.0, b.0)?;
+        Some(std::mem::replace(&mut self.graph[edge], bond))
+    }
+
+    pub fn remove_bond(&mut self, a: AtomIndex, b: AtomIndex) -> Option<Bond> {
+        let edge = self.graph.find_edge(a.0, b.0)?;
+        self.graph.remove_edge(edge)
+    }
+
+    pub fn atom_mut(&mut self, atom: AtomIndex) -> &mut Atom {
+        &mut self.graph[atom.0]
+    }
 }
 
+/// Molecules are equal if their graphs are isomorphic with equal atoms and bonds. As chirality
+/// depends on the order of an atom's neighbours, each stereocentre is compared after mapping its
+/// neighbours through the isomorphism, so the same centre written in a different order is equal.
 impl PartialEq<Molecule> for Molecule {
     fn eq(&self, other: &Molecule) -> bool {
-        petgraph::algo::is_isomorphic_matching(
-            &self.graph,
-            &other.graph,
-            |na, nb| na == nb,
-            |ea, eb| ea == eb,
-        )
+        if self.atom_count() != other.atom_count() || self.bond_count() != other.bond_count() {
+            return false;
+        }
+        let colors = match refined_colors(self, other) {
+            Some(colors) => colors,
+            None => return false,
+        };
+        let mut mapping = vec![None; self.atom_count()];
+        let mut used = vec![false; other.atom_count()];
+        let order = search_order(self, &colors[0]);
+        extend_isomorphism(self, other, &colors, &order, &mut mapping, &mut used)
+    }
+}
+
+/// Colours the atoms of both molecules alike by their properties and degree, then refines the
+/// colours by those of each atom's neighbours and bonds until they stop splitting, so that only
+/// atoms of the same colour can map onto each other. Returns nothing if the molecules' colours
+/// differ, as then they can't be isomorphic.
+fn refined_colors(a: &Molecule, b: &Molecule) -> Option<[Vec<usize>; 2]> {
+    let mols = [a, b];
+    let mut ids = HashMap::new();
+    let mut colors = mols.map(|mol| {
+        mol.atoms()
+            .map(|atom| {
+                let key = (achiral(mol.atom(atom)), mol.degree(atom));
+                let next = ids.len();
+                *ids.entry(key).or_insert(next)
+            })
+            .collect::<Vec<usize>>()
+    });
+    let mut count = ids.len();
+    loop {
+        let mut ids = HashMap::new();
+        let refined = [0, 1].map(|i| {
+            let mol = mols[i];
+            mol.atoms()
+                .map(|atom| {
+                    let mut neighbors: Vec<(Bond, usize)> = mol
+                        .neighbors(atom)
+                        .map(|n| (mol.bond(atom, n).unwrap(), colors[i][n.index()]))
+                        .collect();
+                    neighbors.sort_unstable();
+                    let next = ids.len();
+                    *ids.entry((colors[i][atom.index()], neighbors))
+                        .or_insert(next)
+                })
+                .collect::<Vec<usize>>()
+        });
+        colors = refined;
+        if ids.len() == count {
+            break;
+        }
+        count = ids.len();
     }
+    let sorted = |colors: &[usize]| {
+        let mut colors = colors.to_vec();
+        colors.sort_unstable();
+        colors
+    };
+    if sorted(&colors[0]) == sorted(&colors[1]) {
+        Some(colors)
+    } else {
+        None
+    }
+}
+
+/// The atoms in breadth first order, so that every atom but the first of each component has a
+/// neighbour earlier in the order. Each component starts from an atom of the rarest colour, which
+/// has the fewest atoms it could map onto.
+fn search_order(mol: &Molecule, colors: &[usize]) -> Vec<AtomIndex> {
+    let mut sizes = HashMap::new();
+    for color in colors {
+        *sizes.entry(*color).or_insert(0) += 1;
+    }
+    let mut starts: Vec<AtomIndex> = mol.atoms().collect();
+    starts.sort_by_key(|atom| sizes[&colors[atom.index()]]);
+    let mut order = vec![];
+    let mut seen = vec![false; mol.atom_count()];
+    for start in starts {
+        if seen[start.index()] {
+            continue;
+        }
+        seen[start.index()] = true;
+        let first = order.len();
+        order.push(start);
+        let mut i = first;
+        while i < order.len() {
+            for n in mol.neighbors(order[i]) {
+                if !seen[n.index()] {
+                    seen[n.index()] = true;
+                    order.push(n);
+                }
+            }
+            i += 1;
+        }
+    }
+    order
+}
+
+/// Maps the remaining atoms of `a` in order onto unused atoms of `b` by backtracking, returning
+/// whether a mapping with the same chirality was found.
+fn extend_isomorphism(
+    a: &Molecule,
+    b: &Molecule,
+    colors: &[Vec<usize>; 2],
+    order: &[AtomIndex],
+    mapping: &mut Vec<Option<AtomIndex>>,
+    used: &mut Vec<bool>,
+) -> bool {
+    let (atom, rest) = match order.split_first() {
+        Some((atom, rest)) => (*atom, rest),
+        None => return same_chirality(a, b, mapping),
+    };
+    let mapped: Vec<(AtomIndex, AtomIndex)> = a
+        .neighbors(atom)
+        .filter_map(|n| mapping[n.index()].map(|m| (n, m)))
+        .collect();
+    let candidates: Vec<AtomIndex> = match mapped.first() {
+        Some((_, m)) => b.neighbors(*m).collect(),
+        None => b.atoms().collect(),
+    };
+    for candidate in candidates {
+        let consistent = !used[candidate.index()]
+            && colors[0][atom.index()] == colors[1][candidate.index()]
+            && mapped
+                .iter()
+                .all(|(n, m)| a.bond(atom, *n) == b.bond(candidate, *m))
+            && b.neighbors(candidate).filter(|n| used[n.index()]).count() == mapped.len();
+        if !consistent {
+            continue;
+        }
+        mapping[atom.index()] = Some(candidate);
+        used[candidate.index()] = true;
+        if extend_isomorphism(a, b, colors, rest, mapping, used) {
+            return true;
+        }
+        mapping[atom.index()] = None;
+        used[candidate.index()] = false;
+    }
+    false
+}
+
+/// The atom without the direction of its chirality, so that atoms are alike whenever they could
+/// be the same stereocentre.
+fn achiral(atom: &Atom) -> Atom {
+    Atom {
+        chirality: atom.chirality.map(|_| Chirality::CounterClockwise),
+        ..*atom
+    }
+}
+
+/// Whether every stereocentre of `a` has the chirality of the atom it maps to, with its
+/// neighbours reordered as their images are ordered in `b`.
+fn same_chirality(a: &Molecule, b: &Molecule, mapping: &[Option<AtomIndex>]) -> bool {
+    a.atoms().all(|atom| {
+        let chirality = match a.atom(atom).chirality {
+            Some(chirality) => chirality,
+            None => return true,
+        };
+        let image = mapping[atom.index()].unwrap();
+        let mut neighbors: Vec<AtomIndex> = a.neighbors(atom).collect();
+        neighbors.sort();
+        let mut keys: Vec<Option<AtomIndex>> = vec![None; a.atom(atom).hydrogens as usize];
+        keys.extend(neighbors.iter().map(|n| mapping[n.index()]));
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by_key(|i| keys[*i]);
+        b.atom(image).chirality == Some(chirality.permuted(&order))
+    })
 }
@@ -0,0 +1,269 @@
+use crate::core::{charge_valence, AtomIndex, Bond, Element, Molecule, Reaction};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+impl Molecule {
+    /// The oxidation state of each atom, by atom index.
+    ///
+    /// Each bond's electrons are given to the more electronegative of its atoms, and bonds between
+    /// atoms of equal or unknown electronegativity are split evenly. The state is the formal charge
+    /// plus one for every electron lost this way. Implicit hydrogens have no state of their own,
+    /// but count as bonds to hydrogen, and aromatic bonds count as the single and double bonds of
+    /// a Kekulé structure.
+    pub fn oxidation_states(&self) -> Vec<i8> {
+        let double = kekule_double_bonds(self);
+        let hydrogen = Element::H.electronegativity();
+        self.atoms()
+            .map(|atom| {
+                let a = self.atom(atom);
+                let electronegativity = a.element.electronegativity();
+                let mut state = a.formal_charge as i16
+                    + a.hydrogens as i16 * polarity(electronegativity, hydrogen);
+                for n in self.neighbors(atom) {
+                    let order = match self.bond(atom, n).unwrap() {
+                        Bond::Aromatic if double.contains(&ordered(atom, n)) => 2,
+                        bond => bond.valence() as i16,
+                    };
+                    state += order
+                        * polarity(electronegativity, self.atom(n).element.electronegativity());
+                }
+                state as i8
+            })
+            .collect()
+    }
+}
+
+/// The change in oxidation state of a mapped atom across a reaction.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct OxidationChange {
+    pub map: usize,
+    pub element: Element,
+    pub before: i8,
+    pub after: i8,
+}
+
+/// The mapped atoms whose oxidation states rise or fall across a reaction, each in ascending order
+/// of map number.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct RedoxSummary {
+    pub oxidized: Vec<OxidationChange>,
+    pub reduced: Vec<OxidationChange>,
+}
+
+impl RedoxSummary {
+    pub fn is_redox(&self) -> bool {
+        !self.oxidized.is_empty() || !self.reduced.is_empty()
+    }
+
+    /// The electrons lost by the oxidized atoms.
+    pub fn electrons_transferred(&self) -> u32 {
+        self.oxidized
+            .iter()
+            .map(|change| (change.after - change.before) as u32)
+            .sum()
+    }
+}
+
+impl Reaction {
+    /// Compares the oxidation states of atoms mapped on both sides. Unmapped atoms, such as those
+    /// of reagents whose atoms aren't carried into the products, aren't included.
+    pub fn redox(&self) -> RedoxSummary {
+        let states = |molecules: &[Molecule]| -> Vec<Vec<i8>> {
+            molecules.iter().map(|mol| mol.oxidation_states()).collect()
+        };
+        let (reactant_states, product_states) = (states(&self.reactants), states(&self.products));
+
+        let mut summary = RedoxSummary::default();
+        for (r, p) in self.atom_mapping() {
+            let before = reactant_states[r.molecule][r.atom.index()];
+            let after = product_states[p.molecule][p.atom.index()];
+            let atom = self.reactants[r.molecule].atom(r.atom);
+            let change = OxidationChange {
+                map: atom.map.unwrap(),
+                element: atom.element,
+                before,
+                after,
+            };
+            if after > before {
+                summary.oxidized.push(change);
+            } else if after < before {
+                summary.reduced.push(change);
+            }
+        }
+        summary
+    }
+}
+
+/// One if the first atom gives up a bond's electrons to the second, minus one if it takes the
+/// second's, and zero if neither is more electronegative.
+fn polarity(electronegativity: Option<f64>, other: Option<f64>) -> i16 {
+    match (electronegativity, other) {
+        (Some(a), Some(b)) if a < b => 1,
+        (Some(a), Some(b)) if a > b => -1,
+        _ => 0,
+    }
+}
+
+fn ordered(a: AtomIndex, b: AtomIndex) -> (AtomIndex, AtomIndex) {
+    (a.min(b), a.max(b))
+}
+
+/// The aromatic bonds that are double in a Kekulé structure, pairing up the aromatic atoms that
+/// are short of their valence. If they can't all be paired, as many are as possible.
+fn kekule_double_bonds(mol: &Molecule) -> HashSet<(AtomIndex, AtomIndex)> {
+    let unsaturated: Vec<AtomIndex> = mol
+        .atoms()
+        .filter(|atom| {
+            let a = mol.atom(*atom);
+            let bonds: u8 = mol
+                .neighbors(*atom)
+                .map(|n| mol.bond(*atom, n).unwrap().valence())
+                .sum();
+            match a.element.default_valence() {
+                Some(valence) if a.aromatic => {
+                    ((bonds + a.hydrogens) as i16)
+                        < valence as i16 + charge_valence(a.element, a.formal_charge)
+                }
+                _ => false,
+            }
+        })
+        .collect();
+
+    let index: HashMap<AtomIndex, usize> = unsaturated
+        .iter()
+        .enumerate()
+        .map(|(i, atom)| (*atom, i))
+        .collect();
+    let adjacency: Vec<Vec<usize>> = unsaturated
+        .iter()
+        .map(|atom| {
+            mol.neighbors(*atom)
+                .filter(|n| mol.bond(*atom, *n) == Some(Bond::Aromatic))
+                .filter_map(|n| index.get(&n).copied())
+                .collect()
+        })
+        .collect();
+    maximum_matching(&adjacency)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, partner)| partner.map(|j| ordered(unsaturated[i], unsaturated[j])))
+        .collect()
+}
+
+/// A maximum matching of a graph given by its adjacency lists, as each vertex's partner. Found by
+/// Edmonds' blossom algorithm, since rings of odd size make the graph non-bipartite.
+fn maximum_matching(adjacency: &[Vec<usize>]) -> Vec<Option<usize>> {
+    let mut matching = Blossoms {
+        adjacency,
+        partner: vec![None; adjacency.len()],
+        parent: vec![],
+        base: vec![],
+        used: vec![],
+    };
+    for root in 0..adjacency.len() {
+        if matching.partner[root].is_some() {
+            continue;
+        }
+        // Flip the matched and unmatched edges along the augmenting path
+        let mut end = matching.augmenting_path(root);
+        while let Some(v) = end {
+            let parent = matching.parent[v].unwrap();
+            let next = matching.partner[parent];
+            matching.partner[v] = Some(parent);
+            matching.partner[parent] = Some(v);
+            end = next;
+        }
+    }
+    matching.partner
+}
+
+/// The state of a search for an augmenting path, in which odd cycles are contracted into their
+/// base vertex.
+struct Blossoms<'a> {
+    adjacency: &'a [Vec<usize>],
+    partner: Vec<Option<usize>>,
+    /// The vertex each vertex was reached from along an unmatched edge.
+    parent: Vec<Option<usize>>,
+    base: Vec<usize>,
+    /// Vertices at an even distance from the root, whose edges are searched.
+    used: Vec<bool>,
+}
+
+impl<'a> Blossoms<'a> {
+    /// The unmatched vertex at the end of an augmenting path from `root`, if there is one.
+    fn augmenting_path(&mut self, root: usize) -> Option<usize> {
+        let n = self.adjacency.len();
+        self.parent = vec![None; n];
+        self.base = (0..n).collect();
+        self.used = vec![false; n];
+        self.used[root] = true;
+        let mut queue = VecDeque::from(vec![root]);
+        while let Some(v) = queue.pop_front() {
+            for &to in &self.adjacency[v] {
+                if self.base[v] == self.base[to] || self.partner[v] == Some(to) {
+                    continue;
+                }
+                let odd_cycle =
+                    to == root || matches!(self.partner[to], Some(m) if self.parent[m].is_some());
+                if odd_cycle {
+                    let base = self.common_base(v, to);
+                    let mut blossom = vec![false; n];
+                    self.mark_path(&mut blossom, v, base, to);
+                    self.mark_path(&mut blossom, to, base, v);
+                    for i in 0..n {
+                        if blossom[self.base[i]] {
+                            self.base[i] = base;
+                            if !self.used[i] {
+                                self.used[i] = true;
+                                queue.push_back(i);
+                            }
+                        }
+                    }
+                } else if self.parent[to].is_none() {
+                    self.parent[to] = Some(v);
+                    match self.partner[to] {
+                        None => return Some(to),
+                        Some(m) => {
+                            self.used[m] = true;
+                            queue.push_back(m);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// The base of the blossom closed by an edge between `a` and `b`, where their paths back to
+    /// the root meet.
+    fn common_base(&self, mut a: usize, mut b: usize) -> usize {
+        let mut seen = vec![false; self.adjacency.len()];
+        loop {
+            a = self.base[a];
+            seen[a] = true;
+            match self.partner[a] {
+                Some(m) => a = self.parent[m].unwrap(),
+                None => break,
+            }
+        }
+        loop {
+            b = self.base[b];
+            if seen[b] {
+                return b;
+            }
+            b = self.parent[self.partner[b].unwrap()].unwrap();
+        }
+    }
+
+    /// Marks the blossom's vertices from `v` back to its base, and points their parents around
+    /// the cycle towards `child` so that a path can go either way.
+    fn mark_path(&mut self, blossom: &mut [bool], mut v: usize, base: usize, mut child: usize) {
+        while self.base[v] != base {
+            let m = self.partner[v].unwrap();
+            blossom[self.base[v]] = true;
+            blossom[self.base[m]] = true;
+            self.parent[v] = Some(child);
+            child = m;
+            v = self.parent[m].unwrap();
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::core::{AtomIndex, Bond, Molecule};
+use std::collections::{BTreeSet, HashMap};
+
+/// An atom in one of a reaction's molecules.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ReactionAtom {
+    /// The index of the molecule in its list, e.g. [`Reaction::reactants`].
+    pub molecule: usize,
+    pub atom: AtomIndex,
+}
+
+/// The bonds that differ between the mapped atoms of a reaction's reactants and products. Atoms
+/// are given by map number, with the lower one first, and each list is sorted.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ReactionCenter {
+    pub formed: Vec<(usize, usize, Bond)>,
+    pub broken: Vec<(usize, usize, Bond)>,
+    /// Bonds whose order changed, from the reactant bond to the product bond.
+    pub changed: Vec<(usize, usize, Bond, Bond)>,
+}
+
+impl ReactionCenter {
+    /// The map numbers of the atoms in any formed, broken or changed bond, in ascending order.
+    pub fn atoms(&self) -> Vec<usize> {
+        let pairs = self
+            .formed
+            .iter()
+            .chain(&self.broken)
+            .map(|(a, b, _)| (*a, *b))
+            .chain(self.changed.iter().map(|(a, b, _, _)| (*a, *b)));
+        let atoms: BTreeSet<usize> = pairs.flat_map(|(a, b)| vec![a, b]).collect();
+        atoms.into_iter().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.formed.is_empty() && self.broken.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A reaction as lists of reactant, agent and product molecules. Atoms are mapped across the
+/// arrow by their [`Atom::map`](crate::core::Atom::map) numbers.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Reaction {
+    pub reactants: Vec<Molecule>,
+    pub agents: Vec<Molecule>,
+    pub products: Vec<Molecule>,
+}
+
+impl Reaction {
+    pub fn new(reactants: Vec<Molecule>, agents: Vec<Molecule>, products: Vec<Molecule>) -> Self {
+        Reaction {
+            reactants,
+            agents,
+            products,
+        }
+    }
+
+    /// Pairs of reactant and product atoms with the same map number, in ascending order of map
+    /// number. Map numbers found on only one side are left out.
+    pub fn atom_mapping(&self) -> Vec<(ReactionAtom, ReactionAtom)> {
+        let reactants = mapped_atoms(&self.reactants);
+        let products = mapped_atoms(&self.products);
+        let mut maps: Vec<&usize> = reactants
+            .keys()
+            .filter(|m| products.contains_key(m))
+            .collect();
+        maps.sort();
+        maps.into_iter()
+            .map(|m| (reactants[m], products[m]))
+            .collect()
+    }
+
+    /// Whether any atom in the reactants or products has a map number.
+    pub fn is_mapped(&self) -> bool {
+        self.reactants
+            .iter()
+            .chain(&self.products)
+            .any(|mol| mol.atoms().any(|a| mol.atom(a).map.is_some()))
+    }
+
+    /// Compares the bonds between atoms mapped on both sides. Bonds to atoms found on only one
+    /// side, such as unmapped leaving groups, aren't included.
+    pub fn center(&self) -> ReactionCenter {
+        let reactants = mapped_bonds(&self.reactants);
+        let products = mapped_bonds(&self.products);
+        let (atoms, product_atoms) = (mapped_atoms(&self.reactants), mapped_atoms(&self.products));
+        let both = |m: &usize| atoms.contains_key(m) && product_atoms.contains_key(m);
+        let pairs: BTreeSet<(usize, usize)> = reactants
+            .keys()
+            .chain(products.keys())
+            .filter(|(a, b)| both(a) && both(b))
+            .copied()
+            .collect();
+
+        let mut center = ReactionCenter::default();
+        for (a, b) in pairs {
+            match (reactants.get(&(a, b)), products.get(&(a, b))) {
+                (None, Some(bond)) => center.formed.push((a, b, *bond)),
+                (Some(bond), None) => center.broken.push((a, b, *bond)),
+                (Some(before), Some(after)) if before != after => {
+                    center.changed.push((a, b, *before, *after))
+                }
+                _ => {}
+            }
+        }
+        center
+    }
+}
+
+/// Every bond between two atoms with map numbers, by their map numbers in ascending order.
+fn mapped_bonds(molecules: &[Molecule]) -> HashMap<(usize, usize), Bond> {
+    let mut bonds = HashMap::new();
+    for mol in molecules {
+        for (a, b, bond) in mol.bonds() {
+            if let (Some(a), Some(b)) = (mol.atom(a).map, mol.atom(b).map) {
+                bonds.insert((a.min(b), a.max(b)), bond);
+            }
+        }
+    }
+    bonds
+}
+
+/// Every atom with a map number, by map number.
+fn mapped_atoms(molecules: &[Molecule]) -> HashMap<usize, ReactionAtom> {
+    let mut atoms = HashMap::new();
+    for (molecule, mol) in molecules.iter().enumerate() {
+        for atom in mol.atoms() {
+            if let Some(map) = mol.atom(atom).map {
+                atoms.insert(map, ReactionAtom { molecule, atom });
+            }
+        }
+    }
+    atoms
+}
@@ -0,0 +1,506 @@
+use crate::core::{AtomIndex, Molecule};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A simple cycle in the molecule graph, with its atoms in ring order.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ring {
+    atoms: Vec<AtomIndex>,
+}
+
+impl Ring {
+    pub fn atoms(&self) -> &[AtomIndex] {
+        &self.atoms
+    }
+
+    pub fn len(&self) -> usize {
+        self.atoms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty()
+    }
+
+    pub fn contains(&self, atom: AtomIndex) -> bool {
+        self.atoms.contains(&atom)
+    }
+
+    /// Iterates over the bonds of the ring as pairs of adjacent atoms.
+    pub fn bonds(&self) -> impl Iterator<Item = (AtomIndex, AtomIndex)> + '_ {
+        let n = self.atoms.len();
+        (0..n).map(move |i| (self.atoms[i], self.atoms[(i + 1) % n]))
+    }
+
+    pub fn contains_bond(&self, a: AtomIndex, b: AtomIndex) -> bool {
+        self.bonds()
+            .any(|(x, y)| (x, y) == (a, b) || (x, y) == (b, a))
+    }
+}
+
+/// A group of SSSR rings connected through shared atoms.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RingSystem {
+    rings: Vec<usize>,
+    atoms: Vec<AtomIndex>,
+    fused: bool,
+    spiro: bool,
+    bridged: bool,
+}
+
+impl RingSystem {
+    /// Indices into [`RingInfo::sssr`].
+    pub fn rings(&self) -> &[usize] {
+        &self.rings
+    }
+
+    pub fn atoms(&self) -> &[AtomIndex] {
+        &self.atoms
+    }
+
+    /// A single ring sharing no atoms with any other.
+    pub fn is_isolated(&self) -> bool {
+        self.rings.len() == 1
+    }
+
+    /// Some pair of rings shares exactly one bond.
+    pub fn is_fused(&self) -> bool {
+        self.fused
+    }
+
+    /// Some pair of rings shares exactly one atom.
+    pub fn is_spiro(&self) -> bool {
+        self.spiro
+    }
+
+    /// Some pair of rings shares more than one bond, or two atoms that are not bonded.
+    pub fn is_bridged(&self) -> bool {
+        self.bridged
+    }
+}
+
+/// The result of ring perception on a molecule.
+///
+/// The smallest set of smallest rings (SSSR) is a minimum cycle basis of the molecule graph. It
+/// is not unique, so ties are broken deterministically by atom index. The relevant cycles are the
+/// union of all minimum cycle bases, which is unique and includes e.g. all six faces of cubane where
+/// the SSSR only holds five.
+#[derive(Clone, Debug)]
+pub struct RingInfo {
+    sssr: Vec<Ring>,
+    relevant: Vec<Ring>,
+    systems: Vec<RingSystem>,
+    atom_rings: Vec<Vec<usize>>,
+}
+
+/// A candidate cycle as both its atom sequence and the set of edges it uses.
+struct Cycle {
+    atoms: Vec<usize>,
+    edges: Vec<u64>,
+}
+
+impl RingInfo {
+    pub fn new(mol: &Molecule) -> Self {
+        let n = mol.atom_count();
+        let mut adjacency = vec![vec![]; n];
+        let mut edge_ids = HashMap::new();
+        for (i, (a, b, _)) in mol.bonds().enumerate() {
+            adjacency[a.index()].push(b.index());
+            adjacency[b.index()].push(a.index());
+            edge_ids.insert((a.index().min(b.index()), a.index().max(b.index())), i);
+        }
+        for neighbors in adjacency.iter_mut() {
+            neighbors.sort_unstable();
+        }
+
+        let words = edge_ids.len().div_ceil(64);
+        let mut prototypes = prototype_cycles(&adjacency, &edge_ids, words);
+        prototypes.sort_by(|a, b| {
+            let (a, b) = (&a.cycle.atoms, &b.cycle.atoms);
+            (a.len(), a).cmp(&(b.len(), b))
+        });
+
+        // Gaussian elimination over GF(2). A prototype is relevant if it is independent of all
+        // strictly shorter ones, and then so is every cycle of its family.
+        let mut basis: Vec<Vec<u64>> = vec![];
+        let mut shorter = vec![];
+        let mut relevant = vec![];
+        for (i, prototype) in prototypes.iter().enumerate() {
+            let len = prototype.cycle.atoms.len();
+            if i == 0 || len != prototypes[i - 1].cycle.atoms.len() {
+                shorter = basis.clone();
+            }
+            if !reduce(&shorter, &prototype.cycle.edges)
+                .iter()
+                .all(|w| *w == 0)
+            {
+                relevant.push(prototype);
+            }
+            let reduced = reduce(&basis, &prototype.cycle.edges);
+            if !reduced.iter().all(|w| *w == 0) {
+                insert(&mut basis, reduced);
+            }
+        }
+        let mut cycles = family_cycles(&relevant, &adjacency, &edge_ids, words);
+        cycles.sort_by(|a, b| (a.atoms.len(), &a.atoms).cmp(&(b.atoms.len(), &b.atoms)));
+
+        // A minimum cycle basis is made of relevant cycles, so the SSSR can be picked from them
+        let mut basis: Vec<Vec<u64>> = vec![];
+        let mut sssr = vec![];
+        for cycle in &cycles {
+            let reduced = reduce(&basis, &cycle.edges);
+            if !reduced.iter().all(|w| *w == 0) {
+                insert(&mut basis, reduced);
+                sssr.push(to_ring(cycle));
+            }
+        }
+        let relevant = cycles.iter().map(to_ring).collect();
+
+        let mut atom_rings = vec![vec![]; n];
+        for (i, ring) in sssr.iter().enumerate() {
+            for atom in ring.atoms() {
+                atom_rings[atom.index()].push(i);
+            }
+        }
+        let systems = ring_systems(&sssr, &atom_rings);
+
+        RingInfo {
+            sssr,
+            relevant,
+            systems,
+            atom_rings,
+        }
+    }
+
+    /// The smallest set of smallest rings, ordered by size.
+    pub fn sssr(&self) -> &[Ring] {
+        &self.sssr
+    }
+
+    /// All relevant cycles, ordered by size.
+    pub fn relevant_cycles(&self) -> &[Ring] {
+        &self.relevant
+    }
+
+    pub fn ring_systems(&self) -> &[RingSystem] {
+        &self.systems
+    }
+
+    pub fn num_rings(&self) -> usize {
+        self.sssr.len()
+    }
+
+    pub fn is_in_ring(&self, atom: AtomIndex) -> bool {
+        !self.atom_rings[atom.index()].is_empty()
+    }
+
+    /// The sizes of the SSSR rings containing the atom, smallest first.
+    pub fn ring_sizes(&self, atom: AtomIndex) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self.atom_rings[atom.index()]
+            .iter()
+            .map(|i| self.sssr[*i].len())
+            .collect();
+        sizes.sort_unstable();
+        sizes
+    }
+
+    pub fn is_in_ring_of_size(&self, atom: AtomIndex, size: usize) -> bool {
+        self.atom_rings[atom.index()]
+            .iter()
+            .any(|i| self.sssr[*i].len() == size)
+    }
+
+    pub fn bond_in_ring(&self, a: AtomIndex, b: AtomIndex) -> bool {
+        self.atom_rings[a.index()]
+            .iter()
+            .any(|i| self.sssr[*i].contains_bond(a, b))
+    }
+}
+
+impl Molecule {
+    pub fn rings(&self) -> RingInfo {
+        RingInfo::new(self)
+    }
+}
+
+fn to_ring(cycle: &Cycle) -> Ring {
+    Ring {
+        atoms: cycle.atoms.iter().map(|a| AtomIndex::new(*a)).collect(),
+    }
+}
+
+fn reduce(basis: &[Vec<u64>], edges: &[u64]) -> Vec<u64> {
+    let mut edges = edges.to_vec();
+    for row in basis {
+        let pivot = leading_bit(row).unwrap();
+        if edges[pivot / 64] & (1 << (pivot % 64)) != 0 {
+            for (w, r) in edges.iter_mut().zip(row) {
+                *w ^= r;
+            }
+        }
+    }
+    edges
+}
+
+/// Inserts a reduced row while keeping the basis sorted by descending pivot, so that a single
+/// pass of `reduce` clears every pivot.
+fn insert(basis: &mut Vec<Vec<u64>>, row: Vec<u64>) {
+    let pivot = leading_bit(&row);
+    let at = basis
+        .iter()
+        .position(|r| leading_bit(r) < pivot)
+        .unwrap_or(basis.len());
+    basis.insert(at, row);
+}
+
+fn leading_bit(row: &[u64]) -> Option<usize> {
+    row.iter()
+        .enumerate()
+        .rev()
+        .find(|(_, w)| **w != 0)
+        .map(|(i, w)| i * 64 + 63 - w.leading_zeros() as usize)
+}
+
+/// A cycle made of two shortest paths from a root atom, joined either by an edge between their
+/// ends or through a middle atom next to both.
+struct Prototype {
+    root: usize,
+    left: usize,
+    middle: Option<usize>,
+    right: usize,
+    cycle: Cycle,
+}
+
+/// Breadth first search over atoms with a higher index than the root, giving the distance to
+/// each atom and its neighbours one step closer to the root.
+fn search(adjacency: &[Vec<usize>], root: usize) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let n = adjacency.len();
+    let mut dist = vec![usize::MAX; n];
+    let mut preds = vec![vec![]; n];
+    let mut queue = VecDeque::new();
+    dist[root] = 0;
+    queue.push_back(root);
+    while let Some(v) = queue.pop_front() {
+        for &w in &adjacency[v] {
+            if w < root {
+                continue;
+            }
+            if dist[w] == usize::MAX {
+                dist[w] = dist[v] + 1;
+                queue.push_back(w);
+            }
+            if dist[w] == dist[v] + 1 {
+                preds[w].push(v);
+            }
+        }
+    }
+    (dist, preds)
+}
+
+/// The cycle of two paths from the same root and an optional middle atom, unless the paths meet
+/// away from the root.
+fn join(
+    left: &[usize],
+    middle: Option<usize>,
+    right: &[usize],
+    edge_ids: &HashMap<(usize, usize), usize>,
+    words: usize,
+) -> Option<Cycle> {
+    if left[1..].iter().any(|a| right[1..].contains(a)) {
+        return None;
+    }
+    let mut atoms = left.to_vec();
+    atoms.extend(middle);
+    atoms.extend(right[1..].iter().rev());
+    let mut edges = vec![0u64; words];
+    for i in 0..atoms.len() {
+        let (a, b) = (atoms[i], atoms[(i + 1) % atoms.len()]);
+        let id = edge_ids[&(a.min(b), a.max(b))];
+        edges[id / 64] |= 1 << (id % 64);
+    }
+    Some(Cycle { atoms, edges })
+}
+
+/// Vismara's prototypes: for each way of closing a cycle with two shortest paths from a root
+/// atom, where the root is the lowest indexed atom of the cycle, the cycle made with one shortest
+/// path to each end. Any pair of atoms on a relevant cycle is joined by a shortest path along the
+/// cycle, so every relevant cycle is in the family of a prototype, the cycles made the same way
+/// with any of the shortest paths.
+fn prototype_cycles(
+    adjacency: &[Vec<usize>],
+    edge_ids: &HashMap<(usize, usize), usize>,
+    words: usize,
+) -> Vec<Prototype> {
+    let mut seen = HashSet::new();
+    let mut prototypes = vec![];
+
+    for root in 0..adjacency.len() {
+        let (dist, preds) = search(adjacency, root);
+        // The shortest path that always steps back to the first predecessor
+        let path = |mut v: usize| {
+            let mut path = vec![v];
+            while let Some(&p) = preds[v].first() {
+                path.push(p);
+                v = p;
+            }
+            path.reverse();
+            path
+        };
+        let mut add = |left: usize, middle: Option<usize>, right: usize| {
+            if let Some(cycle) = join(&path(left), middle, &path(right), edge_ids, words) {
+                if seen.insert(cycle.edges.clone()) {
+                    prototypes.push(Prototype {
+                        root,
+                        left,
+                        middle,
+                        right,
+                        cycle,
+                    });
+                }
+            }
+        };
+
+        for y in root..adjacency.len() {
+            if dist[y] == usize::MAX || dist[y] == 0 {
+                continue;
+            }
+            // Odd cycles close over an edge between two atoms at the same distance
+            for &z in adjacency[y]
+                .iter()
+                .filter(|z| **z > y && dist[**z] == dist[y])
+            {
+                add(y, None, z);
+            }
+            // Even cycles close at an atom with two predecessors
+            for (i, &p) in preds[y].iter().enumerate() {
+                for &q in &preds[y][i + 1..] {
+                    add(p, Some(y), q);
+                }
+            }
+        }
+    }
+
+    prototypes
+}
+
+/// Every cycle in the families of the prototypes.
+fn family_cycles(
+    prototypes: &[&Prototype],
+    adjacency: &[Vec<usize>],
+    edge_ids: &HashMap<(usize, usize), usize>,
+    words: usize,
+) -> Vec<Cycle> {
+    let mut seen = HashSet::new();
+    let mut cycles = vec![];
+    let mut searches = HashMap::new();
+    for prototype in prototypes {
+        let (preds, paths) = searches.entry(prototype.root).or_insert_with(|| {
+            let (_, preds) = search(adjacency, prototype.root);
+            (preds, HashMap::new())
+        });
+        let lefts = shortest_paths(prototype.left, preds, paths);
+        let rights = shortest_paths(prototype.right, preds, paths);
+        for left in &lefts {
+            for right in &rights {
+                if let Some(cycle) = join(left, prototype.middle, right, edge_ids, words) {
+                    if seen.insert(cycle.edges.clone()) {
+                        cycles.push(cycle);
+                    }
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// All shortest paths from the search root to `v`, memoized per root. These are only listed for
+/// the ends of relevant prototypes, whose families are all relevant cycles.
+fn shortest_paths(
+    v: usize,
+    preds: &[Vec<usize>],
+    memo: &mut HashMap<usize, Vec<Vec<usize>>>,
+) -> Vec<Vec<usize>> {
+    if let Some(paths) = memo.get(&v) {
+        return paths.clone();
+    }
+    let paths = if preds[v].is_empty() {
+        vec![vec![v]]
+    } else {
+        let mut paths = vec![];
+        for &p in &preds[v] {
+            for mut path in shortest_paths(p, preds, memo) {
+                path.push(v);
+                paths.push(path);
+            }
+        }
+        paths
+    };
+    memo.insert(v, paths.clone());
+    paths
+}
+
+fn ring_systems(sssr: &[Ring], atom_rings: &[Vec<usize>]) -> Vec<RingSystem> {
+    // Union-find over rings sharing an atom
+    let mut parent: Vec<usize> = (0..sssr.len()).collect();
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        if parent[i] != i {
+            parent[i] = find(parent, parent[i]);
+        }
+        parent[i]
+    }
+    for rings in atom_rings {
+        for pair in rings.windows(2) {
+            let (a, b) = (find(&mut parent, pair[0]), find(&mut parent, pair[1]));
+            parent[a.max(b)] = a.min(b);
+        }
+    }
+
+    let mut systems: Vec<RingSystem> = vec![];
+    let mut index_of = HashMap::new();
+    for ring in 0..sssr.len() {
+        let root = find(&mut parent, ring);
+        let i = *index_of.entry(root).or_insert_with(|| {
+            systems.push(RingSystem {
+                rings: vec![],
+                atoms: vec![],
+                fused: false,
+                spiro: false,
+                bridged: false,
+            });
+            systems.len() - 1
+        });
+        systems[i].rings.push(ring);
+    }
+
+    for system in systems.iter_mut() {
+        let mut atoms: Vec<AtomIndex> = system
+            .rings
+            .iter()
+            .flat_map(|r| sssr[*r].atoms().iter().copied())
+            .collect();
+        atoms.sort();
+        atoms.dedup();
+        system.atoms = atoms;
+
+        for (i, &a) in system.rings.iter().enumerate() {
+            for &b in &system.rings[i + 1..] {
+                let shared: Vec<AtomIndex> = sssr[a]
+                    .atoms()
+                    .iter()
+                    .copied()
+                    .filter(|atom| sssr[b].contains(*atom))
+                    .collect();
+                match shared.len() {
+                    0 => {}
+                    1 => system.spiro = true,
+                    2 if sssr[a].contains_bond(shared[0], shared[1])
+                        && sssr[b].contains_bond(shared[0], shared[1]) =>
+                    {
+                        system.fused = true
+                    }
+                    _ => system.bridged = true,
+                }
+            }
+        }
+    }
+
+    systems
+}
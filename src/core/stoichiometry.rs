@@ -0,0 +1,368 @@
+use crate::core::{ChemicalEquation, MolecularFormula};
+use std::fmt;
+
+/// A mass, stored in grams.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct Mass(f64);
+
+impl Mass {
+    pub fn grams(grams: f64) -> Self {
+        Mass(grams)
+    }
+
+    pub fn milligrams(milligrams: f64) -> Self {
+        Mass(milligrams / 1000.0)
+    }
+
+    pub fn kilograms(kilograms: f64) -> Self {
+        Mass(kilograms * 1000.0)
+    }
+
+    pub fn as_grams(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_milligrams(&self) -> f64 {
+        self.0 * 1000.0
+    }
+}
+
+/// An amount of substance, stored in moles.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct Amount(f64);
+
+impl Amount {
+    pub fn moles(moles: f64) -> Self {
+        Amount(moles)
+    }
+
+    pub fn millimoles(millimoles: f64) -> Self {
+        Amount(millimoles / 1000.0)
+    }
+
+    pub fn as_moles(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_millimoles(&self) -> f64 {
+        self.0 * 1000.0
+    }
+}
+
+/// A volume, stored in litres.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct Volume(f64);
+
+impl Volume {
+    pub fn liters(liters: f64) -> Self {
+        Volume(liters)
+    }
+
+    pub fn milliliters(milliliters: f64) -> Self {
+        Volume(milliliters / 1000.0)
+    }
+
+    pub fn as_liters(&self) -> f64 {
+        self.0
+    }
+
+    pub fn as_milliliters(&self) -> f64 {
+        self.0 * 1000.0
+    }
+}
+
+/// A density, stored in g/mL.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct Density(f64);
+
+impl Density {
+    pub fn grams_per_milliliter(density: f64) -> Self {
+        Density(density)
+    }
+
+    pub fn as_grams_per_milliliter(&self) -> f64 {
+        self.0
+    }
+}
+
+/// A concentration, stored in mol/L.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct Molarity(f64);
+
+impl Molarity {
+    pub fn molar(molar: f64) -> Self {
+        Molarity(molar)
+    }
+
+    pub fn millimolar(millimolar: f64) -> Self {
+        Molarity(millimolar / 1000.0)
+    }
+
+    pub fn as_molar(&self) -> f64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Mass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} g", self.0)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} mol", self.0)
+    }
+}
+
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} L", self.0)
+    }
+}
+
+impl fmt::Display for Density {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} g/mL", self.0)
+    }
+}
+
+impl fmt::Display for Molarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} M", self.0)
+    }
+}
+
+/// How much of a species is used, in any of the ways it is measured out.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Quantity {
+    Mass(Mass),
+    Amount(Amount),
+    /// A neat liquid measured by volume.
+    Liquid {
+        volume: Volume,
+        density: Density,
+    },
+    /// A volume of a solution of the species.
+    Solution {
+        volume: Volume,
+        molarity: Molarity,
+    },
+}
+
+impl Quantity {
+    /// The amount of a species with the given molecular weight in g/mol.
+    pub fn amount(&self, molecular_weight: f64) -> Amount {
+        match self {
+            Quantity::Mass(mass) => Amount(mass.as_grams() / molecular_weight),
+            Quantity::Amount(amount) => *amount,
+            Quantity::Liquid { volume, density } => Amount(
+                volume.as_milliliters() * density.as_grams_per_milliliter() / molecular_weight,
+            ),
+            Quantity::Solution { volume, molarity } => {
+                Amount(volume.as_liters() * molarity.as_molar())
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StoichiometryError {
+    /// The equation's coefficients don't conserve every element and the charge.
+    Unbalanced,
+    /// No reactant or product has a quantity to scale the reaction by.
+    NoQuantities,
+    /// A quantity was given for a reactant index past the end of the equation.
+    NoSuchReactant(usize),
+    /// A quantity was given for a product index past the end of the equation.
+    NoSuchProduct(usize),
+}
+
+impl fmt::Display for StoichiometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoichiometryError::Unbalanced => write!(f, "equation is not balanced"),
+            StoichiometryError::NoQuantities => write!(f, "no quantities given"),
+            StoichiometryError::NoSuchReactant(index) => write!(f, "no reactant {}", index),
+            StoichiometryError::NoSuchProduct(index) => write!(f, "no product {}", index),
+        }
+    }
+}
+
+impl std::error::Error for StoichiometryError {}
+
+/// An amount of a species along with its mass.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SpeciesAmount {
+    pub amount: Amount,
+    pub mass: Mass,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ReactantReport {
+    /// The amount used, if it was given.
+    pub given: Option<SpeciesAmount>,
+    /// The amount consumed by the reaction at its calculated scale.
+    pub required: SpeciesAmount,
+    /// Moles used relative to the limiting reagent, or required if not given. Coefficients are
+    /// not taken into account, as is usual for equivalents.
+    pub equivalents: f64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct StoichiometryReport {
+    /// The index of the reactant that runs out first, if any reactant quantities were given.
+    pub limiting_reagent: Option<usize>,
+    /// How many times the reaction as written takes place.
+    pub extent: Amount,
+    pub reactants: Vec<ReactantReport>,
+    /// The theoretical yield of each product.
+    pub products: Vec<SpeciesAmount>,
+}
+
+/// Plans a reaction from a balanced equation and quantities of some of its species.
+///
+/// The reaction's scale is set by the limiting reagent among the reactants with quantities. If
+/// only products have quantities, it's the scale that makes enough of each of them.
+#[derive(Clone, Debug)]
+pub struct Stoichiometry<'e> {
+    equation: &'e ChemicalEquation,
+    reactants: Vec<Option<Quantity>>,
+    products: Vec<Option<Quantity>>,
+    /// The first index given that isn't in the equation.
+    invalid: Option<StoichiometryError>,
+}
+
+impl<'e> Stoichiometry<'e> {
+    pub fn new(equation: &'e ChemicalEquation) -> Self {
+        Stoichiometry {
+            equation,
+            reactants: vec![None; equation.reactants().len()],
+            products: vec![None; equation.products().len()],
+            invalid: None,
+        }
+    }
+
+    /// Sets the quantity of the reactant at an index in the equation. An index past the end makes
+    /// the calculation fail.
+    pub fn reactant(mut self, index: usize, quantity: Quantity) -> Self {
+        match self.reactants.get_mut(index) {
+            Some(q) => *q = Some(quantity),
+            None => {
+                let error = StoichiometryError::NoSuchReactant(index);
+                self.invalid.get_or_insert(error);
+            }
+        }
+        self
+    }
+
+    /// Sets the quantity wanted of the product at an index in the equation. An index past the end
+    /// makes the calculation fail.
+    pub fn product(mut self, index: usize, quantity: Quantity) -> Self {
+        match self.products.get_mut(index) {
+            Some(q) => *q = Some(quantity),
+            None => {
+                let error = StoichiometryError::NoSuchProduct(index);
+                self.invalid.get_or_insert(error);
+            }
+        }
+        self
+    }
+
+    pub fn calculate(&self) -> Result<StoichiometryReport, StoichiometryError> {
+        if let Some(error) = &self.invalid {
+            return Err(error.clone());
+        }
+        if !self.equation.is_balanced() {
+            return Err(StoichiometryError::Unbalanced);
+        }
+        let reactants = amounts(&self.reactants, self.equation.reactants());
+        let products = amounts(&self.products, self.equation.products());
+
+        let limiting = extents(&reactants, self.equation.reactants())
+            .into_iter()
+            .fold(None, |min: Option<(usize, f64)>, (i, e)| match min {
+                Some((_, m)) if m <= e => min,
+                _ => Some((i, e)),
+            });
+        let (limiting_reagent, extent) = match limiting {
+            Some((i, extent)) => (Some(i), extent),
+            None => {
+                let extent = extents(&products, self.equation.products())
+                    .into_iter()
+                    .map(|(_, e)| e)
+                    .fold(None, |max: Option<f64>, e| {
+                        Some(max.map_or(e, |m| m.max(e)))
+                    })
+                    .ok_or(StoichiometryError::NoQuantities)?;
+                (None, extent)
+            }
+        };
+
+        // Equivalents are relative to the limiting reagent, or else the first reactant
+        let reference = match limiting_reagent {
+            Some(i) => reactants[i].unwrap().as_moles(),
+            None => self
+                .equation
+                .reactants()
+                .first()
+                .map_or(extent, |(n, _)| extent * *n as f64),
+        };
+        let reactant_reports = self
+            .equation
+            .reactants()
+            .iter()
+            .zip(&reactants)
+            .map(|((n, formula), given)| {
+                let required = extent * *n as f64;
+                let used = given.map_or(required, |a| a.as_moles());
+                ReactantReport {
+                    given: given.map(|a| species_amount(a.as_moles(), formula)),
+                    required: species_amount(required, formula),
+                    equivalents: used / reference,
+                }
+            })
+            .collect();
+        let product_reports = self
+            .equation
+            .products()
+            .iter()
+            .map(|(n, formula)| species_amount(extent * *n as f64, formula))
+            .collect();
+
+        Ok(StoichiometryReport {
+            limiting_reagent,
+            extent: Amount(extent),
+            reactants: reactant_reports,
+            products: product_reports,
+        })
+    }
+}
+
+fn amounts(
+    quantities: &[Option<Quantity>],
+    species: &[(u32, MolecularFormula)],
+) -> Vec<Option<Amount>> {
+    quantities
+        .iter()
+        .zip(species)
+        .map(|(q, (_, formula))| q.map(|q| q.amount(formula.molecular_weight())))
+        .collect()
+}
+
+/// The extent of reaction each species with an amount allows, by index.
+fn extents(amounts: &[Option<Amount>], species: &[(u32, MolecularFormula)]) -> Vec<(usize, f64)> {
+    amounts
+        .iter()
+        .zip(species)
+        .enumerate()
+        .filter_map(|(i, (a, (n, _)))| a.map(|a| (i, a.as_moles() / *n as f64)))
+        .collect()
+}
+
+fn species_amount(moles: f64, formula: &MolecularFormula) -> SpeciesAmount {
+    SpeciesAmount {
+        amount: Amount(moles),
+        mass: Mass(moles * formula.molecular_weight()),
+    }
+}
@@ -1,6 +1,7 @@
 // http://opensmiles.org/opensmiles.html
 
 pub use crate::core::Bond;
+pub use crate::core::Chirality;
 pub use crate::core::Element;
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -20,10 +21,12 @@ pub enum Symbol {
 pub struct Atom {
     pub isotope: Option<u16>,
     pub symbol: Symbol,
-    // chiral
+    pub chirality: Option<Chirality>,
     pub hydrogens: Option<u8>,
     pub charge: Option<i8>,
     pub atom_class: Option<usize>,
+    /// Whether the atom was written in brackets, in which case it has no implicit hydrogens.
+    pub bracketed: bool,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -1,5 +1,16 @@
 mod ast;
 mod parse;
+mod query;
+mod reaction;
+mod smarts;
+mod smiles;
+mod writer;
+
+pub use query::*;
+pub use reaction::*;
+pub use smarts::*;
+pub use smiles::*;
+pub use writer::*;
 
 #[cfg(test)]
 mod tests {
@@ -132,6 +143,16 @@ mod tests {
             test_case("42]", err("42]", nom::error::ErrorKind::Tag));
         }
 
+        #[test]
+        fn can_parse_chirality() {
+            let test_case = test_case(chirality);
+
+            test_case("@H", ok("H", Chirality::CounterClockwise));
+            test_case("@@H", ok("H", Chirality::Clockwise));
+            test_case("@@@", ok("@", Chirality::Clockwise));
+            test_case("H", err("H", ErrorKind::Tag));
+        }
+
         #[test]
         fn can_parse_isotope() {
             let test_case = test_case(isotope);
@@ -178,15 +199,18 @@ mod tests {
             hydrogens: Option<u8>,
             charge: Option<i8>,
             atom_class: Option<usize>,
+            bracketed: bool,
         ) -> IResult<&str, Atom> {
             super::ok(
                 rest,
                 Atom {
                     symbol,
                     isotope,
+                    chirality: None,
                     hydrogens,
                     charge,
                     atom_class,
+                    bracketed,
                 },
             )
         }
@@ -204,11 +228,20 @@ mod tests {
                     Some(3),
                     Some(1),
                     Some(2),
+                    true,
                 ),
             );
             test_case(
                 "[Br]",
-                ok("", Symbol::Element(Element::Br), None, None, None, None),
+                ok(
+                    "",
+                    Symbol::Element(Element::Br),
+                    None,
+                    None,
+                    None,
+                    None,
+                    true,
+                ),
             );
             test_case(
                 "[nH-2:35]CC",
@@ -219,6 +252,22 @@ mod tests {
                     Some(1),
                     Some(-2),
                     Some(35),
+                    true,
+                ),
+            );
+            test_case(
+                "[13C@@H]",
+                super::ok(
+                    "",
+                    Atom {
+                        symbol: Symbol::Element(Element::C),
+                        isotope: Some(13),
+                        chirality: Some(Chirality::Clockwise),
+                        hydrogens: Some(1),
+                        charge: None,
+                        atom_class: None,
+                        bracketed: true,
+                    },
                 ),
             );
             test_case("[1h+:1]", err("h+:1]", ErrorKind::Tag));
@@ -232,17 +281,44 @@ mod tests {
 
             test_case(
                 "Br",
-                ok("", Symbol::Element(Element::Br), None, None, None, None),
+                ok(
+                    "",
+                    Symbol::Element(Element::Br),
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                ),
             );
             test_case(
                 "OBr",
-                ok("Br", Symbol::Element(Element::O), None, None, None, None),
+                ok(
+                    "Br",
+                    Symbol::Element(Element::O),
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                ),
             );
             test_case(
                 "nCl",
-                ok("Cl", Symbol::Aromatic(Element::N), None, None, None, None),
+                ok(
+                    "Cl",
+                    Symbol::Aromatic(Element::N),
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                ),
+            );
+            test_case(
+                "*C",
+                ok("C", Symbol::Wildcard, None, None, None, None, false),
             );
-            test_case("*C", ok("C", Symbol::Wildcard, None, None, None, None));
         }
 
         #[test]
@@ -258,11 +334,20 @@ mod tests {
                     Some(3),
                     Some(1),
                     Some(2),
+                    true,
                 ),
             );
             test_case(
                 "OBr",
-                ok("Br", Symbol::Element(Element::O), None, None, None, None),
+                ok(
+                    "Br",
+                    Symbol::Element(Element::O),
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                ),
             );
         }
     }
@@ -276,9 +361,11 @@ mod tests {
                 atom: Atom {
                     isotope: None,
                     symbol: Symbol::Element(element),
+                    chirality: None,
                     hydrogens: None,
                     charge: None,
                     atom_class: None,
+                    bracketed: false,
                 },
                 ring_bonds: vec![],
                 branches: vec![],
@@ -443,9 +530,11 @@ mod tests {
                         atom: Atom {
                             isotope: None,
                             symbol: Symbol::Element(Element::N),
+                            chirality: None,
                             charge: Some(1),
                             hydrogens: None,
                             atom_class: None,
+                            bracketed: true,
                         },
                         ring_bonds: vec![],
                         branches: vec![
@@ -473,9 +562,11 @@ mod tests {
                         atom: Atom {
                             isotope: None,
                             symbol: Symbol::Element(Element::C),
+                            chirality: None,
                             charge: None,
                             hydrogens: None,
                             atom_class: None,
+                            bracketed: false,
                         },
                         ring_bonds: vec![
                             RingBond {
@@ -493,4 +584,604 @@ mod tests {
             );
         }
     }
+
+    mod molecule {
+        use crate::core::{AtomIndex, Bond, Element, Molecule};
+        use crate::daylight::*;
+
+        fn smiles(s: &str) -> Molecule {
+            molecule_from_smiles(s).expect("Invalid SMILES")
+        }
+
+        fn hydrogens(mol: &Molecule) -> Vec<u8> {
+            mol.atoms().map(|a| mol.atom(a).hydrogens).collect()
+        }
+
+        #[test]
+        fn can_build_chains_and_branches() {
+            let mol = smiles("CC(=O)OC");
+            assert_eq!(mol.atom_count(), 5);
+            assert_eq!(mol.bond_count(), 4);
+            assert_eq!(
+                mol.bond(AtomIndex::new(1), AtomIndex::new(2)),
+                Some(Bond::Double)
+            );
+            assert_eq!(
+                mol.bond(AtomIndex::new(1), AtomIndex::new(3)),
+                Some(Bond::Single)
+            );
+            assert_eq!(hydrogens(&mol), vec![3, 0, 0, 0, 3]);
+
+            // Atoms are numbered in the order they are written
+            let mol = smiles("N(C)(CC)O");
+            assert_eq!(mol.atom(AtomIndex::new(3)).element, Element::C);
+            assert_eq!(mol.atom(AtomIndex::new(4)).element, Element::O);
+            assert_eq!(mol.degree(AtomIndex::new(0)), 3);
+            assert_eq!(hydrogens(&mol), vec![0, 3, 2, 3, 1]);
+        }
+
+        #[test]
+        fn bracket_atoms_have_no_implicit_hydrogens() {
+            assert_eq!(hydrogens(&smiles("[Cl]")), vec![0]);
+            assert_eq!(hydrogens(&smiles("[C]")), vec![0]);
+            assert_eq!(hydrogens(&smiles("C[O]")), vec![3, 0]);
+            assert_eq!(smiles("[Cl]").formula().to_string(), "Cl");
+            assert_eq!(smiles("[Na+].[Cl-]"), smiles("[Cl-].[Na+]"));
+        }
+
+        #[test]
+        fn can_build_rings() {
+            let mol = smiles("C1CC=1");
+            assert_eq!(mol.bond_count(), 3);
+            assert_eq!(
+                mol.bond(AtomIndex::new(0), AtomIndex::new(2)),
+                Some(Bond::Double)
+            );
+            assert_eq!(hydrogens(&mol), vec![1, 2, 1]);
+
+            let benzene = smiles("c1ccccc1");
+            assert_eq!(benzene.bond_count(), 6);
+            assert!(benzene.bonds().all(|(_, _, bond)| bond == Bond::Aromatic));
+            assert_eq!(hydrogens(&benzene), vec![1; 6]);
+            assert_eq!(smiles("c1ccncc1").atom(AtomIndex::new(3)).hydrogens, 0);
+            assert_eq!(smiles("c1cc[nH]c1").atom(AtomIndex::new(3)).hydrogens, 1);
+            // Atoms whose bonds fill their valence give a lone pair to the ring
+            assert_eq!(hydrogens(&smiles("s1cccc1")), vec![0, 1, 1, 1, 1]);
+            assert_eq!(hydrogens(&smiles("Cn1cccc1")), vec![3, 0, 1, 1, 1, 1]);
+            assert_eq!(hydrogens(&smiles("o1cccc1")), vec![0, 1, 1, 1, 1]);
+
+            // Ring bond numbers can be reused once closed
+            assert_eq!(smiles("C1CC1C1CC1").bond_count(), 7);
+            assert_eq!(smiles("C%12CC%12").bond_count(), 3);
+        }
+
+        #[test]
+        fn can_build_bracket_atoms() {
+            let mol = smiles("[13CH3:7][N+](C)(C)C.[Cl-]");
+            let c = mol.atom(AtomIndex::new(0));
+            assert_eq!(c.isotope, Some(13));
+            assert_eq!(c.hydrogens, 3);
+            assert_eq!(c.map, Some(7));
+            assert_eq!(mol.atom(AtomIndex::new(1)).formal_charge, 1);
+            assert_eq!(mol.atom(AtomIndex::new(1)).hydrogens, 0);
+            assert_eq!(mol.atom(AtomIndex::new(5)).formal_charge, -1);
+            assert_eq!(mol.components().len(), 2);
+        }
+
+        #[test]
+        fn can_build_stereocentres() {
+            use crate::core::Chirality::*;
+            let chirality = |s: &str, atom: usize| smiles(s).atom(AtomIndex::new(atom)).chirality;
+
+            // The implicit hydrogen comes first in the core model, which swaps it with the N
+            assert_eq!(chirality("N[C@@H](C)C(=O)O", 1), Some(CounterClockwise));
+            // The same centre with its branches written the other way round
+            assert_eq!(chirality("N[C@H](C(=O)O)C", 1), Some(Clockwise));
+            assert_eq!(chirality("[C@@H](N)(C)C(=O)O", 0), Some(Clockwise));
+            // A ring bond is a neighbour where its number is written, not where it closes
+            assert_eq!(chirality("[C@@]1(Cl)(F)CCC1", 0), Some(CounterClockwise));
+            assert_eq!(chirality("CC", 0), None);
+
+            // Reordering atoms keeps the same configuration
+            let mol = smiles("N[C@@H](C)C(=O)O");
+            let mut reversed: Vec<AtomIndex> = mol.atoms().collect();
+            reversed.reverse();
+            assert_eq!(
+                mol.subgraph(&reversed).atom(AtomIndex::new(4)).chirality,
+                Some(Clockwise)
+            );
+        }
+
+        #[test]
+        fn stereocentres_are_compared_by_configuration() {
+            // L-alanine written from different ends
+            let l_alanine = smiles("N[C@@H](C)C(=O)O");
+            assert_eq!(l_alanine, smiles("C[C@H](N)C(=O)O"));
+            assert_eq!(l_alanine, smiles("OC(=O)[C@@H](N)C"));
+            assert_eq!(l_alanine, smiles("[C@@H](N)(C(=O)O)C"));
+            // D-alanine
+            assert!(l_alanine != smiles("C[C@@H](N)C(=O)O"));
+            assert!(l_alanine != smiles("N[C@H](C)C(=O)O"));
+            assert!(l_alanine != smiles("NC(C)C(=O)O"));
+
+            // Swapping two of its neighbours inverts a centre, and inverting it again undoes that
+            let mol = smiles("F[C@](Cl)(Br)I");
+            assert!(mol != smiles("F[C@](Br)(Cl)I"));
+            assert_eq!(mol, smiles("F[C@@](Br)(Cl)I"));
+            assert_eq!(mol, smiles("I[C@@](Cl)(Br)F"));
+        }
+
+        #[test]
+        fn symmetric_molecules_are_compared_quickly() {
+            // Buckminsterfullerene, renumbered from the other end
+            let c60 = smiles(
+                "c12c3c4c5c1c1c6c7c2c2c8c3c3c9c4c4c%10c5c5c1c1c6c6c%11c7c2c2c7c8c3c3c8c9c4c4c9c%10\
+                c5c5c1c1c6c6c%11c2c2c7c3c3c8c4c4c9c5c1c1c6c2c3c41",
+            );
+            let mut atoms: Vec<AtomIndex> = c60.atoms().collect();
+            atoms.reverse();
+            assert_eq!(c60, c60.subgraph(&atoms));
+            // Every atom looks alike to its neighbours in both, but one ring isn't two
+            let ring = |n: usize| format!("C1{}C1", "C".repeat(n - 2));
+            assert!(smiles(&ring(30)) != smiles(&format!("{}.{}", ring(14), ring(16))));
+        }
+
+        #[test]
+        fn can_fill_higher_valences() {
+            // Sulfuric acid and a hypervalent phosphorus
+            assert_eq!(hydrogens(&smiles("OS(=O)(=O)O")), vec![1, 0, 0, 0, 1]);
+            assert_eq!(smiles("P(=O)(C)C").atom(AtomIndex::new(0)).hydrogens, 1);
+            assert_eq!(smiles("C=S(=O)").atom(AtomIndex::new(1)).hydrogens, 0);
+        }
+
+        #[test]
+        fn can_reject_invalid_smiles() {
+            assert_eq!(
+                molecule_from_smiles("C1CC"),
+                Err(SmilesError::UnclosedRing(1))
+            );
+            assert_eq!(
+                molecule_from_smiles("C=1CC#1"),
+                Err(SmilesError::ConflictingRingBond(1))
+            );
+            assert_eq!(
+                molecule_from_smiles("C1C1"),
+                Err(SmilesError::DuplicateBond)
+            );
+            assert_eq!(molecule_from_smiles("CC*"), Err(SmilesError::Wildcard));
+            assert_eq!(molecule_from_smiles("CC)C"), Err(SmilesError::Syntax(2)));
+            assert_eq!(molecule_from_smiles(""), Err(SmilesError::Syntax(0)));
+        }
+    }
+
+    mod writer {
+        use crate::core::Molecule;
+        use crate::daylight::*;
+
+        fn smiles(s: &str) -> Molecule {
+            molecule_from_smiles(s).expect("Invalid SMILES")
+        }
+
+        fn round_trip(s: &str) -> String {
+            molecule_to_smiles(&smiles(s))
+        }
+
+        #[test]
+        fn can_write_smiles() {
+            assert_eq!(round_trip("CC(=O)OC"), "CC(=O)OC");
+            assert_eq!(round_trip("N(C)(CC)O"), "N(C)(CC)O");
+            assert_eq!(round_trip("C#N"), "C#N");
+            assert_eq!(round_trip("CCO.[Na+].[Cl-]"), "CCO.[Na+].[Cl-]");
+            assert_eq!(round_trip("[13CH3:7][N+](C)(C)C"), "[13CH3:7][N+](C)(C)C");
+            assert_eq!(round_trip("[O-2]"), "[O-2]");
+            assert_eq!(round_trip("[Fe+3]"), "[Fe+3]");
+            // Hydrogen counts the parser would not infer need brackets
+            assert_eq!(round_trip("[CH2]C"), "[CH2]C");
+            assert_eq!(round_trip("[CH3]C"), "CC");
+            assert_eq!(round_trip("[NH4+]"), "[NH4+]");
+        }
+
+        #[test]
+        fn can_write_rings() {
+            assert_eq!(round_trip("C1CCCCC1"), "C1CCCCC1");
+            assert_eq!(round_trip("c1ccccc1"), "c1ccccc1");
+            assert_eq!(round_trip("c1cc[nH]c1"), "c1cc[nH]c1");
+            // Single bonds between aromatic atoms are written out
+            assert_eq!(round_trip("c1ccccc1-c1ccccc1"), "c1ccccc1-c1ccccc1");
+            assert_eq!(round_trip("C12CC1CC2"), "C12CC1CC2");
+
+            // Ring numbers are reused once closed
+            let written = round_trip("C1CC1CC1CC1");
+            assert_eq!(written, "C1CC1CC1CC1");
+        }
+
+        #[test]
+        fn can_round_trip_molecules() {
+            for s in &[
+                "CC(=O)Oc1ccccc1C(=O)O",
+                "CN1C=NC2=C1C(=O)N(C(=O)N2C)C",
+                "C1CC2CCC1C2",
+                "OC(=O)C1CCC(CC1)N",
+                "C[N+](C)(C)CC(=O)[O-]",
+                "c1ccc2ccccc2c1",
+                "N[C@@H](C)C(=O)O",
+                "N[C@H](C(=O)O)C",
+                "[C@@]1(Cl)(F)CCC1",
+                "F[C@](Cl)(Br)I",
+                "C[C@H]1CC[C@@H](O)CC1",
+            ] {
+                let mol = smiles(s);
+                let written = molecule_to_smiles(&mol);
+                assert_eq!(smiles(&written), mol, "{} was written as {}", s, written);
+                // Stereocentres keep their configuration, not just their presence
+                let chirality =
+                    |m: &Molecule| m.atoms().map(|a| m.atom(a).chirality).collect::<Vec<_>>();
+                assert_eq!(
+                    chirality(&smiles(&written)),
+                    chirality(&mol),
+                    "{} was written as {}",
+                    s,
+                    written
+                );
+            }
+        }
+
+        #[test]
+        fn can_write_canonical_smiles() {
+            let canonical = |s: &str| molecule_to_canonical_smiles(&smiles(s));
+            for group in &[
+                vec!["CCO", "OCC", "C(O)C"],
+                vec!["CC(C)O", "OC(C)C", "C(C)(C)O"],
+                vec!["c1ccncc1", "n1ccccc1", "c1cnccc1"],
+                vec!["CC(=O)Oc1ccccc1C(=O)O", "OC(=O)c1ccccc1OC(C)=O"],
+                vec!["F[C@H](Cl)Br", "Cl[C@@H](F)Br", "Br[C@@H](Cl)F"],
+                vec!["CCO.[Na+].[Cl-]", "[Cl-].OCC.[Na+]"],
+            ] {
+                let first = canonical(group[0]);
+                for s in group {
+                    assert_eq!(canonical(s), first, "{}", s);
+                }
+                assert_eq!(smiles(&first), smiles(group[0]));
+            }
+            assert_ne!(canonical("F[C@H](Cl)Br"), canonical("F[C@@H](Cl)Br"));
+        }
+    }
+
+    mod reaction {
+        use crate::core::{AtomIndex, ReactionAtom};
+        use crate::daylight::*;
+
+        #[test]
+        fn can_parse_reaction_smiles() {
+            let reaction = reaction_from_smiles("CC(=O)O.OCC>[H+]>CC(=O)OCC.O").unwrap();
+            assert_eq!(reaction.reactants.len(), 2);
+            assert_eq!(reaction.agents.len(), 1);
+            assert_eq!(reaction.products.len(), 2);
+            assert_eq!(reaction.products[0].atom_count(), 6);
+            assert!(!reaction.is_mapped());
+
+            let reaction = reaction_from_smiles(">>CC").unwrap();
+            assert!(reaction.reactants.is_empty());
+            assert!(reaction.agents.is_empty());
+            assert_eq!(reaction.products.len(), 1);
+        }
+
+        #[test]
+        fn can_map_atoms_across_the_arrow() {
+            let reaction =
+                reaction_from_smiles("[CH3:1][C:2](=[O:3])[OH:4].[OH:5][CH3:6]>>[CH3:1][C:2](=[O:3])[O:5][CH3:6].[OH2:4]")
+                    .unwrap();
+            assert!(reaction.is_mapped());
+            let mapping = reaction.atom_mapping();
+            assert_eq!(mapping.len(), 6);
+            let atom = |molecule, atom| ReactionAtom {
+                molecule,
+                atom: AtomIndex::new(atom),
+            };
+            assert_eq!(mapping[3], (atom(0, 3), atom(1, 0)));
+            assert_eq!(mapping[4], (atom(1, 0), atom(0, 3)));
+        }
+
+        #[test]
+        fn can_write_reaction_smiles() {
+            for s in &[
+                "CC(=O)O.OCC>[H+]>CC(=O)OCC.O",
+                "[CH3:1][Br:2].[OH-:3]>>[CH3:1][OH:3].[Br-:2]",
+                ">>CC",
+                "C=C.[H][H]>[Pd]>CC",
+            ] {
+                let reaction = reaction_from_smiles(s).unwrap();
+                assert_eq!(&reaction_to_smiles(&reaction), s);
+            }
+        }
+
+        #[test]
+        fn can_reject_invalid_reaction_smiles() {
+            assert_eq!(reaction_from_smiles("CC>>C)C"), Err(SmilesError::Syntax(5)));
+            assert_eq!(reaction_from_smiles("CC>C"), Err(SmilesError::Syntax(4)));
+            assert_eq!(reaction_from_smiles("C>C>C>C"), Err(SmilesError::Syntax(5)));
+            assert_eq!(
+                reaction_from_smiles("C1C>>C"),
+                Err(SmilesError::UnclosedRing(1))
+            );
+        }
+    }
+
+    mod smarts {
+        use crate::core::{AtomIndex, Chirality, Element};
+        use crate::daylight::*;
+
+        fn smarts(s: &str) -> Query {
+            query_from_smarts(s).expect("Invalid SMARTS")
+        }
+
+        fn expr(s: &str, atom: usize) -> AtomExpr {
+            smarts(s).atom(AtomIndex::new(atom)).expr.clone()
+        }
+
+        fn p(primitive: AtomPrimitive) -> AtomExpr {
+            Expr::Primitive(primitive)
+        }
+
+        fn symbol(element: Element, aromatic: bool) -> AtomExpr {
+            p(AtomPrimitive::Symbol { element, aromatic })
+        }
+
+        #[test]
+        fn can_parse_primitives() {
+            use AtomPrimitive::*;
+            assert_eq!(expr("C", 0), symbol(Element::C, false));
+            assert_eq!(expr("c", 0), symbol(Element::C, true));
+            assert_eq!(expr("[#6]", 0), p(AtomicNumber(6)));
+            assert_eq!(expr("*", 0), p(Any));
+            assert_eq!(expr("a", 0), p(Aromatic));
+            assert_eq!(expr("[A]", 0), p(Aliphatic));
+            assert_eq!(
+                expr("[NX3H2]", 0),
+                Expr::And(vec![
+                    symbol(Element::N, false),
+                    p(Connectivity(3)),
+                    p(TotalHydrogens(2)),
+                ])
+            );
+            assert_eq!(
+                expr("[Nv3]", 0),
+                Expr::And(vec![symbol(Element::N, false), p(Valence(3))])
+            );
+            assert_eq!(
+                expr("[D2R]", 0),
+                Expr::And(vec![p(Degree(2)), p(RingMembership(None))])
+            );
+            assert_eq!(
+                expr("[r5x2]", 0),
+                Expr::And(vec![p(RingSize(Some(5))), p(RingConnectivity(Some(2)))])
+            );
+            assert_eq!(
+                expr("[13C-2]", 0),
+                Expr::And(vec![
+                    p(Isotope(13)),
+                    symbol(Element::C, false),
+                    p(Charge(-2))
+                ])
+            );
+            assert_eq!(
+                expr("[N++]", 0),
+                Expr::And(vec![symbol(Element::N, false), p(Charge(2))])
+            );
+            assert_eq!(
+                expr("[v4h]", 0),
+                Expr::And(vec![p(Valence(4)), p(ImplicitHydrogens(None))])
+            );
+        }
+
+        #[test]
+        fn can_parse_element_symbols() {
+            use AtomPrimitive::*;
+            // Two letter symbols win over a one letter primitive followed by another
+            assert_eq!(expr("[Cl]", 0), symbol(Element::Cl, false));
+            assert_eq!(expr("[Al]", 0), symbol(Element::Al, false));
+            assert_eq!(expr("[se]", 0), symbol(Element::Se, true));
+            assert_eq!(
+                expr("[CH]", 0),
+                Expr::And(vec![symbol(Element::C, false), p(TotalHydrogens(1))])
+            );
+            assert_eq!(
+                expr("[nH]", 0),
+                Expr::And(vec![symbol(Element::N, true), p(TotalHydrogens(1))])
+            );
+            // A lone H is a hydrogen atom, not a hydrogen count
+            assert_eq!(expr("[H]", 0), symbol(Element::H, false));
+            assert_eq!(
+                expr("[2H+]", 0),
+                Expr::And(vec![symbol(Element::H, false), p(Isotope(2)), p(Charge(1))])
+            );
+        }
+
+        #[test]
+        fn can_parse_logical_operators() {
+            use AtomPrimitive::*;
+            let c = || symbol(Element::C, false);
+            let n = || symbol(Element::N, false);
+            let o = || symbol(Element::O, false);
+
+            assert_eq!(expr("[!C]", 0), Expr::Not(Box::new(c())));
+            assert_eq!(expr("[C,N]", 0), Expr::Or(vec![c(), n()]));
+            assert_eq!(expr("[C&H3]", 0), expr("[CH3]", 0));
+            // `&` binds tighter than `,`, which binds tighter than `;`
+            assert_eq!(
+                expr("[C,N&H1]", 0),
+                Expr::Or(vec![c(), Expr::And(vec![n(), p(TotalHydrogens(1))])])
+            );
+            assert_eq!(
+                expr("[C,N;H1]", 0),
+                Expr::And(vec![Expr::Or(vec![c(), n()]), p(TotalHydrogens(1))])
+            );
+            assert_eq!(
+                expr("[!C;!N,O]", 0),
+                Expr::And(vec![
+                    Expr::Not(Box::new(c())),
+                    Expr::Or(vec![Expr::Not(Box::new(n())), o()]),
+                ])
+            );
+            assert_eq!(expr("[C:3]", 0), c());
+            assert_eq!(smarts("[C:3]").atom(AtomIndex::new(0)).map, Some(3));
+        }
+
+        #[test]
+        fn can_parse_bonds() {
+            use BondPrimitive::*;
+            let bond = |s: &str, a: usize, b: usize| {
+                smarts(s)
+                    .bond(AtomIndex::new(a), AtomIndex::new(b))
+                    .cloned()
+            };
+
+            assert_eq!(bond("CC", 0, 1), Some(Expr::Primitive(Implicit)));
+            assert_eq!(bond("C=C", 0, 1), Some(Expr::Primitive(Double)));
+            assert_eq!(bond("C~C", 0, 1), Some(Expr::Primitive(Any)));
+            assert_eq!(
+                bond("C!:C", 0, 1),
+                Some(Expr::Not(Box::new(Expr::Primitive(Aromatic))))
+            );
+            assert_eq!(
+                bond("C-@C", 0, 1),
+                Some(Expr::And(vec![
+                    Expr::Primitive(Single),
+                    Expr::Primitive(Ring)
+                ]))
+            );
+            assert_eq!(
+                bond("C=,#C", 0, 1),
+                Some(Expr::Or(vec![
+                    Expr::Primitive(Double),
+                    Expr::Primitive(Triple)
+                ]))
+            );
+            assert_eq!(bond("C1CC@1", 0, 2), Some(Expr::Primitive(Ring)));
+            assert_eq!(bond("C(=O)O", 0, 1), Some(Expr::Primitive(Double)));
+            assert_eq!(bond("C.C", 0, 1), None);
+        }
+
+        #[test]
+        fn can_parse_recursive_smarts() {
+            let query = smarts("[$(CO),$(C=O)]N");
+            assert_eq!(query.atom_count(), 2);
+            match &query.atom(AtomIndex::new(0)).expr {
+                Expr::Or(exprs) => {
+                    assert_eq!(
+                        exprs[0],
+                        Expr::Primitive(AtomPrimitive::Recursive(Box::new(smarts("CO"))))
+                    );
+                    assert_eq!(
+                        exprs[1],
+                        Expr::Primitive(AtomPrimitive::Recursive(Box::new(smarts("C=O"))))
+                    );
+                }
+                expr => panic!("Unexpected expression {:?}", expr),
+            }
+            // Recursion can be nested
+            assert_eq!(smarts("[$([C;$(C=O)]O)]").atom_count(), 1);
+        }
+
+        #[test]
+        fn can_parse_stereocentres() {
+            use AtomPrimitive::TotalHydrogens;
+            // The same reordering as in SMILES, with the hydrogen count taking the implicit H
+            assert_eq!(
+                expr("N[C@@H](C)C(=O)O", 1),
+                Expr::And(vec![
+                    symbol(Element::C, false),
+                    p(AtomPrimitive::Chirality(Chirality::CounterClockwise)),
+                    p(TotalHydrogens(1)),
+                ])
+            );
+            assert_eq!(
+                expr("[C@@](F)(Cl)(Br)I", 0),
+                Expr::And(vec![
+                    symbol(Element::C, false),
+                    p(AtomPrimitive::Chirality(Chirality::Clockwise))
+                ])
+            );
+        }
+
+        #[test]
+        fn can_merge_hydrogens() {
+            let merged = smarts("[#6](-[#1])(~[H])=O").merge_hydrogens();
+            assert_eq!(merged.atom_count(), 2);
+            assert_eq!(
+                merged.atom(AtomIndex::new(0)).expr,
+                Expr::And(vec![
+                    p(AtomPrimitive::AtomicNumber(6)),
+                    Expr::Not(Box::new(p(AtomPrimitive::TotalHydrogens(0)))),
+                    Expr::Not(Box::new(p(AtomPrimitive::TotalHydrogens(1)))),
+                ])
+            );
+            // A bond that not every bond to a hydrogen matches is kept, with its hydrogen
+            let merged = smarts("[#6](@[#1])=O").merge_hydrogens();
+            assert_eq!(merged.atom_count(), 3);
+            assert_eq!(merged.bond_count(), 2);
+        }
+
+        #[test]
+        fn can_reject_invalid_smarts() {
+            assert_eq!(query_from_smarts("C1CC"), Err(SmartsError::UnclosedRing(1)));
+            assert_eq!(
+                query_from_smarts("C=1CC#1"),
+                Err(SmartsError::ConflictingRingBond(1))
+            );
+            assert_eq!(query_from_smarts("C1C1"), Err(SmartsError::DuplicateBond));
+            assert_eq!(query_from_smarts("[C"), Err(SmartsError::Syntax(0)));
+            assert_eq!(query_from_smarts("CC)"), Err(SmartsError::Syntax(2)));
+            assert_eq!(query_from_smarts(""), Err(SmartsError::Syntax(0)));
+        }
+
+        #[test]
+        fn can_write_smarts() {
+            let write = |s: &str| query_to_smarts(&smarts(s));
+            assert_eq!(write("CC(=O)O"), "[C][C](=[O])[O]");
+            assert_eq!(write("[C;H0;D3;+0:1]-c"), "[C;H0;D3;+0:1]-[c]");
+            assert_eq!(write("[H][Cl]"), "[#1][Cl]");
+            assert_eq!(write("C~1~*@N1"), "[C]~1~[*]@[N]1");
+            // Negations are pushed onto primitives, and nesting SMARTS can't express is expanded
+            assert_eq!(write("[!$(C=O)]"), "[!$([C]=[O])]");
+            assert_eq!(write("[C,N;!R]"), "[C,N;!R]");
+            assert_eq!(write("[!C,N]"), "[!C,N]");
+            assert_eq!(write("[!C&!N;X3]"), "[!C&!N;X3]");
+            assert_eq!(write("[!C&!N,X3]"), "[!C&!N,X3]");
+            // Implicit bonds are left implicit, or spelled out within a bond expression
+            assert_eq!(write("C!=C"), "[C]!=[C]");
+            // Only a hydrogen the atom must have is written as a neighbour
+            assert_eq!(write("F[C@@;!H1](Cl)(Br)I"), "[F][C&@@;!H1]([Cl])([Br])[I]");
+
+            // Aromatic elements without a symbol of their own negate as a pair
+            let mut query = Query::new();
+            query.add_atom(QueryAtom {
+                expr: Expr::Not(Box::new(Expr::Primitive(AtomPrimitive::Symbol {
+                    element: Element::Te,
+                    aromatic: true,
+                }))),
+                map: None,
+            });
+            assert_eq!(query_to_smarts(&query), "[!#52,!a]");
+        }
+
+        #[test]
+        fn can_round_trip_queries() {
+            for s in &[
+                "[CX3](=O)[OX2H1]",
+                "[#6;R2]@[#7;!H0]",
+                "[$([NH2]C),$(N(C)C)]",
+                "c1ccc2ccccc2c1",
+                "[O-,N+;h1,r5,x2]/C=C/[v4&D2]",
+                "N[C@@H](C)C(=O)O",
+                "[C@](F)(Cl)(Br)I",
+                "[2#6+].[Se;a]",
+                "[13C;+2:7]#[N:8]",
+            ] {
+                let query = smarts(s);
+                let written = query_to_smarts(&query);
+                assert_eq!(smarts(&written), query, "{} was written as {}", s, written);
+            }
+        }
+    }
 }
@@ -5,7 +5,7 @@ use nom::{
     character::complete::{digit1, satisfy},
     combinator::{map_res, opt, recognize, success, value},
     error::ParseError,
-    multi::{fold_many0, many0, many_m_n},
+    multi::{fold_many0, many0, many_m_n, separated_list1},
     sequence::{pair, preceded, terminated, tuple},
     AsChar, Compare, IResult, InputIter, InputLength, InputTake, Offset, Parser, Slice,
 };
@@ -91,17 +91,21 @@ pub fn organic_symbol(input: &str) -> IResult<&str, Symbol> {
 
 pub fn symbol(input: &str) -> IResult<&str, Symbol> {
     let ar = |el, t| value(Symbol::Aromatic(el), tag(t));
+    let element = |el: &str| -> Result<Symbol, ()> {
+        let el: Element = el.parse()?;
+        Ok(Symbol::Element(el))
+    };
     alt((
+        // A letter that doesn't make a two letter symbol may be followed by a SMARTS primitive,
+        // as in `[Nv3]`
         map_res(
-            recognize(tuple((
+            recognize(pair(
                 satisfy(|c| c.is_ascii_uppercase()),
-                opt(satisfy(|c| c.is_ascii_lowercase())),
-            ))),
-            |el: &str| -> Result<Symbol, ()> {
-                let el: Element = el.parse()?;
-                Ok(Symbol::Element(el))
-            },
+                satisfy(|c| c.is_ascii_lowercase()),
+            )),
+            element,
         ),
+        map_res(recognize(satisfy(|c| c.is_ascii_uppercase())), element),
         ar(Element::Se, "se"),
         ar(Element::As, "as"),
         ar(Element::B, "b"),
@@ -114,6 +118,13 @@ pub fn symbol(input: &str) -> IResult<&str, Symbol> {
     ))(input)
 }
 
+pub fn chirality(input: &str) -> IResult<&str, Chirality> {
+    alt((
+        value(Chirality::Clockwise, tag("@@")),
+        value(Chirality::CounterClockwise, tag("@")),
+    ))(input)
+}
+
 pub fn atom_class(input: &str) -> IResult<&str, usize> {
     map_res(preceded(tag(":"), digit1), |num_str: &str| {
         num_str.parse::<usize>()
@@ -132,7 +143,7 @@ pub fn bracket_atom(input: &str) -> IResult<&str, Atom> {
                 tuple((
                     opt(isotope),
                     symbol,
-                    // opt chiral
+                    opt(chirality),
                     opt(hydrogens),
                     opt(charge),
                     opt(atom_class),
@@ -140,13 +151,15 @@ pub fn bracket_atom(input: &str) -> IResult<&str, Atom> {
                 tag("]"),
             ),
         ),
-        |(isotope, symbol, hydrogens, charge, atom_class)| -> Result<Atom, ()> {
+        |(isotope, symbol, chirality, hydrogens, charge, atom_class)| -> Result<Atom, ()> {
             Ok(Atom {
                 isotope,
                 symbol,
+                chirality,
                 hydrogens,
                 charge,
                 atom_class,
+                bracketed: true,
             })
         },
     )(input)
@@ -157,9 +170,11 @@ pub fn organic_atom(input: &str) -> IResult<&str, Atom> {
         Ok(Atom {
             symbol,
             isotope: None,
+            chirality: None,
             charge: None,
             atom_class: None,
             hydrogens: None,
+            bracketed: false,
         })
     })(input)
 }
@@ -224,3 +239,8 @@ pub fn branch(input: &str) -> IResult<&str, Branch> {
         |(bond, chain): (Option<Bond>, Chain)| -> Result<Branch, ()> { Ok(Branch { bond, chain }) },
     )(input)
 }
+
+/// A full SMILES string: one or more chains separated by dots.
+pub fn smiles(input: &str) -> IResult<&str, Vec<Chain>> {
+    separated_list1(tag("."), chain)(input)
+}
@@ -0,0 +1,378 @@
+use crate::core::{AtomIndex, Bond, Chirality, Element, Molecule};
+use petgraph::prelude::*;
+
+/// A logical expression over query primitives.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expr<T> {
+    Primitive(T),
+    Not(Box<Expr<T>>),
+    And(Vec<Expr<T>>),
+    Or(Vec<Expr<T>>),
+}
+
+impl<T> Expr<T> {
+    pub(crate) fn and(mut exprs: Vec<Expr<T>>) -> Self {
+        if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::And(exprs)
+        }
+    }
+
+    pub(crate) fn or(mut exprs: Vec<Expr<T>>) -> Self {
+        if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::Or(exprs)
+        }
+    }
+
+    /// Evaluates the expression with `test` deciding each primitive.
+    pub fn matches(&self, test: &mut impl FnMut(&T) -> bool) -> bool {
+        match self {
+            Expr::Primitive(p) => test(p),
+            Expr::Not(e) => !e.matches(test),
+            Expr::And(es) => es.iter().all(|e| e.matches(test)),
+            Expr::Or(es) => es.iter().any(|e| e.matches(test)),
+        }
+    }
+
+    /// Evaluates the expression where `test` may leave some primitives undecided, as `None`. The
+    /// result is only decided if it is the same however those turn out.
+    pub(crate) fn may_match(&self, test: &mut impl FnMut(&T) -> Option<bool>) -> Option<bool> {
+        match self {
+            Expr::Primitive(p) => test(p),
+            Expr::Not(e) => e.may_match(test).map(|m| !m),
+            Expr::And(es) => {
+                let mut result = Some(true);
+                for e in es {
+                    match e.may_match(test) {
+                        Some(false) => return Some(false),
+                        Some(true) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            Expr::Or(es) => {
+                let mut result = Some(false);
+                for e in es {
+                    match e.may_match(test) {
+                        Some(true) => return Some(true),
+                        Some(false) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+        }
+    }
+
+    /// Whether the expression can only match where `primitive` does.
+    pub(crate) fn requires(&self, primitive: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let mut test = |p: &T| if p == primitive { Some(false) } else { None };
+        self.may_match(&mut test) == Some(false)
+    }
+
+    /// Iterates over every primitive in the expression, regardless of the logic around it.
+    pub fn primitives(&self) -> Vec<&T> {
+        match self {
+            Expr::Primitive(p) => vec![p],
+            Expr::Not(e) => e.primitives(),
+            Expr::And(es) | Expr::Or(es) => es.iter().flat_map(|e| e.primitives()).collect(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum AtomPrimitive {
+    /// `*`
+    Any,
+    /// `a`
+    Aromatic,
+    /// `A`
+    Aliphatic,
+    /// An element symbol, e.g. `C` for aliphatic or `c` for aromatic carbon.
+    Symbol { element: Element, aromatic: bool },
+    /// `#<n>`, an element either aromatic or not.
+    AtomicNumber(u8),
+    /// `D<n>`, the number of explicit connections.
+    Degree(u8),
+    /// `X<n>`, the number of connections including implicit hydrogens.
+    Connectivity(u8),
+    /// `H<n>`, the number of attached hydrogens.
+    TotalHydrogens(u8),
+    /// `h<n>`, the number of implicit hydrogens. Without a count, at least one.
+    ImplicitHydrogens(Option<u8>),
+    /// `R<n>`, the number of SSSR rings the atom is in. Without a count, any ring.
+    RingMembership(Option<u8>),
+    /// `r<n>`, in an SSSR ring of this size. Without a size, any ring.
+    RingSize(Option<u8>),
+    /// `x<n>`, the number of ring bonds. Without a count, at least one.
+    RingConnectivity(Option<u8>),
+    /// `v<n>`, the total bond order.
+    Valence(u8),
+    /// `+<n>` or `-<n>`
+    Charge(i8),
+    /// A leading number, e.g. `13` in `[13C]`.
+    Isotope(u16),
+    /// `@` or `@@`, relative to the query atom's neighbours in the same order as
+    /// [`Atom::chirality`](crate::core::Atom::chirality).
+    Chirality(Chirality),
+    /// `$(...)`, the atom is the first atom of a match of the inner query.
+    Recursive(Box<Query>),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum BondPrimitive {
+    /// A bond written without a symbol, matching single or aromatic bonds.
+    Implicit,
+    /// `-`
+    Single,
+    /// `=`
+    Double,
+    /// `#`
+    Triple,
+    /// `:`
+    Aromatic,
+    /// `~`
+    Any,
+    /// `@`
+    Ring,
+    /// `/`, matched as a single bond.
+    Up,
+    /// `\`, matched as a single bond.
+    Down,
+}
+
+pub type AtomExpr = Expr<AtomPrimitive>;
+pub type BondExpr = Expr<BondPrimitive>;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct QueryAtom {
+    pub expr: AtomExpr,
+    /// The atom map, e.g. `1` in `[C:1]`.
+    pub map: Option<usize>,
+}
+
+/// A query graph, such as one parsed from SMARTS. Its atoms are addressed by [`AtomIndex`] like
+/// those of a molecule, but they are expressions to be matched rather than atoms.
+#[derive(Clone, Debug)]
+pub struct Query {
+    graph: UnGraph<QueryAtom, BondExpr>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query {
+            graph: Graph::new_undirected(),
+        }
+    }
+
+    pub fn add_atom(&mut self, atom: QueryAtom) -> AtomIndex {
+        AtomIndex::new(self.graph.add_node(atom).index())
+    }
+
+    /// Bonds two atoms. Returns the existing bond expression instead if they are already bonded.
+    pub fn add_bond(&mut self, a: AtomIndex, b: AtomIndex, bond: BondExpr) -> Result<(), BondExpr> {
+        let (a, b) = (node(a), node(b));
+        match self.graph.find_edge(a, b) {
+            Some(edge) => Err(self.graph[edge].clone()),
+            None => {
+                self.graph.add_edge(a, b, bond);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn atom(&self, atom: AtomIndex) -> &QueryAtom {
+        &self.graph[node(atom)]
+    }
+
+    pub fn atom_mut(&mut self, atom: AtomIndex) -> &mut QueryAtom {
+        &mut self.graph[node(atom)]
+    }
+
+    pub fn atom_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn bond_count(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    pub fn atoms(&self) -> impl Iterator<Item = AtomIndex> + '_ {
+        self.graph.node_indices().map(|n| AtomIndex::new(n.index()))
+    }
+
+    /// Iterates over every bond once as `(a, b, bond)`.
+    pub fn bonds(&self) -> impl Iterator<Item = (AtomIndex, AtomIndex, &BondExpr)> + '_ {
+        self.graph.edge_references().map(|edge| {
+            (
+                AtomIndex::new(edge.source().index()),
+                AtomIndex::new(edge.target().index()),
+                edge.weight(),
+            )
+        })
+    }
+
+    pub fn bond(&self, a: AtomIndex, b: AtomIndex) -> Option<&BondExpr> {
+        self.graph
+            .find_edge(node(a), node(b))
+            .map(|edge| &self.graph[edge])
+    }
+
+    pub fn neighbors(&self, atom: AtomIndex) -> impl Iterator<Item = AtomIndex> + '_ {
+        self.graph
+            .neighbors(node(atom))
+            .map(|n| AtomIndex::new(n.index()))
+    }
+
+    pub fn degree(&self, atom: AtomIndex) -> usize {
+        self.graph.neighbors(node(atom)).count()
+    }
+
+    /// The query with hydrogen atoms, written as `[#1]` or `[H]` with a single heavy neighbour,
+    /// merged into a count of at least that many hydrogens on the neighbour, so that they match
+    /// implicit hydrogens too. Hydrogens bonded by anything but a plain, `-` or `~` bond are kept,
+    /// as their bond wouldn't match every bond to a hydrogen. The first atom is kept, as it anchors recursive queries. The
+    /// remaining atoms keep their order.
+    pub fn merge_hydrogens(&self) -> Query {
+        let merged: Vec<bool> = self
+            .atoms()
+            .map(|atom| {
+                atom.index() > 0
+                    && self.degree(atom) == 1
+                    && self.atom(atom).map.is_none()
+                    && is_hydrogen(&self.atom(atom).expr)
+                    && self.neighbors(atom).all(|n| {
+                        !is_hydrogen(&self.atom(n).expr)
+                            && matches_any_single(self.bond(atom, n).unwrap())
+                    })
+            })
+            .collect();
+
+        let mut query = Query::new();
+        let mut indices = vec![None; self.atom_count()];
+        for atom in self.atoms().filter(|atom| !merged[atom.index()]) {
+            let hydrogens = self.neighbors(atom).filter(|n| merged[n.index()]).count() as u8;
+            let mut exprs = vec![merge_recursive(&self.atom(atom).expr)];
+            exprs.extend(
+                (0..hydrogens).map(|h| {
+                    Expr::Not(Box::new(Expr::Primitive(AtomPrimitive::TotalHydrogens(h))))
+                }),
+            );
+            indices[atom.index()] = Some(query.add_atom(QueryAtom {
+                expr: Expr::and(exprs),
+                map: self.atom(atom).map,
+            }));
+        }
+        for (a, b, bond) in self.bonds() {
+            if let (Some(a), Some(b)) = (indices[a.index()], indices[b.index()]) {
+                query.add_bond(a, b, bond.clone()).unwrap();
+            }
+        }
+        query
+    }
+}
+
+fn is_hydrogen(expr: &AtomExpr) -> bool {
+    matches!(
+        expr,
+        Expr::Primitive(AtomPrimitive::AtomicNumber(1))
+            | Expr::Primitive(AtomPrimitive::Symbol {
+                element: Element::H,
+                aromatic: false,
+            })
+    )
+}
+
+/// Whether the bond matches every single bond.
+fn matches_any_single(bond: &BondExpr) -> bool {
+    matches!(
+        bond,
+        Expr::Primitive(BondPrimitive::Implicit)
+            | Expr::Primitive(BondPrimitive::Single)
+            | Expr::Primitive(BondPrimitive::Any)
+    )
+}
+
+/// The expression with hydrogens merged in any recursive queries it has.
+fn merge_recursive(expr: &AtomExpr) -> AtomExpr {
+    match expr {
+        Expr::Primitive(AtomPrimitive::Recursive(inner)) => {
+            Expr::Primitive(AtomPrimitive::Recursive(Box::new(inner.merge_hydrogens())))
+        }
+        Expr::Primitive(p) => Expr::Primitive(p.clone()),
+        Expr::Not(e) => Expr::Not(Box::new(merge_recursive(e))),
+        Expr::And(es) => Expr::And(es.iter().map(merge_recursive).collect()),
+        Expr::Or(es) => Expr::Or(es.iter().map(merge_recursive).collect()),
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Query::new()
+    }
+}
+
+/// Queries are equal if they have the same atoms and bonds in the same order.
+impl PartialEq for Query {
+    fn eq(&self, other: &Query) -> bool {
+        self.atoms()
+            .map(|a| self.atom(a))
+            .eq(other.atoms().map(|a| other.atom(a)))
+            && self
+                .graph
+                .edge_references()
+                .map(|e| (e.source(), e.target(), e.weight()))
+                .eq(other
+                    .graph
+                    .edge_references()
+                    .map(|e| (e.source(), e.target(), e.weight())))
+    }
+}
+
+/// A query matching the molecule as a substructure: each atom matches its element, aromaticity,
+/// and any charge, isotope or chirality it has, and each bond matches its bond order. Hydrogen
+/// counts are not compared.
+impl From<&Molecule> for Query {
+    fn from(mol: &Molecule) -> Self {
+        let mut query = Query::new();
+        for atom in mol.atoms() {
+            let a = mol.atom(atom);
+            let mut primitives = vec![AtomPrimitive::Symbol {
+                element: a.element,
+                aromatic: a.aromatic,
+            }];
+            if a.formal_charge != 0 {
+                primitives.push(AtomPrimitive::Charge(a.formal_charge));
+            }
+            primitives.extend(a.isotope.map(AtomPrimitive::Isotope));
+            primitives.extend(a.chirality.map(AtomPrimitive::Chirality));
+            query.add_atom(QueryAtom {
+                expr: Expr::and(primitives.into_iter().map(Expr::Primitive).collect()),
+                map: a.map,
+            });
+        }
+        for (a, b, bond) in mol.bonds() {
+            let bond = match bond {
+                Bond::Single => BondPrimitive::Single,
+                Bond::Double => BondPrimitive::Double,
+                Bond::Triple => BondPrimitive::Triple,
+                Bond::Aromatic => BondPrimitive::Aromatic,
+            };
+            query
+                .add_bond(a, b, Expr::Primitive(bond))
+                .expect("Molecule has a duplicate bond");
+        }
+        query
+    }
+}
+
+fn node(atom: AtomIndex) -> NodeIndex {
+    NodeIndex::new(atom.index())
+}
@@ -0,0 +1,56 @@
+use crate::core::{Molecule, Reaction};
+use crate::daylight::{molecule_from_smiles, molecule_to_smiles, SmilesError};
+
+/// Parses reaction SMILES, `reactants>agents>products`, where each part is a dot separated list
+/// of molecules and may be empty. Syntax errors are reported as offsets into the whole string.
+pub fn reaction_from_smiles(smiles: &str) -> Result<Reaction, SmilesError> {
+    let parts: Vec<&str> = smiles.split('>').collect();
+    if parts.len() != 3 {
+        // The end of the input if there are too few arrows, or else the first extra one
+        let offset = match parts.len() {
+            n if n < 3 => smiles.len(),
+            _ => parts[..3].iter().map(|p| p.len() + 1).sum::<usize>() - 1,
+        };
+        return Err(SmilesError::Syntax(offset));
+    }
+
+    let mut sides = vec![];
+    let mut start = 0;
+    for part in parts {
+        let molecules = molecules_from_smiles(part).map_err(|e| match e {
+            SmilesError::Syntax(offset) => SmilesError::Syntax(start + offset),
+            e => e,
+        })?;
+        sides.push(molecules);
+        start += part.len() + 1;
+    }
+    let products = sides.pop().unwrap();
+    let agents = sides.pop().unwrap();
+    let reactants = sides.pop().unwrap();
+    Ok(Reaction::new(reactants, agents, products))
+}
+
+/// Each dot separated component is a separate molecule.
+fn molecules_from_smiles(smiles: &str) -> Result<Vec<Molecule>, SmilesError> {
+    if smiles.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(molecule_from_smiles(smiles)?.fragments())
+}
+
+/// Writes a reaction as reaction SMILES. See [`molecule_to_smiles`].
+pub fn reaction_to_smiles(reaction: &Reaction) -> String {
+    let side = |molecules: &[Molecule]| {
+        molecules
+            .iter()
+            .map(molecule_to_smiles)
+            .collect::<Vec<String>>()
+            .join(".")
+    };
+    format!(
+        "{}>{}>{}",
+        side(&reaction.reactants),
+        side(&reaction.agents),
+        side(&reaction.products)
+    )
+}
@@ -0,0 +1,439 @@
+// https://www.daylight.com/dayhtml/doc/theory/theory.smarts.html
+
+use crate::core::{AtomIndex, Bond, Element};
+use crate::daylight::ast::Symbol;
+use crate::daylight::parse::{self, bracketed, digit_m_n};
+use crate::daylight::query::*;
+use crate::daylight::smiles::{core_order, Neighbor};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{digit1, satisfy},
+    combinator::{map, map_res, opt, peek, recognize, value},
+    multi::{many0, many_m_n, separated_list1},
+    sequence::{pair, preceded, tuple},
+    IResult,
+};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SmartsError {
+    /// The input is not valid SMARTS from this byte offset onwards.
+    Syntax(usize),
+    /// A ring bond number was opened but never closed.
+    UnclosedRing(usize),
+    /// The two ends of a ring bond specify different bonds.
+    ConflictingRingBond(usize),
+    /// The same pair of atoms was bonded twice, e.g. by a ring bond between neighbours.
+    DuplicateBond,
+}
+
+impl fmt::Display for SmartsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmartsError::Syntax(offset) => write!(f, "invalid SMARTS at offset {}", offset),
+            SmartsError::UnclosedRing(n) => write!(f, "ring bond {} is never closed", n),
+            SmartsError::ConflictingRingBond(n) => {
+                write!(f, "ring bond {} has conflicting bond expressions", n)
+            }
+            SmartsError::DuplicateBond => write!(f, "atoms are bonded more than once"),
+        }
+    }
+}
+
+impl std::error::Error for SmartsError {}
+
+/// Parses a SMARTS pattern into a query. Atoms are numbered in the order they are written, and a
+/// bond written without a symbol matches single or aromatic bonds.
+pub fn query_from_smarts(smarts: &str) -> Result<Query, SmartsError> {
+    let chains = match self::smarts(smarts) {
+        Ok(("", chains)) => chains,
+        Ok((rest, _)) => return Err(SmartsError::Syntax(smarts.len() - rest.len())),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+            return Err(SmartsError::Syntax(smarts.len() - e.input.len()))
+        }
+        Err(nom::Err::Incomplete(_)) => return Err(SmartsError::Syntax(smarts.len())),
+    };
+    query_from_chains(&chains)
+}
+
+fn query_from_chains(chains: &[Chain]) -> Result<Query, SmartsError> {
+    let mut builder = Builder::default();
+    for chain in chains {
+        builder.chain(chain, None)?;
+    }
+    builder.finish()
+}
+
+#[derive(Clone, Debug)]
+struct BranchedAtom {
+    atom: QueryAtom,
+    ring_bonds: Vec<(Option<BondExpr>, usize)>,
+    branches: Vec<(Option<BondExpr>, Chain)>,
+}
+
+/// The atoms of a chain in the order they are written, each with the bond to the atom before it.
+type Chain = Vec<(Option<BondExpr>, BranchedAtom)>;
+
+type Primitive<T> = fn(&str) -> IResult<&str, T>;
+
+/// A logical expression over primitives. From highest to lowest precedence the operators are
+/// `!`, `&` (or no operator at all), `,` and `;`.
+fn expression<T: Clone>(primitive: Primitive<T>, input: &str) -> IResult<&str, Expr<T>> {
+    map(
+        separated_list1(tag(";"), |i| disjunction(primitive, i)),
+        Expr::and,
+    )(input)
+}
+
+fn disjunction<T: Clone>(primitive: Primitive<T>, input: &str) -> IResult<&str, Expr<T>> {
+    map(
+        separated_list1(tag(","), |i| conjunction(primitive, i)),
+        Expr::or,
+    )(input)
+}
+
+fn conjunction<T: Clone>(primitive: Primitive<T>, input: &str) -> IResult<&str, Expr<T>> {
+    map(
+        pair(
+            |i| negation(primitive, i),
+            many0(preceded(opt(tag("&")), |i| negation(primitive, i))),
+        ),
+        |(first, mut rest)| {
+            rest.insert(0, first);
+            Expr::and(rest)
+        },
+    )(input)
+}
+
+fn negation<T: Clone>(primitive: Primitive<T>, input: &str) -> IResult<&str, Expr<T>> {
+    alt((
+        map(preceded(tag("!"), |i| negation(primitive, i)), |e| {
+            Expr::Not(Box::new(e))
+        }),
+        map(primitive, Expr::Primitive),
+    ))(input)
+}
+
+fn count(input: &str) -> IResult<&str, u8> {
+    map_res(digit1, |num_str: &str| num_str.parse::<u8>())(input)
+}
+
+/// Charges as in SMILES, or repeated signs such as `++`.
+fn charge(input: &str) -> IResult<&str, i8> {
+    alt((
+        map(recognize(many_m_n(2, 15, tag("+"))), |s: &str| {
+            s.len() as i8
+        }),
+        map(recognize(many_m_n(2, 15, tag("-"))), |s: &str| {
+            -(s.len() as i8)
+        }),
+        parse::charge,
+    ))(input)
+}
+
+fn symbol_primitive(symbol: Symbol) -> AtomPrimitive {
+    match symbol {
+        Symbol::Element(element) => AtomPrimitive::Symbol {
+            element,
+            aromatic: false,
+        },
+        Symbol::Aromatic(element) => AtomPrimitive::Symbol {
+            element,
+            aromatic: true,
+        },
+        Symbol::Wildcard => AtomPrimitive::Any,
+    }
+}
+
+/// Two letter element symbols take precedence over a one letter primitive followed by another,
+/// so `[Cl]` is chlorine and `[Ar]` is argon.
+fn two_letter_element(input: &str) -> IResult<&str, AtomPrimitive> {
+    map_res(
+        recognize(pair(
+            satisfy(|c| c.is_ascii_uppercase()),
+            satisfy(|c| c.is_ascii_lowercase()),
+        )),
+        |el: &str| -> Result<AtomPrimitive, ()> {
+            let element: Element = el.parse()?;
+            Ok(AtomPrimitive::Symbol {
+                element,
+                aromatic: false,
+            })
+        },
+    )(input)
+}
+
+fn atom_primitive(input: &str) -> IResult<&str, AtomPrimitive> {
+    use AtomPrimitive::*;
+    let counted = |t, f: fn(Option<u8>) -> AtomPrimitive| map(preceded(tag(t), opt(count)), f);
+    alt((
+        map_res(bracketed("$(", ")", smarts), |chains| {
+            query_from_chains(&chains).map(|q| Recursive(Box::new(q)))
+        }),
+        map(preceded(tag("#"), count), AtomicNumber),
+        map(charge, Charge),
+        map(parse::chirality, Chirality),
+        map(parse::isotope, Isotope),
+        two_letter_element,
+        counted("D", |n| Degree(n.unwrap_or(1))),
+        counted("X", |n| Connectivity(n.unwrap_or(1))),
+        map(parse::hydrogens, TotalHydrogens),
+        counted("h", ImplicitHydrogens),
+        counted("R", RingMembership),
+        counted("r", RingSize),
+        counted("x", RingConnectivity),
+        counted("v", |n| Valence(n.unwrap_or(1))),
+        map(parse::symbol, symbol_primitive),
+        value(Aromatic, tag("a")),
+        value(Aliphatic, tag("A")),
+    ))(input)
+}
+
+/// `[H]`, with an optional isotope and charge, is a hydrogen atom rather than a hydrogen count.
+fn hydrogen_atom(input: &str) -> IResult<&str, AtomExpr> {
+    map(
+        tuple((
+            opt(parse::isotope),
+            tag("H"),
+            opt(charge),
+            peek(alt((tag("]"), tag(":")))),
+        )),
+        |(isotope, _, charge, _)| {
+            let mut primitives = vec![Expr::Primitive(AtomPrimitive::Symbol {
+                element: Element::H,
+                aromatic: false,
+            })];
+            primitives.extend(isotope.map(|i| Expr::Primitive(AtomPrimitive::Isotope(i))));
+            primitives.extend(charge.map(|c| Expr::Primitive(AtomPrimitive::Charge(c))));
+            Expr::and(primitives)
+        },
+    )(input)
+}
+
+fn bracket_atom(input: &str) -> IResult<&str, QueryAtom> {
+    map(
+        bracketed(
+            "[",
+            "]",
+            pair(
+                alt((hydrogen_atom, |i| expression(atom_primitive, i))),
+                opt(parse::atom_class),
+            ),
+        ),
+        |(expr, map)| QueryAtom { expr, map },
+    )(input)
+}
+
+fn organic_atom(input: &str) -> IResult<&str, QueryAtom> {
+    map(
+        alt((
+            map(parse::organic_symbol, symbol_primitive),
+            value(AtomPrimitive::Aromatic, tag("a")),
+            value(AtomPrimitive::Aliphatic, tag("A")),
+        )),
+        |primitive| QueryAtom {
+            expr: Expr::Primitive(primitive),
+            map: None,
+        },
+    )(input)
+}
+
+fn bond_primitive(input: &str) -> IResult<&str, BondPrimitive> {
+    alt((
+        map(parse::bond, |bond| match bond {
+            Bond::Single => BondPrimitive::Single,
+            Bond::Double => BondPrimitive::Double,
+            Bond::Triple => BondPrimitive::Triple,
+            Bond::Aromatic => BondPrimitive::Aromatic,
+        }),
+        value(BondPrimitive::Any, tag("~")),
+        value(BondPrimitive::Ring, tag("@")),
+        value(BondPrimitive::Up, tag("/")),
+        value(BondPrimitive::Down, tag("\\")),
+    ))(input)
+}
+
+fn bond(input: &str) -> IResult<&str, BondExpr> {
+    expression(bond_primitive, input)
+}
+
+fn ring_bond(input: &str) -> IResult<&str, (Option<BondExpr>, usize)> {
+    pair(
+        opt(bond),
+        map_res(
+            alt((digit_m_n(1, 1), preceded(tag("%"), digit_m_n(2, 2)))),
+            |num_str: &str| num_str.parse::<usize>(),
+        ),
+    )(input)
+}
+
+fn branched_atom(input: &str) -> IResult<&str, BranchedAtom> {
+    map(
+        tuple((
+            alt((bracket_atom, organic_atom)),
+            many0(ring_bond),
+            many0(bracketed("(", ")", pair(opt(bond), chain))),
+        )),
+        |(atom, ring_bonds, branches)| BranchedAtom {
+            atom,
+            ring_bonds,
+            branches,
+        },
+    )(input)
+}
+
+fn chain(input: &str) -> IResult<&str, Chain> {
+    map(
+        pair(branched_atom, many0(pair(opt(bond), branched_atom))),
+        |(first, mut rest)| {
+            rest.insert(0, (None, first));
+            rest
+        },
+    )(input)
+}
+
+/// A full SMARTS pattern: one or more chains separated by dots.
+fn smarts(input: &str) -> IResult<&str, Vec<Chain>> {
+    separated_list1(tag("."), chain)(input)
+}
+
+#[derive(Default)]
+struct Builder {
+    query: Query,
+    open_rings: HashMap<usize, (AtomIndex, Option<BondExpr>, usize)>,
+    written_order: HashMap<AtomIndex, Vec<Neighbor>>,
+    implicit_neighbors: Vec<AtomIndex>,
+}
+
+impl Builder {
+    /// Adds a chain, bonding its first atom to `from` if given.
+    fn chain(
+        &mut self,
+        chain: &[(Option<BondExpr>, BranchedAtom)],
+        from: Option<(AtomIndex, Option<BondExpr>)>,
+    ) -> Result<(), SmartsError> {
+        let (mut prev, mut from_bond) = match from {
+            Some((atom, bond)) => (Some(atom), bond),
+            None => (None, None),
+        };
+        for (i, (bond, ba)) in chain.iter().enumerate() {
+            let atom = self.query.add_atom(ba.atom.clone());
+            let bond = if i == 0 {
+                from_bond.take()
+            } else {
+                bond.clone()
+            };
+            if let Some(prev) = prev {
+                self.add_bond(prev, atom, bond)?;
+                self.neighbors(prev).push(Neighbor::Atom(atom));
+                self.neighbors(atom).push(Neighbor::Atom(prev));
+            }
+            let has_hydrogen = ba.atom.expr.requires(&AtomPrimitive::TotalHydrogens(1));
+            let chiral = ba
+                .atom
+                .expr
+                .primitives()
+                .iter()
+                .any(|p| matches!(p, AtomPrimitive::Chirality(_)));
+            // A stereocentre written with three neighbours has an implicit fourth where the
+            // hydrogen would be, which is only known once all its neighbours are added
+            if has_hydrogen || chiral {
+                self.neighbors(atom).push(Neighbor::ImplicitHydrogen);
+            }
+            if chiral && !has_hydrogen {
+                self.implicit_neighbors.push(atom);
+            }
+            for (bond, ring_number) in &ba.ring_bonds {
+                self.ring_bond(atom, bond, *ring_number)?;
+            }
+            for (bond, branch) in &ba.branches {
+                self.chain(branch, Some((atom, bond.clone())))?;
+            }
+            prev = Some(atom);
+        }
+        Ok(())
+    }
+
+    fn ring_bond(
+        &mut self,
+        atom: AtomIndex,
+        bond: &Option<BondExpr>,
+        ring_number: usize,
+    ) -> Result<(), SmartsError> {
+        match self.open_rings.remove(&ring_number) {
+            Some((other, other_bond, slot)) => {
+                let bond = match (other_bond, bond.clone()) {
+                    (Some(a), Some(b)) if a != b => {
+                        return Err(SmartsError::ConflictingRingBond(ring_number))
+                    }
+                    (a, b) => a.or(b),
+                };
+                self.add_bond(other, atom, bond)?;
+                self.neighbors(other)[slot] = Neighbor::Atom(atom);
+                self.neighbors(atom).push(Neighbor::Atom(other));
+            }
+            None => {
+                let slot = self.neighbors(atom).len();
+                self.neighbors(atom).push(Neighbor::Pending);
+                self.open_rings
+                    .insert(ring_number, (atom, bond.clone(), slot));
+            }
+        }
+        Ok(())
+    }
+
+    fn neighbors(&mut self, atom: AtomIndex) -> &mut Vec<Neighbor> {
+        self.written_order.entry(atom).or_default()
+    }
+
+    fn add_bond(
+        &mut self,
+        a: AtomIndex,
+        b: AtomIndex,
+        bond: Option<BondExpr>,
+    ) -> Result<(), SmartsError> {
+        if a == b {
+            return Err(SmartsError::DuplicateBond);
+        }
+        let bond = bond.unwrap_or(Expr::Primitive(BondPrimitive::Implicit));
+        self.query
+            .add_bond(a, b, bond)
+            .map_err(|_| SmartsError::DuplicateBond)
+    }
+
+    fn finish(mut self) -> Result<Query, SmartsError> {
+        if let Some(ring_number) = self.open_rings.keys().min() {
+            return Err(SmartsError::UnclosedRing(*ring_number));
+        }
+        for atom in &self.implicit_neighbors {
+            let written = self.written_order.get_mut(atom).unwrap();
+            if written.len() > 4 {
+                written.retain(|n| *n != Neighbor::ImplicitHydrogen);
+            }
+        }
+        for (atom, written) in &self.written_order {
+            let order = core_order(written);
+            permute_chirality(&mut self.query.atom_mut(*atom).expr, &order);
+        }
+        Ok(self.query)
+    }
+}
+
+/// Rewrites every chirality primitive in the expression from written neighbour order to the core
+/// model's order.
+fn permute_chirality(expr: &mut AtomExpr, order: &[usize]) {
+    match expr {
+        Expr::Primitive(AtomPrimitive::Chirality(chirality)) => {
+            *chirality = chirality.permuted(order)
+        }
+        Expr::Primitive(_) => {}
+        Expr::Not(e) => permute_chirality(e, order),
+        Expr::And(es) | Expr::Or(es) => {
+            for e in es {
+                permute_chirality(e, order);
+            }
+        }
+    }
+}
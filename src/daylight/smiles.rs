@@ -0,0 +1,264 @@
+use crate::core::{self, AtomIndex, Bond, Element, Molecule};
+use crate::daylight::ast::*;
+use crate::daylight::parse;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SmilesError {
+    /// The input is not valid SMILES from this byte offset onwards.
+    Syntax(usize),
+    /// A ring bond number was opened but never closed.
+    UnclosedRing(usize),
+    /// The two ends of a ring bond specify different bonds.
+    ConflictingRingBond(usize),
+    /// The same pair of atoms was bonded twice, e.g. by a ring bond between neighbours.
+    DuplicateBond,
+    /// Wildcard atoms have no element and cannot be part of a molecule.
+    Wildcard,
+}
+
+impl fmt::Display for SmilesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmilesError::Syntax(offset) => write!(f, "invalid SMILES at offset {}", offset),
+            SmilesError::UnclosedRing(n) => write!(f, "ring bond {} is never closed", n),
+            SmilesError::ConflictingRingBond(n) => {
+                write!(f, "ring bond {} has conflicting bond orders", n)
+            }
+            SmilesError::DuplicateBond => write!(f, "atoms are bonded more than once"),
+            SmilesError::Wildcard => write!(f, "wildcard atoms are not supported"),
+        }
+    }
+}
+
+impl std::error::Error for SmilesError {}
+
+/// Parses a SMILES string into a molecule. Atoms in the organic subset get implicit hydrogens
+/// from their lowest normal valence that fits their bonds; bracket atoms only have the hydrogens
+/// they list.
+pub fn molecule_from_smiles(smiles: &str) -> Result<Molecule, SmilesError> {
+    let chains = match parse::smiles(smiles) {
+        Ok(("", chains)) => chains,
+        Ok((rest, _)) => return Err(SmilesError::Syntax(smiles.len() - rest.len())),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+            return Err(SmilesError::Syntax(smiles.len() - e.input.len()))
+        }
+        Err(nom::Err::Incomplete(_)) => return Err(SmilesError::Syntax(smiles.len())),
+    };
+    molecule_from_chains(&chains)
+}
+
+fn molecule_from_chains(chains: &[Chain]) -> Result<Molecule, SmilesError> {
+    let mut builder = Builder::default();
+    for chain in chains {
+        builder.chain(chain, None)?;
+    }
+    builder.finish()
+}
+
+/// A neighbour in the order it was written, for interpreting chirality.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub(super) enum Neighbor {
+    Atom(AtomIndex),
+    ImplicitHydrogen,
+    /// A ring bond that has been opened but not closed yet.
+    Pending,
+}
+
+#[derive(Default)]
+struct Builder {
+    mol: Molecule,
+    open_rings: HashMap<usize, (AtomIndex, Option<Bond>, usize)>,
+    organic: Vec<AtomIndex>,
+    written_order: HashMap<AtomIndex, Vec<Neighbor>>,
+}
+
+impl Builder {
+    /// Adds a chain, bonding its first atom to `from` if given, and returns its last atom.
+    fn chain(
+        &mut self,
+        chain: &Chain,
+        from: Option<(AtomIndex, Option<Bond>)>,
+    ) -> Result<AtomIndex, SmilesError> {
+        // The chain is stored last atom first, so unwind it before adding atoms in order
+        let mut links = vec![];
+        let mut chain = chain;
+        loop {
+            match chain {
+                Chain::ChainBond(cb) => {
+                    links.push((cb.bond, &cb.branched_atom));
+                    chain = &cb.chain;
+                }
+                Chain::BranchedAtom(ba) => {
+                    links.push((None, ba));
+                    break;
+                }
+            }
+        }
+
+        let mut prev = from.map(|(atom, _)| atom);
+        let mut bond = from.and_then(|(_, bond)| bond);
+        // Each link's bond joins it to the atom before it
+        for (i, (_, ba)) in links.iter().enumerate().rev() {
+            let atom = self.atom(&ba.atom)?;
+            if let Some(prev) = prev {
+                self.bond(prev, atom, bond)?;
+            }
+            if ba.atom.hydrogens.unwrap_or(0) > 0 {
+                self.neighbors(atom).push(Neighbor::ImplicitHydrogen);
+            }
+            for ring_bond in &ba.ring_bonds {
+                self.ring_bond(atom, ring_bond)?;
+            }
+            for branch in &ba.branches {
+                self.chain(&branch.chain, Some((atom, branch.bond)))?;
+            }
+            prev = Some(atom);
+            bond = i.checked_sub(1).and_then(|_| links[i - 1].0);
+        }
+
+        Ok(prev.unwrap())
+    }
+
+    fn ring_bond(&mut self, atom: AtomIndex, ring_bond: &RingBond) -> Result<(), SmilesError> {
+        match self.open_rings.remove(&ring_bond.ring_number) {
+            Some((other, bond, slot)) => {
+                let bond = match (bond, ring_bond.bond) {
+                    (Some(a), Some(b)) if a != b => {
+                        return Err(SmilesError::ConflictingRingBond(ring_bond.ring_number))
+                    }
+                    (a, b) => a.or(b),
+                };
+                self.add_bond(other, atom, bond)?;
+                // The opening atom saw this neighbour where the ring bond number was written
+                self.neighbors(other)[slot] = Neighbor::Atom(atom);
+                self.neighbors(atom).push(Neighbor::Atom(other));
+            }
+            None => {
+                let slot = self.neighbors(atom).len();
+                self.neighbors(atom).push(Neighbor::Pending);
+                self.open_rings
+                    .insert(ring_bond.ring_number, (atom, ring_bond.bond, slot));
+            }
+        }
+        Ok(())
+    }
+
+    fn atom(&mut self, atom: &Atom) -> Result<AtomIndex, SmilesError> {
+        let (element, aromatic) = match atom.symbol {
+            Symbol::Element(el) => (el, false),
+            Symbol::Aromatic(el) => (el, true),
+            Symbol::Wildcard => return Err(SmilesError::Wildcard),
+        };
+        let index = self.mol.add_atom(core::Atom {
+            element,
+            isotope: atom.isotope,
+            formal_charge: atom.charge.unwrap_or(0),
+            hydrogens: atom.hydrogens.unwrap_or(0),
+            aromatic,
+            chirality: atom.chirality,
+            map: atom.atom_class,
+        });
+        if !atom.bracketed && is_organic(element) {
+            self.organic.push(index);
+        }
+        Ok(index)
+    }
+
+    fn neighbors(&mut self, atom: AtomIndex) -> &mut Vec<Neighbor> {
+        self.written_order.entry(atom).or_default()
+    }
+
+    fn bond(&mut self, a: AtomIndex, b: AtomIndex, bond: Option<Bond>) -> Result<(), SmilesError> {
+        self.add_bond(a, b, bond)?;
+        self.neighbors(a).push(Neighbor::Atom(b));
+        self.neighbors(b).push(Neighbor::Atom(a));
+        Ok(())
+    }
+
+    fn add_bond(
+        &mut self,
+        a: AtomIndex,
+        b: AtomIndex,
+        bond: Option<Bond>,
+    ) -> Result<(), SmilesError> {
+        let bond = bond.unwrap_or_else(|| {
+            if self.mol.atom(a).aromatic && self.mol.atom(b).aromatic {
+                Bond::Aromatic
+            } else {
+                Bond::Single
+            }
+        });
+        if a == b {
+            return Err(SmilesError::DuplicateBond);
+        }
+        self.mol
+            .add_bond(a, b, bond)
+            .map_err(|_| SmilesError::DuplicateBond)
+    }
+
+    fn finish(mut self) -> Result<Molecule, SmilesError> {
+        if let Some(ring_number) = self.open_rings.keys().min() {
+            return Err(SmilesError::UnclosedRing(*ring_number));
+        }
+        for atom in self.organic {
+            let hydrogens = implicit_hydrogens(&self.mol, atom);
+            self.mol.atom_mut(atom).hydrogens = hydrogens;
+        }
+
+        // Reorder each stereocentre's neighbours from the written order to the order used by
+        // the core model: implicit hydrogen first, then ascending atom index.
+        for (atom, written) in &self.written_order {
+            if let Some(chirality) = self.mol.atom(*atom).chirality {
+                let order = core_order(written);
+                self.mol.atom_mut(*atom).chirality = Some(chirality.permuted(&order));
+            }
+        }
+
+        Ok(self.mol)
+    }
+}
+
+/// The permutation from a stereocentre's written neighbour order to the core model's order, as
+/// taken by [`Chirality::permuted`](core::Chirality::permuted). Every ring bond must be closed.
+pub(super) fn core_order(written: &[Neighbor]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..written.len()).collect();
+    order.sort_by_key(|i| match written[*i] {
+        Neighbor::ImplicitHydrogen => None,
+        Neighbor::Atom(n) => Some(n),
+        Neighbor::Pending => unreachable!(),
+    });
+    order
+}
+
+pub(super) fn is_organic(element: Element) -> bool {
+    use Element::*;
+    matches!(element, B | C | N | O | P | S | F | Cl | Br | I)
+}
+
+/// The hydrogens needed to bring an organic subset atom up to its lowest normal valence that is
+/// at least the sum of its bond orders. Aromatic atoms have one of their valences taken by the
+/// aromatic system, unless their bonds already fill a valence, as for the nitrogen of
+/// N-methylpyrrole or the sulfur of thiophene, which give a lone pair to the ring instead. Any
+/// hydrogens the atom already has are ignored.
+pub(crate) fn implicit_hydrogens(mol: &Molecule, atom: AtomIndex) -> u8 {
+    use Element::*;
+    let valences: &[u8] = match mol.atom(atom).element {
+        B => &[3],
+        C => &[4],
+        N | P => &[3, 5],
+        O => &[2],
+        S => &[2, 4, 6],
+        F | Cl | Br | I => &[1],
+        _ => &[],
+    };
+    let used = mol.valence(atom) - mol.atom(atom).hydrogens;
+    if mol.atom(atom).aromatic && valences.contains(&(used - 1)) {
+        return 0;
+    }
+    valences
+        .iter()
+        .find(|v| **v >= used)
+        .map_or(0, |v| v - used)
+}
@@ -0,0 +1,487 @@
+use crate::core::{AtomIndex, Bond, Chirality, Element, Molecule};
+use crate::daylight::smiles::{implicit_hydrogens, is_organic};
+use crate::daylight::{AtomExpr, AtomPrimitive, BondExpr, BondPrimitive, Expr, Query};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Writes a molecule as SMILES. Each component starts from its lowest atom and neighbours are
+/// visited in ascending order, so the output depends on atom order and is not canonical. Atoms
+/// are bracketed only when the organic subset can't express them, including when their hydrogen
+/// count differs from the one the parser would fill in.
+pub fn molecule_to_smiles(mol: &Molecule) -> String {
+    write_graph(mol)
+}
+
+/// Writes a molecule as canonical SMILES, the same for any order of its atoms. Atoms are written
+/// in the order of their [canonical ranks](Molecule::canonical_ranks).
+pub fn molecule_to_canonical_smiles(mol: &Molecule) -> String {
+    let ranks = mol.canonical_ranks();
+    let mut atoms: Vec<AtomIndex> = mol.atoms().collect();
+    atoms.sort_by_key(|atom| ranks[atom.index()]);
+    write_graph(&mol.subgraph(&atoms))
+}
+
+/// Writes a query as SMARTS, in the same order as [`molecule_to_smiles`]. Every atom is
+/// bracketed, and expressions are rewritten where SMARTS precedence can't express them as they
+/// are, so the output may differ from the SMARTS the query was parsed from.
+pub fn query_to_smarts(query: &Query) -> String {
+    write_graph(query)
+}
+
+fn write_graph(graph: &impl Graph) -> String {
+    let mut writer = Writer::new(graph);
+    let mut components = vec![];
+    for atom in graph.atom_indices() {
+        if !writer.visited.contains(&atom) {
+            writer.visit(atom, None);
+            let mut out = String::new();
+            writer.write(&mut out, atom, None);
+            components.push(out);
+        }
+    }
+    components.join(".")
+}
+
+/// What the writer needs from a molecule or query.
+trait Graph {
+    fn atom_indices(&self) -> Vec<AtomIndex>;
+
+    fn neighbor_indices(&self, atom: AtomIndex) -> Vec<AtomIndex>;
+
+    /// Whether the atom has a neighbour written as part of it, like the hydrogen of `[C@H]`,
+    /// which comes first in the core model's neighbour order.
+    fn implicit_neighbor(&self, atom: AtomIndex) -> bool;
+
+    fn write_atom(&self, out: &mut String, atom: AtomIndex, written: &[Option<AtomIndex>]);
+
+    fn write_bond(&self, out: &mut String, a: AtomIndex, b: AtomIndex);
+
+    /// Converts chirality from the core model's neighbour order to the written order.
+    fn written_chirality(
+        &self,
+        atom: AtomIndex,
+        chirality: Chirality,
+        written: &[Option<AtomIndex>],
+    ) -> Chirality {
+        let mut core: Vec<Option<AtomIndex>> = vec![];
+        if self.implicit_neighbor(atom) {
+            core.push(None);
+        }
+        let mut neighbors = self.neighbor_indices(atom);
+        neighbors.sort();
+        core.extend(neighbors.into_iter().map(Some));
+
+        let order: Vec<usize> = written
+            .iter()
+            .map(|n| core.iter().position(|c| c == n).unwrap())
+            .collect();
+        chirality.permuted(&order)
+    }
+}
+
+struct Writer<'g, G: Graph> {
+    graph: &'g G,
+    visited: HashSet<AtomIndex>,
+    children: HashMap<AtomIndex, Vec<AtomIndex>>,
+    /// Ring bonds by the atom written first, then by the atom that closes them.
+    openings: HashMap<AtomIndex, Vec<AtomIndex>>,
+    closings: HashMap<AtomIndex, Vec<AtomIndex>>,
+    ring_numbers: HashMap<(AtomIndex, AtomIndex), usize>,
+}
+
+impl<'g, G: Graph> Writer<'g, G> {
+    fn new(graph: &'g G) -> Self {
+        Writer {
+            graph,
+            visited: HashSet::new(),
+            children: HashMap::new(),
+            openings: HashMap::new(),
+            closings: HashMap::new(),
+            ring_numbers: HashMap::new(),
+        }
+    }
+
+    /// Builds the depth first spanning tree, recording every other bond as a ring bond.
+    fn visit(&mut self, atom: AtomIndex, parent: Option<AtomIndex>) {
+        self.visited.insert(atom);
+        let mut neighbors = self.graph.neighbor_indices(atom);
+        neighbors.sort();
+        for n in neighbors {
+            if Some(n) == parent || self.is_ring_bond(atom, n) {
+                continue;
+            }
+            if self.visited.contains(&n) {
+                // Only ancestors can have been visited, so the ring bond is opened there
+                self.openings.entry(n).or_default().push(atom);
+                self.closings.entry(atom).or_default().push(n);
+            } else {
+                self.children.entry(atom).or_default().push(n);
+                self.visit(n, Some(atom));
+            }
+        }
+    }
+
+    fn is_ring_bond(&self, a: AtomIndex, b: AtomIndex) -> bool {
+        let bonded = |x: &AtomIndex, y: AtomIndex| matches!(self.openings.get(x), Some(partners) if partners.contains(&y));
+        bonded(&a, b) || bonded(&b, a)
+    }
+
+    fn write(&mut self, out: &mut String, atom: AtomIndex, from: Option<AtomIndex>) {
+        let closings = self.closings.get(&atom).cloned().unwrap_or_default();
+        let openings = self.openings.get(&atom).cloned().unwrap_or_default();
+        let children = self.children.get(&atom).cloned().unwrap_or_default();
+
+        // Neighbours in the order the parser will see them
+        let mut written: Vec<Option<AtomIndex>> = from.into_iter().map(Some).collect();
+        if self.graph.implicit_neighbor(atom) {
+            written.push(None);
+        }
+        written.extend(
+            closings
+                .iter()
+                .chain(&openings)
+                .chain(&children)
+                .map(|n| Some(*n)),
+        );
+        self.graph.write_atom(out, atom, &written);
+
+        for partner in closings {
+            let number = self.ring_numbers.remove(&(partner, atom)).unwrap();
+            write_ring_number(out, number);
+        }
+        for partner in openings {
+            let number = (1..)
+                .find(|n| !self.ring_numbers.values().any(|used| used == n))
+                .unwrap();
+            self.ring_numbers.insert((atom, partner), number);
+            self.graph.write_bond(out, atom, partner);
+            write_ring_number(out, number);
+        }
+        for (i, child) in children.iter().enumerate() {
+            let branch = i + 1 < children.len();
+            if branch {
+                out.push('(');
+            }
+            self.graph.write_bond(out, atom, *child);
+            self.write(out, *child, Some(atom));
+            if branch {
+                out.push(')');
+            }
+        }
+    }
+}
+
+impl Graph for Molecule {
+    fn atom_indices(&self) -> Vec<AtomIndex> {
+        self.atoms().collect()
+    }
+
+    fn neighbor_indices(&self, atom: AtomIndex) -> Vec<AtomIndex> {
+        self.neighbors(atom).collect()
+    }
+
+    fn implicit_neighbor(&self, atom: AtomIndex) -> bool {
+        needs_brackets(self, atom) && self.atom(atom).hydrogens > 0
+    }
+
+    fn write_atom(&self, out: &mut String, atom: AtomIndex, written: &[Option<AtomIndex>]) {
+        let a = self.atom(atom);
+        let symbol = if a.aromatic {
+            a.element.symbol().to_lowercase()
+        } else {
+            a.element.symbol().to_string()
+        };
+        if !needs_brackets(self, atom) {
+            out.push_str(&symbol);
+            return;
+        }
+
+        out.push('[');
+        if let Some(isotope) = a.isotope {
+            write!(out, "{}", isotope).unwrap();
+        }
+        out.push_str(&symbol);
+        if let Some(chirality) = a.chirality {
+            write_chirality(out, self.written_chirality(atom, chirality, written));
+        }
+        match a.hydrogens {
+            0 => {}
+            1 => out.push('H'),
+            n => write!(out, "H{}", n).unwrap(),
+        }
+        if a.formal_charge != 0 {
+            write_charge(out, a.formal_charge);
+        }
+        write_map(out, a.map);
+        out.push(']');
+    }
+
+    /// Omits the bond where the parser would infer the same one.
+    fn write_bond(&self, out: &mut String, a: AtomIndex, b: AtomIndex) {
+        let aromatic = self.atom(a).aromatic && self.atom(b).aromatic;
+        out.push_str(match self.bond(a, b).unwrap() {
+            Bond::Single if aromatic => "-",
+            Bond::Single => "",
+            Bond::Double => "=",
+            Bond::Triple => "#",
+            Bond::Aromatic if aromatic => "",
+            Bond::Aromatic => ":",
+        });
+    }
+}
+
+fn needs_brackets(mol: &Molecule, atom: AtomIndex) -> bool {
+    let a = mol.atom(atom);
+    let organic = if a.aromatic {
+        matches!(
+            a.element,
+            Element::B | Element::C | Element::N | Element::O | Element::P | Element::S
+        )
+    } else {
+        is_organic(a.element)
+    };
+    !organic
+        || a.isotope.is_some()
+        || a.formal_charge != 0
+        || a.chirality.is_some()
+        || a.map.is_some()
+        || a.hydrogens != implicit_hydrogens(mol, atom)
+}
+
+impl Graph for Query {
+    fn atom_indices(&self) -> Vec<AtomIndex> {
+        self.atoms().collect()
+    }
+
+    fn neighbor_indices(&self, atom: AtomIndex) -> Vec<AtomIndex> {
+        self.neighbors(atom).collect()
+    }
+
+    /// As the SMARTS parser decides it: a stereocentre with fewer than four other neighbours, or
+    /// an atom with one hydrogen.
+    fn implicit_neighbor(&self, atom: AtomIndex) -> bool {
+        let expr = &self.atom(atom).expr;
+        let chiral = expr
+            .primitives()
+            .iter()
+            .any(|p| matches!(p, AtomPrimitive::Chirality(_)));
+        let hydrogen = expr.requires(&AtomPrimitive::TotalHydrogens(1));
+        hydrogen || (chiral && self.degree(atom) < 4)
+    }
+
+    fn write_atom(&self, out: &mut String, atom: AtomIndex, written: &[Option<AtomIndex>]) {
+        let a = self.atom(atom);
+        out.push('[');
+        write_expr(out, &spell_out_aromatic(&a.expr), ";", &mut |out, p| {
+            write_atom_primitive(out, p, |c| self.written_chirality(atom, c, written))
+        });
+        write_map(out, a.map);
+        out.push(']');
+    }
+
+    fn write_bond(&self, out: &mut String, a: AtomIndex, b: AtomIndex) {
+        let expr = self.bond(a, b).unwrap();
+        if *expr == Expr::Primitive(BondPrimitive::Implicit) {
+            return;
+        }
+        write_expr(out, &spell_out_implicit(expr), ";", &mut |out, p| {
+            out.push_str(match p {
+                BondPrimitive::Implicit => "",
+                BondPrimitive::Single => "-",
+                BondPrimitive::Double => "=",
+                BondPrimitive::Triple => "#",
+                BondPrimitive::Aromatic => ":",
+                BondPrimitive::Any => "~",
+                BondPrimitive::Ring => "@",
+                BondPrimitive::Up => "/",
+                BondPrimitive::Down => "\\",
+            })
+        });
+    }
+}
+
+/// Replaces implicit bonds, which can only be written alone, with the bonds they stand for.
+fn spell_out_implicit(expr: &BondExpr) -> BondExpr {
+    match expr {
+        Expr::Primitive(BondPrimitive::Implicit) => Expr::Or(vec![
+            Expr::Primitive(BondPrimitive::Single),
+            Expr::Primitive(BondPrimitive::Aromatic),
+        ]),
+        Expr::Primitive(p) => Expr::Primitive(*p),
+        Expr::Not(e) => Expr::Not(Box::new(spell_out_implicit(e))),
+        Expr::And(es) => Expr::And(es.iter().map(spell_out_implicit).collect()),
+        Expr::Or(es) => Expr::Or(es.iter().map(spell_out_implicit).collect()),
+    }
+}
+
+/// Aromatic elements without a lowercase symbol are written as an atomic number and `a`, which is
+/// expanded here so that negating the pair negates both.
+fn spell_out_aromatic(expr: &AtomExpr) -> AtomExpr {
+    match expr {
+        Expr::Primitive(AtomPrimitive::Symbol {
+            element,
+            aromatic: true,
+        }) if !has_aromatic_symbol(*element) => Expr::And(vec![
+            Expr::Primitive(AtomPrimitive::AtomicNumber(element.atomic_number())),
+            Expr::Primitive(AtomPrimitive::Aromatic),
+        ]),
+        Expr::Primitive(p) => Expr::Primitive(p.clone()),
+        Expr::Not(e) => Expr::Not(Box::new(spell_out_aromatic(e))),
+        Expr::And(es) => Expr::And(es.iter().map(spell_out_aromatic).collect()),
+        Expr::Or(es) => Expr::Or(es.iter().map(spell_out_aromatic).collect()),
+    }
+}
+
+fn has_aromatic_symbol(element: Element) -> bool {
+    matches!(
+        element,
+        Element::B
+            | Element::C
+            | Element::N
+            | Element::O
+            | Element::P
+            | Element::S
+            | Element::As
+            | Element::Se
+    )
+}
+
+/// A conjunction of possibly negated primitives.
+type Term<'e, T> = Vec<(bool, &'e T)>;
+
+/// Writes an expression as a low precedence conjunction of disjunctions of high precedence
+/// conjunctions, such as `a&b,c;d`. SMARTS has no parentheses, so anything nested differently is
+/// expanded into that form.
+fn write_expr<T>(
+    out: &mut String,
+    expr: &Expr<T>,
+    separator: &str,
+    write_primitive: &mut impl FnMut(&mut String, &T),
+) {
+    let conjuncts = match expr {
+        Expr::And(es) => es.iter().collect(),
+        e => vec![e],
+    };
+    for (i, conjunct) in conjuncts.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(separator);
+        }
+        for (j, term) in disjunctive_form(conjunct, false).into_iter().enumerate() {
+            if j > 0 {
+                out.push(',');
+            }
+            for (k, (negated, primitive)) in term.into_iter().enumerate() {
+                if k > 0 {
+                    out.push('&');
+                }
+                if negated {
+                    out.push('!');
+                }
+                write_primitive(out, primitive);
+            }
+        }
+    }
+}
+
+/// The expression, or its negation, as a disjunction of conjunctions of primitives.
+fn disjunctive_form<T>(expr: &Expr<T>, negated: bool) -> Vec<Term<'_, T>> {
+    match (expr, negated) {
+        (Expr::Primitive(p), _) => vec![vec![(negated, p)]],
+        (Expr::Not(e), _) => disjunctive_form(e, !negated),
+        (Expr::Or(es), false) | (Expr::And(es), true) => es
+            .iter()
+            .flat_map(|e| disjunctive_form(e, negated))
+            .collect(),
+        (Expr::And(es), false) | (Expr::Or(es), true) => {
+            es.iter().fold(vec![vec![]], |terms, e| {
+                let other = disjunctive_form(e, negated);
+                terms
+                    .iter()
+                    .flat_map(|t| {
+                        other.iter().map(move |o| {
+                            let mut term = t.clone();
+                            term.extend(o.iter().cloned());
+                            term
+                        })
+                    })
+                    .collect()
+            })
+        }
+    }
+}
+
+fn write_atom_primitive(
+    out: &mut String,
+    primitive: &AtomPrimitive,
+    written_chirality: impl Fn(Chirality) -> Chirality,
+) {
+    use AtomPrimitive::*;
+    let counted = |out: &mut String, symbol: &str, n: Option<u8>| {
+        out.push_str(symbol);
+        if let Some(n) = n {
+            write!(out, "{}", n).unwrap();
+        }
+    };
+    match primitive {
+        Any => out.push('*'),
+        Aromatic => out.push('a'),
+        Aliphatic => out.push('A'),
+        // `H` alone is a hydrogen count
+        Symbol {
+            element: Element::H,
+            aromatic: false,
+        } => out.push_str("#1"),
+        Symbol {
+            element,
+            aromatic: false,
+        } => out.push_str(element.symbol()),
+        // Others are spelled out before writing
+        Symbol {
+            element,
+            aromatic: true,
+        } => out.push_str(&element.symbol().to_lowercase()),
+        AtomicNumber(n) => write!(out, "#{}", n).unwrap(),
+        Degree(n) => counted(out, "D", Some(*n)),
+        Connectivity(n) => counted(out, "X", Some(*n)),
+        TotalHydrogens(n) => counted(out, "H", Some(*n)),
+        ImplicitHydrogens(n) => counted(out, "h", *n),
+        RingMembership(n) => counted(out, "R", *n),
+        RingSize(n) => counted(out, "r", *n),
+        RingConnectivity(n) => counted(out, "x", *n),
+        Valence(n) => counted(out, "v", Some(*n)),
+        Charge(charge) => write_charge(out, *charge),
+        Isotope(isotope) => write!(out, "{}", isotope).unwrap(),
+        Chirality(chirality) => write_chirality(out, written_chirality(*chirality)),
+        Recursive(query) => write!(out, "$({})", query_to_smarts(query)).unwrap(),
+    }
+}
+
+fn write_chirality(out: &mut String, chirality: Chirality) {
+    match chirality {
+        Chirality::CounterClockwise => out.push('@'),
+        Chirality::Clockwise => out.push_str("@@"),
+    }
+}
+
+fn write_charge(out: &mut String, charge: i8) {
+    match charge {
+        1 => out.push('+'),
+        -1 => out.push('-'),
+        c if c >= 0 => write!(out, "+{}", c).unwrap(),
+        c => write!(out, "{}", c).unwrap(),
+    }
+}
+
+fn write_map(out: &mut String, map: Option<usize>) {
+    if let Some(map) = map {
+        write!(out, ":{}", map).unwrap();
+    }
+}
+
+fn write_ring_number(out: &mut String, number: usize) {
+    if number < 10 {
+        write!(out, "{}", number).unwrap();
+    } else {
+        write!(out, "%{}", number).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use crate::core::{Bond, Element, Molecule};
+use crate::daylight::{query_from_smarts, Query};
+use crate::substructure::SubstructureSearch;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Single bonds outside rings between atoms that aren't terminal or in triple bonds. This is
+/// RDKit's strict definition, which also leaves out the C–X bonds of amides, esters and their
+/// analogues, which barely rotate, and bonds to trihalomethyl and tert-butyl groups, whose
+/// rotation doesn't change their shape.
+const ROTATABLE: &str = "[!$(*#*)&!D1&!$(C(F)(F)F)&!$(C(Cl)(Cl)Cl)&!$(C(Br)(Br)Br)\
+    &!$(C([CH3])([CH3])[CH3])&!$([CD3](=[N,O,S])-!@[#7,O,S!D1])&!$([#7,O,S!D1]-!@[CD3]=[N,O,S])\
+    &!$([CD3](=[N+])-!@[#7!D1])&!$([#7!D1]-!@[CD3]=[N+])]-,:;!@[!$(*#*)&!D1&!$(C(F)(F)F)\
+    &!$(C(Cl)(Cl)Cl)&!$(C(Br)(Br)Br)&!$(C([CH3])([CH3])[CH3])]";
+
+impl Molecule {
+    /// Lipinski's count of hydrogen bond donors: the hydrogens on nitrogen and oxygen atoms.
+    pub fn lipinski_donors(&self) -> usize {
+        self.atoms()
+            .filter(|atom| matches!(self.atom(*atom).element, Element::N | Element::O))
+            .map(|atom| {
+                let explicit = self
+                    .neighbors(atom)
+                    .filter(|n| self.atom(*n).element == Element::H)
+                    .count();
+                self.atom(atom).hydrogens as usize + explicit
+            })
+            .sum()
+    }
+
+    /// Lipinski's count of hydrogen bond acceptors: the nitrogen and oxygen atoms.
+    pub fn lipinski_acceptors(&self) -> usize {
+        self.atoms()
+            .filter(|atom| matches!(self.atom(*atom).element, Element::N | Element::O))
+            .count()
+    }
+
+    pub fn rotatable_bond_count(&self) -> usize {
+        static QUERY: OnceLock<Query> = OnceLock::new();
+        let query = QUERY.get_or_init(|| query_from_smarts(ROTATABLE).unwrap());
+        let bonds: HashSet<_> = SubstructureSearch::new(query)
+            .matches(self)
+            .map(|atoms| (atoms[0].min(atoms[1]), atoms[0].max(atoms[1])))
+            .collect();
+        bonds.len()
+    }
+
+    /// The fraction of carbons that are sp3, with only single bonds, or zero if there are none.
+    pub fn fraction_sp3(&self) -> f64 {
+        let carbons: Vec<_> = self
+            .atoms()
+            .filter(|atom| self.atom(*atom).element == Element::C)
+            .collect();
+        if carbons.is_empty() {
+            return 0.0;
+        }
+        let sp3 = carbons
+            .iter()
+            .filter(|atom| {
+                !self.atom(**atom).aromatic
+                    && self
+                        .neighbors(**atom)
+                        .all(|n| self.bond(**atom, n) == Some(Bond::Single))
+            })
+            .count();
+        sp3 as f64 / carbons.len() as f64
+    }
+
+    /// The number of rings in the smallest set of smallest rings.
+    pub fn ring_count(&self) -> usize {
+        self.rings().num_rings()
+    }
+
+    /// The number of rings whose bonds are all aromatic.
+    pub fn aromatic_ring_count(&self) -> usize {
+        self.rings()
+            .sssr()
+            .iter()
+            .filter(|ring| {
+                ring.bonds()
+                    .all(|(a, b)| self.bond(a, b) == Some(Bond::Aromatic))
+            })
+            .count()
+    }
+
+    /// The number of rings with a bond that isn't aromatic.
+    pub fn aliphatic_ring_count(&self) -> usize {
+        self.ring_count() - self.aromatic_ring_count()
+    }
+}
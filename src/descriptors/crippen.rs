@@ -0,0 +1,321 @@
+use crate::core::{AtomIndex, Bond, Element, Molecule};
+use crate::daylight::{query_from_smarts, Query};
+use crate::substructure::{SubstructureSearch, Target};
+use std::sync::OnceLock;
+
+/// The Wildman–Crippen atom types of heavy atoms as (type, SMARTS, logP, molar refractivity),
+/// from J. Chem. Inf. Comput. Sci. 1999, 39, 868–873 as encoded by RDKit. Each atom takes the
+/// first type it matches, and types the paper gives no refractivity for have none here.
+const TYPES: &[(&str, &str, f64, f64)] = &[
+    ("C1", "[CH4]", 0.1441, 2.503),
+    ("C1", "[CH3]C", 0.1441, 2.503),
+    ("C1", "[CH2](C)C", 0.1441, 2.503),
+    ("C2", "[CH](C)(C)C", 0.0, 2.433),
+    ("C2", "[C](C)(C)(C)C", 0.0, 2.433),
+    ("C3", "[CH3][N,O,P,S,F,Cl,Br,I]", -0.2035, 2.753),
+    ("C3", "[CH2X4]([N,O,P,S,F,Cl,Br,I])[A;!#1]", -0.2035, 2.753),
+    (
+        "C4",
+        "[CH1X4]([N,O,P,S,F,Cl,Br,I])[A;!#1][A;!#1]",
+        -0.2051,
+        2.731,
+    ),
+    (
+        "C4",
+        "[CH0X4]([N,O,P,S,F,Cl,Br,I])[A;!#1]([A;!#1])[A;!#1]",
+        -0.2051,
+        2.731,
+    ),
+    ("C5", "[C]=[!C;A;!#1]", -0.2783, 5.007),
+    ("C6", "[CH2]=C", 0.1551, 3.513),
+    ("C6", "[CH1](=C)[A;!#1]", 0.1551, 3.513),
+    ("C6", "[CH0](=C)([A;!#1])[A;!#1]", 0.1551, 3.513),
+    ("C6", "[C](=C)=C", 0.1551, 3.513),
+    ("C7", "[CX2]#[A]", 0.0017, 3.888),
+    ("C8", "[CH3]c", 0.08452, 2.464),
+    ("C9", "[CH3]a", -0.1444, 2.412),
+    ("C10", "[CH2X4]a", -0.0516, 2.488),
+    ("C11", "[CHX4]a", 0.1193, 2.582),
+    ("C12", "[CH0X4]a", -0.0967, 2.576),
+    (
+        "C13",
+        "[cH0]-[A;!C;!N;!O;!S;!F;!Cl;!Br;!I;!#1]",
+        -0.5443,
+        4.041,
+    ),
+    ("C14", "[c][#9]", 0.0, 3.257),
+    ("C15", "[c][#17]", 0.245, 3.564),
+    ("C16", "[c][#35]", 0.198, 3.18),
+    ("C17", "[c][#53]", 0.0, 3.104),
+    ("C18", "[cH]", 0.1581, 3.35),
+    ("C19", "[c](:a)(:a):a", 0.2955, 4.346),
+    ("C20", "[c](:a)(:a)-a", 0.2713, 3.904),
+    ("C21", "[c](:a)(:a)-C", 0.136, 3.509),
+    ("C22", "[c](:a)(:a)-N", 0.4619, 3.067),
+    ("C23", "[c](:a)(:a)-O", 0.5437, 3.853),
+    ("C24", "[c](:a)(:a)-S", 0.1893, 2.673),
+    ("C25", "[c](:a)(:a)=[C,N,O]", -0.8186, 3.135),
+    ("C26", "[C](=C)(a)[A;!#1]", 0.264, 4.305),
+    ("C26", "[C](=C)(c)a", 0.264, 4.305),
+    ("C26", "[CH1](=C)a", 0.264, 4.305),
+    ("C26", "[C]=c", 0.264, 4.305),
+    (
+        "C27",
+        "[CX4][A;!C;!N;!O;!P;!S;!F;!Cl;!Br;!I;!#1]",
+        0.2148,
+        2.693,
+    ),
+    ("CS", "[#6]", 0.08129, 3.243),
+    ("N1", "[NH2+0][A;!#1]", -1.019, 2.262),
+    ("N2", "[NH+0]([A;!#1])[A;!#1]", -0.7096, 2.173),
+    ("N3", "[NH2+0]a", -1.027, 2.827),
+    ("N4", "[NH1+0]([!#1;A,a])a", -0.5188, 3.0),
+    ("N5", "[NH+0]=[!#1;A,a]", 0.08387, 1.757),
+    ("N6", "[N+0](=[!#1;A,a])[!#1;A,a]", 0.1836, 2.428),
+    ("N7", "[N+0]([A;!#1])([A;!#1])[A;!#1]", -0.3187, 1.839),
+    ("N8", "[N+0](a)([!#1;A,a])[A;!#1]", -0.4458, 2.819),
+    ("N8", "[N+0](a)(a)a", -0.4458, 2.819),
+    ("N9", "[N+0]#[A;!#1]", 0.01508, 1.725),
+    ("N10", "[NH3,NH2,NH;+,+2,+3]", -1.95, 0.0),
+    ("N11", "[n+0]", -0.3239, 2.202),
+    ("N12", "[n;+,+2,+3]", -1.119, 0.0),
+    (
+        "N13",
+        "[NH0;+,+2,+3]([A;!#1])([A;!#1])([A;!#1])[A;!#1]",
+        -0.3396,
+        0.2604,
+    ),
+    (
+        "N13",
+        "[NH0;+,+2,+3](=[A;!#1])([A;!#1])[!#1;A,a]",
+        -0.3396,
+        0.2604,
+    ),
+    ("N13", "[NH0;+,+2,+3](=[#6])=[#7]", -0.3396, 0.2604),
+    ("N14", "[N;+,+2,+3]#[A;!#1]", 0.2887, 3.359),
+    ("N14", "[N;-,-2,-3]", 0.2887, 3.359),
+    ("N14", "[N;+,+2,+3](=[N;-,-2,-3])=N", 0.2887, 3.359),
+    ("NS", "[#7]", -0.4806, 2.134),
+    ("O1", "[o]", 0.1552, 1.08),
+    ("O2", "[OH,OH2]", -0.2893, 0.8238),
+    ("O3", "[O]([A;!#1])[A;!#1]", -0.0684, 1.085),
+    ("O4", "[O](a)[A;!#1]", -0.4195, 1.182),
+    ("O4", "[O](a)a", -0.4195, 1.182),
+    ("O5", "[O]=[#7,#8]", 0.0335, 3.367),
+    ("O5", "[OX1;-;$([OX1;-][#7])]", 0.0335, 3.367),
+    ("O6", "[OX1;-;$([OX1;-][#16])]", -0.3339, 0.7774),
+    ("O6", "[O;+0]=[#16;+0]", -0.3339, 0.7774),
+    ("O12", "[O-]C(=O)", -1.326, 0.0),
+    ("O7", "[OX1;-;!$([OX1;-][#7,#16])]", -1.189, 0.0),
+    ("O8", "[O]=c", 0.1788, 3.135),
+    ("O9", "[O]=[CH]C", -0.1526, 0.0),
+    ("O9", "O=C(C)[A;!#1]", -0.1526, 0.0),
+    ("O9", "[O]=[CH2]", -0.1526, 0.0),
+    ("O9", "[O]=[CH]N", -0.1526, 0.0),
+    ("O9", "[O]=[CH]O", -0.1526, 0.0),
+    ("O9", "[O]=[CX2]=O", -0.1526, 0.0),
+    ("O10", "[O]=[CH]c", 0.1129, 0.2215),
+    ("O10", "O=C([C,c])[a;!#1]", 0.1129, 0.2215),
+    ("O10", "O=C(c)[A;!#1]", 0.1129, 0.2215),
+    ("O11", "O=C([!#1;!#6])[!#1;!#6]", 0.4833, 0.389),
+    ("OS", "[#8]", -0.1188, 0.6865),
+    ("F", "[#9+0]", 0.4202, 1.108),
+    ("Cl", "[#17+0]", 0.6895, 5.853),
+    ("Br", "[#35+0]", 0.8456, 8.927),
+    ("I", "[#53+0]", 0.8857, 14.02),
+    ("Hal", "[#9,#17,#35,#53;-]", -2.996, 0.0),
+    ("Hal", "[#53;+,+2,+3]", -2.996, 0.0),
+    ("Hal", "[+;#3,#11,#19,#37,#55]", -2.996, 0.0),
+    ("P", "[#15]", 0.8612, 6.92),
+    ("S1", "[S+0]", 0.6482, 7.591),
+    (
+        "S2",
+        "[S-,S-2,S-3,S-4,S+,S+2,S+3,S+4,S+5,S+6]",
+        -0.0024,
+        7.365,
+    ),
+    ("S3", "[s]", 0.6237, 6.691),
+    ("Me1", "[#3,#11,#19,#37,#55]", -0.3808, 5.754),
+    ("Me1", "[#4,#12,#20,#38,#56]", -0.3808, 5.754),
+    ("Me1", "[#5,#13,#31,#49,#81]", -0.3808, 5.754),
+    ("Me1", "[#14,#32,#50,#82]", -0.3808, 5.754),
+    ("Me1", "[#33,#51,#83]", -0.3808, 5.754),
+    ("Me1", "[#34,#52,#84]", -0.3808, 5.754),
+    (
+        "Me2",
+        "[#21,#22,#23,#24,#25,#26,#27,#28,#29,#30]",
+        -0.0025,
+        0.0,
+    ),
+    (
+        "Me2",
+        "[#39,#40,#41,#42,#43,#44,#45,#46,#47,#48]",
+        -0.0025,
+        0.0,
+    ),
+    (
+        "Me2",
+        "[#57,#58,#59,#60,#61,#62,#63,#64,#65,#66,#67,#68,#69,#70,#71,#72,#73,#74,#75,#76,#77,\
+         #78,#79,#80]",
+        -0.0025,
+        0.0,
+    ),
+    (
+        "Me2",
+        "[#89,#90,#91,#92,#93,#94,#95,#96,#97,#98,#99,#100,#101,#102,#103]",
+        -0.0025,
+        0.0,
+    ),
+];
+
+/// The hydrogen types as (type, logP, molar refractivity): on carbon, in alcohols, in amines,
+/// in acids and anything else.
+const HYDROGENS: [(&str, f64, f64); 5] = [
+    ("H1", 0.123, 1.057),
+    ("H2", -0.2677, 1.395),
+    ("H3", 0.2142, 0.9627),
+    ("H4", 0.298, 1.805),
+    ("HS", 0.1125, 1.112),
+];
+
+/// An atom's share of a molecule's Crippen logP and molar refractivity.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CrippenContribution {
+    /// The Wildman–Crippen type of the atom, if it has one.
+    pub atom_type: Option<&'static str>,
+    pub log_p: f64,
+    pub mr: f64,
+}
+
+/// The atom type SMARTS, parsed on first use.
+fn type_queries() -> &'static [Query] {
+    static QUERIES: OnceLock<Vec<Query>> = OnceLock::new();
+    QUERIES.get_or_init(|| {
+        TYPES
+            .iter()
+            .map(|(_, smarts, _, _)| query_from_smarts(smarts).unwrap())
+            .collect()
+    })
+}
+
+impl Molecule {
+    /// The Wildman–Crippen logP and molar refractivity contributions of each atom, by atom
+    /// index. Those of implicit hydrogens are included in the atoms they're attached to.
+    ///
+    /// Atom types depend on aromaticity, which is taken as given by the molecule.
+    pub fn crippen_contributions(&self) -> Vec<CrippenContribution> {
+        let mut contributions: Vec<CrippenContribution> = self
+            .atoms()
+            .map(|atom| {
+                let a = self.atom(atom);
+                let mut contribution = CrippenContribution {
+                    atom_type: None,
+                    log_p: 0.0,
+                    mr: 0.0,
+                };
+                if a.element == Element::H {
+                    let parent = self.neighbors(atom).next();
+                    let (name, log_p, mr) = HYDROGENS[hydrogen_type(self, parent, Some(atom))];
+                    contribution.atom_type = Some(name);
+                    contribution.log_p = log_p;
+                    contribution.mr = mr;
+                } else if a.hydrogens > 0 {
+                    let (_, log_p, mr) = HYDROGENS[hydrogen_type(self, Some(atom), None)];
+                    contribution.log_p = a.hydrogens as f64 * log_p;
+                    contribution.mr = a.hydrogens as f64 * mr;
+                }
+                contribution
+            })
+            .collect();
+
+        let mut typed: Vec<bool> = self
+            .atoms()
+            .map(|atom| self.atom(atom).element == Element::H)
+            .collect();
+        let target = Target::new(self);
+        for ((name, _, log_p, mr), query) in TYPES.iter().zip(type_queries()) {
+            if typed.iter().all(|t| *t) {
+                break;
+            }
+            let search = SubstructureSearch::new(query);
+            for atoms in search.target_matches(&target) {
+                let atom = atoms[0].index();
+                if !typed[atom] {
+                    typed[atom] = true;
+                    contributions[atom].atom_type = Some(name);
+                    contributions[atom].log_p += log_p;
+                    contributions[atom].mr += mr;
+                }
+            }
+        }
+        contributions
+    }
+
+    /// The Wildman–Crippen estimate of the octanol–water partition coefficient.
+    pub fn crippen_log_p(&self) -> f64 {
+        self.crippen_contributions().iter().map(|c| c.log_p).sum()
+    }
+
+    /// The Wildman–Crippen estimate of molar refractivity.
+    pub fn crippen_mr(&self) -> f64 {
+        self.crippen_contributions().iter().map(|c| c.mr).sum()
+    }
+}
+
+/// The type of a hydrogen attached to `parent`, which is either the hydrogen atom `hydrogen` or
+/// one of the parent's implicit hydrogens.
+fn hydrogen_type(mol: &Molecule, parent: Option<AtomIndex>, hydrogen: Option<AtomIndex>) -> usize {
+    let parent = match parent {
+        Some(parent) => parent,
+        None => return 4,
+    };
+    let p = mol.atom(parent);
+    match p.element {
+        Element::H | Element::C => return 0,
+        Element::N => return 2,
+        Element::O => {}
+        _ => return 1,
+    }
+
+    // The oxygen's other neighbours, with its other hydrogens
+    let others: Vec<AtomIndex> = mol
+        .neighbors(parent)
+        .filter(|n| Some(*n) != hydrogen)
+        .collect();
+    let other_hydrogens = p.hydrogens as usize - hydrogen.is_none() as usize;
+    let aliphatic = |atom: AtomIndex, element: Element| {
+        let a = mol.atom(atom);
+        a.element == element && !a.aromatic
+    };
+    let alcohol = others.iter().any(|n| {
+        let a = mol.atom(*n);
+        let carbon = a.element == Element::C;
+        let connections = mol.degree(*n) + a.hydrogens as usize;
+        // An sp3 or aromatic carbon, or anything but carbon, nitrogen, oxygen or sulfur
+        carbon && (a.aromatic || connections == 4)
+            || ![Element::C, Element::N, Element::O, Element::S].contains(&a.element)
+    });
+    if other_hydrogens > 0 || alcohol {
+        return 1;
+    }
+    if others.iter().any(|n| mol.atom(*n).element == Element::N) {
+        return 2;
+    }
+    let acid = others.iter().any(|n| {
+        aliphatic(*n, Element::O)
+            || aliphatic(*n, Element::S)
+            || aliphatic(*n, Element::C)
+                && mol.neighbors(*n).any(|m| {
+                    mol.bond(*n, m) == Some(Bond::Double)
+                        && (mol.atom(m).element == Element::C
+                            || mol.atom(m).element == Element::N
+                            || aliphatic(m, Element::O)
+                            || aliphatic(m, Element::S))
+                })
+    });
+    if acid {
+        3
+    } else {
+        4
+    }
+}
@@ -0,0 +1,159 @@
+mod counts;
+mod crippen;
+mod qed;
+mod rules;
+mod tpsa;
+
+pub use crippen::*;
+pub use qed::*;
+pub use rules::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::core::Molecule;
+    use crate::daylight::*;
+    use crate::descriptors::*;
+
+    fn smiles(s: &str) -> Molecule {
+        molecule_from_smiles(s).expect("Invalid SMILES")
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    const ASPIRIN: &str = "CC(=O)Oc1ccccc1C(=O)O";
+    const CAFFEINE: &str = "Cn1cnc2c1c(=O)n(C)c(=O)n2C";
+
+    #[test]
+    fn can_leave_atoms_untyped() {
+        // Francium matches none of the types, so every one is tried
+        let contributions = smiles("[Fr]").crippen_contributions();
+        assert_eq!(contributions[0].atom_type, None);
+        assert_close(contributions[0].log_p, 0.0);
+    }
+
+    #[test]
+    fn can_compute_crippen_log_p() {
+        assert_close(smiles("c1ccccc1").crippen_log_p(), 1.6866);
+        assert_close(smiles("CCO").crippen_log_p(), -0.0014);
+        assert_close(smiles("Oc1ccccc1").crippen_log_p(), 1.3922);
+        assert_close(smiles(ASPIRIN).crippen_log_p(), 1.3101);
+        assert_close(smiles(CAFFEINE).crippen_log_p(), -1.0293);
+        // Explicit hydrogens count the same as implicit ones
+        assert_close(smiles("[H]OCC").crippen_log_p(), -0.0014);
+
+        assert_close(smiles("c1ccccc1").crippen_mr(), 26.442);
+        assert_close(smiles("CCO").crippen_mr(), 12.7598);
+    }
+
+    #[test]
+    fn can_explain_crippen_contributions() {
+        let contributions = smiles("CCO").crippen_contributions();
+        let types: Vec<_> = contributions.iter().map(|c| c.atom_type).collect();
+        assert_eq!(types, vec![Some("C1"), Some("C3"), Some("O2")]);
+        // The hydroxyl oxygen and its alcohol hydrogen
+        assert_close(contributions[2].log_p, -0.2893 - 0.2677);
+    }
+
+    #[test]
+    fn can_compute_tpsa() {
+        assert_close(smiles("CCO").tpsa(), 20.23);
+        assert_close(smiles("c1ccncc1").tpsa(), 12.89);
+        assert_close(smiles(ASPIRIN).tpsa(), 63.6);
+        assert_close(smiles(CAFFEINE).tpsa(), 61.82);
+        assert_close(smiles("CCCC").tpsa(), 0.0);
+    }
+
+    #[test]
+    fn can_count_lipinski_donors_and_acceptors() {
+        let aspirin = smiles(ASPIRIN);
+        assert_eq!(aspirin.lipinski_donors(), 1);
+        assert_eq!(aspirin.lipinski_acceptors(), 4);
+        assert_eq!(smiles("NCCO").lipinski_donors(), 3);
+        assert_eq!(smiles(CAFFEINE).lipinski_donors(), 0);
+    }
+
+    #[test]
+    fn can_count_rotatable_bonds() {
+        assert_eq!(smiles(ASPIRIN).rotatable_bond_count(), 2);
+        assert_eq!(smiles("CCCC").rotatable_bond_count(), 1);
+        assert_eq!(smiles("CC").rotatable_bond_count(), 0);
+        // Bonds in rings and amide bonds don't count, though those beside them do
+        assert_eq!(smiles("C1CCCCC1").rotatable_bond_count(), 0);
+        assert_eq!(smiles("CC(=O)NCC").rotatable_bond_count(), 1);
+        assert_eq!(smiles("CC#CC").rotatable_bond_count(), 0);
+        assert_eq!(smiles("CCCC(F)(F)F").rotatable_bond_count(), 1);
+        assert_eq!(smiles("CC(C)(C)CC").rotatable_bond_count(), 0);
+    }
+
+    #[test]
+    fn can_compute_fraction_sp3() {
+        assert_close(smiles(ASPIRIN).fraction_sp3(), 1.0 / 9.0);
+        assert_close(smiles("C1CCCCC1").fraction_sp3(), 1.0);
+        assert_close(smiles("c1ccccc1").fraction_sp3(), 0.0);
+        assert_close(smiles("O").fraction_sp3(), 0.0);
+    }
+
+    #[test]
+    fn can_count_rings() {
+        let naphthalene = smiles("c1ccc2ccccc2c1");
+        assert_eq!(naphthalene.ring_count(), 2);
+        assert_eq!(naphthalene.aromatic_ring_count(), 2);
+        let indane = smiles("C1Cc2ccccc2C1");
+        assert_eq!(indane.aromatic_ring_count(), 1);
+        assert_eq!(indane.aliphatic_ring_count(), 1);
+        assert_eq!(smiles(ASPIRIN).heavy_atom_count(), 13);
+    }
+
+    #[test]
+    fn can_evaluate_drug_likeness_rules() {
+        let aspirin = smiles(ASPIRIN);
+        for rule in &[
+            DrugLikenessRule::Lipinski,
+            DrugLikenessRule::Veber,
+            DrugLikenessRule::Ghose,
+            DrugLikenessRule::Egan,
+        ] {
+            let evaluation = rule.evaluate(&aspirin);
+            assert!(evaluation.passes(), "{:?}", evaluation);
+            assert_eq!(evaluation.failures().count(), 0);
+        }
+        let lead_like = DrugLikenessRule::LeadLike.evaluate(&aspirin);
+        assert!(!lead_like.passes());
+        let failures: Vec<Descriptor> = lead_like.failures().map(|c| c.descriptor).collect();
+        assert_eq!(failures, vec![Descriptor::MolecularWeight]);
+
+        // Lipinski allows one failure but not two
+        let long = |n: usize| smiles(&"C".repeat(n));
+        let evaluation = DrugLikenessRule::Lipinski.evaluate(&long(30));
+        assert_eq!(evaluation.failures().count(), 1);
+        assert!(evaluation.passes());
+        let evaluation = DrugLikenessRule::Lipinski.evaluate(&long(40));
+        let failures: Vec<Descriptor> = evaluation.failures().map(|c| c.descriptor).collect();
+        assert_eq!(
+            failures,
+            vec![Descriptor::MolecularWeight, Descriptor::LogP]
+        );
+        assert!(!evaluation.passes());
+    }
+
+    #[test]
+    fn can_estimate_drug_likeness() {
+        let properties = smiles(ASPIRIN).qed_properties();
+        assert_eq!(properties.acceptors, 4);
+        assert_eq!(properties.donors, 1);
+        assert_eq!(properties.rotatable_bonds, 2);
+        assert_eq!(properties.aromatic_rings, 1);
+        // A phenyl ester and an ester
+        assert_eq!(properties.alerts, 2);
+        for d in properties.desirabilities().iter() {
+            assert!(*d > 0.0 && *d <= 1.0);
+        }
+        assert_close(smiles(ASPIRIN).qed(), 0.55012);
+        assert_close(smiles(CAFFEINE).qed(), 0.53846);
+        // Ibuprofen and paracetamol
+        assert_close(smiles("CC(C)Cc1ccc(C(C)C(=O)O)cc1").qed(), 0.82160);
+        assert_close(smiles("CC(=O)Nc1ccc(O)cc1").qed(), 0.59507);
+    }
+}
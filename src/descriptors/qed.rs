@@ -0,0 +1,213 @@
+use crate::core::Molecule;
+use crate::daylight::{query_from_smarts, Query};
+use crate::filters::FilterCatalog;
+use crate::substructure::{SubstructureSearch, Target};
+use std::sync::OnceLock;
+
+/// The parameters a, b, c, d, e, f and the maximum of the asymmetric double sigmoid desirability
+/// function of each QED property, from Bickerton et al., Nat. Chem. 2012, 4, 90–98.
+const DESIRABILITY: [[f64; 7]; 8] = [
+    // Molecular weight
+    [
+        2.817065973,
+        392.5754953,
+        290.7489764,
+        2.419764353,
+        49.22325677,
+        65.37051707,
+        104.9805561,
+    ],
+    // logP
+    [
+        3.172690585,
+        137.8624751,
+        2.534937431,
+        4.581497897,
+        0.822739154,
+        0.576295591,
+        131.3186604,
+    ],
+    // Acceptors
+    [
+        2.948620388,
+        160.4605972,
+        3.615294657,
+        4.435986202,
+        0.290141953,
+        1.300669958,
+        148.7763046,
+    ],
+    // Donors
+    [
+        1.618662227,
+        1010.051101,
+        0.985094388,
+        0.000000001,
+        0.713820843,
+        0.920922555,
+        258.1632616,
+    ],
+    // Polar surface area
+    [
+        1.876861559,
+        125.2232657,
+        62.90773554,
+        87.83366614,
+        12.01999824,
+        28.51324732,
+        104.5686167,
+    ],
+    // Rotatable bonds
+    [
+        0.010000000,
+        272.4121427,
+        2.558379970,
+        1.565547684,
+        1.271567166,
+        2.758063707,
+        105.4420403,
+    ],
+    // Aromatic rings
+    [
+        3.217788970,
+        957.7374108,
+        2.274627939,
+        0.000000001,
+        1.317690384,
+        0.375760881,
+        312.3372610,
+    ],
+    // Structural alerts
+    [
+        0.010000000,
+        1199.094025,
+        -0.09002883,
+        0.000000001,
+        0.185904477,
+        0.875193782,
+        417.7253140,
+    ],
+];
+
+/// The weights of the QED properties, in the same order, that give the best-performing weighted
+/// QED of the paper.
+pub const QED_WEIGHTS: [f64; 8] = [0.66, 0.46, 0.05, 0.61, 0.06, 0.65, 0.48, 0.95];
+
+/// Hydrogen bond acceptors, each atom counted once per pattern it matches.
+const ACCEPTORS: &[&str] = &[
+    "[oH0;X2]",
+    "[OH1;X2;v2]",
+    "[OH0;X2;v2]",
+    "[OH0;X1;v2]",
+    "[O-;X1]",
+    "[SH0;X2;v2]",
+    "[SH0;X1;v2]",
+    "[S-;X1]",
+    "[nH0;X2]",
+    "[NH0;X1;v3]",
+    "[$([N;+0;X3;v3]);!$(N[C,S]=O)]",
+];
+
+const DONORS: &str = "[N&!H0&v3,N&!H0&+1&v4,O&H1&+0,S&H1&+0,n&H1&+0]";
+
+/// The properties that QED combines.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct QedProperties {
+    pub molecular_weight: f64,
+    /// Crippen logP.
+    pub log_p: f64,
+    pub acceptors: usize,
+    pub donors: usize,
+    pub polar_surface_area: f64,
+    pub rotatable_bonds: usize,
+    pub aromatic_rings: usize,
+    /// The number of different structural alerts matched.
+    pub alerts: usize,
+}
+
+impl QedProperties {
+    /// The desirability of each property from 0 to 1, in the order of [`QED_WEIGHTS`].
+    pub fn desirabilities(&self) -> [f64; 8] {
+        let values = [
+            self.molecular_weight,
+            self.log_p,
+            self.acceptors as f64,
+            self.donors as f64,
+            self.polar_surface_area,
+            self.rotatable_bonds as f64,
+            self.aromatic_rings as f64,
+            self.alerts as f64,
+        ];
+        let mut desirabilities = [0.0; 8];
+        for (i, x) in values.iter().enumerate() {
+            let [a, b, c, d, e, f, max] = DESIRABILITY[i];
+            let rising = 1.0 + (-(x - c + d / 2.0) / e).exp();
+            let falling = 1.0 + (-(x - c - d / 2.0) / f).exp();
+            desirabilities[i] = (a + b / rising * (1.0 - 1.0 / falling)) / max;
+        }
+        desirabilities
+    }
+
+    /// The weighted geometric mean of the desirabilities.
+    pub fn qed(&self, weights: &[f64; 8]) -> f64 {
+        let total: f64 = weights.iter().sum();
+        let sum: f64 = self
+            .desirabilities()
+            .iter()
+            .zip(weights)
+            .map(|(d, w)| w * d.ln())
+            .sum();
+        (sum / total).exp()
+    }
+}
+
+/// The acceptor, donor and alert patterns, parsed on first use.
+struct Patterns {
+    acceptors: Vec<Query>,
+    donors: Query,
+    alerts: &'static FilterCatalog,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        acceptors: ACCEPTORS
+            .iter()
+            .map(|smarts| query_from_smarts(smarts).unwrap())
+            .collect(),
+        donors: query_from_smarts(DONORS).unwrap(),
+        alerts: FilterCatalog::brenk(),
+    })
+}
+
+impl Molecule {
+    pub fn qed_properties(&self) -> QedProperties {
+        let patterns = patterns();
+        let target = Target::new(self);
+        let count = |query: &Query| {
+            let search = SubstructureSearch::new(query).unique(true);
+            search.target_matches(&target).count()
+        };
+        let alerts = patterns
+            .alerts
+            .filters()
+            .iter()
+            .filter(|filter| SubstructureSearch::new(&filter.query).has_target_match(&target))
+            .count();
+        QedProperties {
+            molecular_weight: self.formula().molecular_weight(),
+            log_p: self.crippen_log_p(),
+            acceptors: patterns.acceptors.iter().map(count).sum(),
+            donors: count(&patterns.donors),
+            polar_surface_area: self.tpsa(),
+            rotatable_bonds: self.rotatable_bond_count(),
+            aromatic_rings: self.aromatic_ring_count(),
+            alerts,
+        }
+    }
+
+    /// The quantitative estimate of drug-likeness, from 0 to 1, with the paper's weights.
+    pub fn qed(&self) -> f64 {
+        self.qed_properties().qed(&QED_WEIGHTS)
+    }
+}
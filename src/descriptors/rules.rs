@@ -0,0 +1,142 @@
+use crate::core::Molecule;
+
+/// A descriptor that drug-likeness rules put limits on.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Descriptor {
+    MolecularWeight,
+    LogP,
+    MolarRefractivity,
+    Donors,
+    Acceptors,
+    RotatableBonds,
+    PolarSurfaceArea,
+    /// All atoms, including hydrogens.
+    AtomCount,
+}
+
+impl Descriptor {
+    fn compute(&self, mol: &Molecule) -> f64 {
+        match self {
+            Descriptor::MolecularWeight => mol.formula().molecular_weight(),
+            Descriptor::LogP => mol.crippen_log_p(),
+            Descriptor::MolarRefractivity => mol.crippen_mr(),
+            Descriptor::Donors => mol.lipinski_donors() as f64,
+            Descriptor::Acceptors => mol.lipinski_acceptors() as f64,
+            Descriptor::RotatableBonds => mol.rotatable_bond_count() as f64,
+            Descriptor::PolarSurfaceArea => mol.tpsa(),
+            Descriptor::AtomCount => mol.formula().elements().map(|(_, n)| n as f64).sum(),
+        }
+    }
+}
+
+/// A descriptor's value for a molecule and the range a rule allows it, inclusive.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Criterion {
+    pub descriptor: Descriptor,
+    pub value: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Criterion {
+    pub fn passes(&self) -> bool {
+        !matches!(self.min, Some(min) if self.value < min)
+            && !matches!(self.max, Some(max) if self.value > max)
+    }
+}
+
+/// A set of limits on descriptors that orally available drugs, or good leads for them, tend to
+/// fall within.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum DrugLikenessRule {
+    /// Lipinski's rule of five: molecular weight at most 500, logP at most 5, at most 5 donors
+    /// and at most 10 acceptors, with one of them allowed to fail.
+    Lipinski,
+    /// Veber: at most 10 rotatable bonds and a polar surface area of at most 140 Å².
+    Veber,
+    /// Ghose: molecular weight from 160 to 480, logP from -0.4 to 5.6, molar refractivity from
+    /// 40 to 130 and from 20 to 70 atoms.
+    Ghose,
+    /// Egan: logP at most 5.88 and a polar surface area of at most 131.6 Å².
+    Egan,
+    /// Lead-likeness as in Teague et al.: molecular weight from 250 to 350, logP at most 3.5 and
+    /// at most 7 rotatable bonds.
+    LeadLike,
+}
+
+impl DrugLikenessRule {
+    /// Each descriptor the rule limits, with its minimum and maximum.
+    fn limits(&self) -> Vec<(Descriptor, Option<f64>, Option<f64>)> {
+        use Descriptor::*;
+        match self {
+            DrugLikenessRule::Lipinski => vec![
+                (MolecularWeight, None, Some(500.0)),
+                (LogP, None, Some(5.0)),
+                (Donors, None, Some(5.0)),
+                (Acceptors, None, Some(10.0)),
+            ],
+            DrugLikenessRule::Veber => vec![
+                (RotatableBonds, None, Some(10.0)),
+                (PolarSurfaceArea, None, Some(140.0)),
+            ],
+            DrugLikenessRule::Ghose => vec![
+                (MolecularWeight, Some(160.0), Some(480.0)),
+                (LogP, Some(-0.4), Some(5.6)),
+                (MolarRefractivity, Some(40.0), Some(130.0)),
+                (AtomCount, Some(20.0), Some(70.0)),
+            ],
+            DrugLikenessRule::Egan => vec![
+                (LogP, None, Some(5.88)),
+                (PolarSurfaceArea, None, Some(131.6)),
+            ],
+            DrugLikenessRule::LeadLike => vec![
+                (MolecularWeight, Some(250.0), Some(350.0)),
+                (LogP, None, Some(3.5)),
+                (RotatableBonds, None, Some(7.0)),
+            ],
+        }
+    }
+
+    /// The number of criteria a molecule can fail and still pass.
+    pub fn allowed_failures(&self) -> usize {
+        match self {
+            DrugLikenessRule::Lipinski => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn evaluate(&self, mol: &Molecule) -> RuleEvaluation {
+        let criteria = self
+            .limits()
+            .into_iter()
+            .map(|(descriptor, min, max)| Criterion {
+                descriptor,
+                value: descriptor.compute(mol),
+                min,
+                max,
+            })
+            .collect();
+        RuleEvaluation {
+            rule: *self,
+            criteria,
+        }
+    }
+}
+
+/// How a molecule fares against each criterion of a rule.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RuleEvaluation {
+    pub rule: DrugLikenessRule,
+    pub criteria: Vec<Criterion>,
+}
+
+impl RuleEvaluation {
+    pub fn failures(&self) -> impl Iterator<Item = &Criterion> + '_ {
+        self.criteria.iter().filter(|c| !c.passes())
+    }
+
+    /// Whether no more criteria fail than the rule allows.
+    pub fn passes(&self) -> bool {
+        self.failures().count() <= self.rule.allowed_failures()
+    }
+}
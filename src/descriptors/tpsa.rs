@@ -0,0 +1,87 @@
+use crate::core::{AtomIndex, Bond, Element, Molecule, RingInfo};
+
+impl Molecule {
+    /// The topological polar surface area in Å², summing Ertl's contributions for each nitrogen
+    /// and oxygen from J. Med. Chem. 2000, 43, 3714–3717. Sulfur and phosphorus aren't counted,
+    /// as in the original method.
+    ///
+    /// Atoms in environments the method has no value for are estimated from their heavy
+    /// neighbours and hydrogens, as RDKit does.
+    pub fn tpsa(&self) -> f64 {
+        let rings = self.rings();
+        self.atoms()
+            .map(|atom| polar_contribution(self, &rings, atom))
+            .sum()
+    }
+}
+
+fn polar_contribution(mol: &Molecule, rings: &RingInfo, atom: AtomIndex) -> f64 {
+    let a = mol.atom(atom);
+    if a.element != Element::N && a.element != Element::O {
+        return 0.0;
+    }
+    let mut hydrogens = a.hydrogens as usize;
+    let (mut single, mut double, mut triple, mut aromatic) = (0, 0, 0, 0);
+    for n in mol.neighbors(atom) {
+        if mol.atom(n).element == Element::H {
+            hydrogens += 1;
+            continue;
+        }
+        match mol.bond(atom, n).unwrap() {
+            Bond::Single => single += 1,
+            Bond::Double => double += 1,
+            Bond::Triple => triple += 1,
+            Bond::Aromatic => aromatic += 1,
+        }
+    }
+    let neighbors = single + double + triple + aromatic;
+    let charge = a.formal_charge;
+    let in_three_ring = rings.is_in_ring_of_size(atom, 3);
+    // Each environment as hydrogens, charge and single, double, triple and aromatic bonds
+    let environment = (hydrogens, charge, [single, double, triple, aromatic]);
+
+    let contribution = if a.element == Element::N {
+        match environment {
+            (0, 0, [0, 0, 1, 0]) => Some(23.79),
+            (1, 0, [0, 1, 0, 0]) => Some(23.85),
+            (2, 0, [1, 0, 0, 0]) => Some(26.02),
+            (2, 1, [0, 1, 0, 0]) => Some(25.59),
+            (3, 1, [1, 0, 0, 0]) => Some(27.64),
+            (0, 0, [1, 1, 0, 0]) => Some(12.36),
+            (0, 0, [0, 1, 1, 0]) => Some(13.60),
+            (1, 0, [2, 0, 0, 0]) if in_three_ring => Some(21.94),
+            (1, 0, [2, 0, 0, 0]) => Some(12.03),
+            (0, 1, [1, 0, 1, 0]) => Some(4.36),
+            (1, 1, [1, 1, 0, 0]) => Some(13.97),
+            (2, 1, [2, 0, 0, 0]) => Some(16.61),
+            (0, 0, [0, 0, 0, 2]) => Some(12.89),
+            (1, 0, [0, 0, 0, 2]) => Some(15.79),
+            (1, 1, [0, 0, 0, 2]) => Some(14.14),
+            (0, 0, [3, 0, 0, 0]) if in_three_ring => Some(3.01),
+            (0, 0, [3, 0, 0, 0]) => Some(3.24),
+            (0, 0, [1, 2, 0, 0]) => Some(11.68),
+            (0, 1, [2, 1, 0, 0]) => Some(3.01),
+            (1, 1, [3, 0, 0, 0]) => Some(4.44),
+            (0, 0, [0, 0, 0, 3]) => Some(4.41),
+            (0, 0, [1, 0, 0, 2]) => Some(4.93),
+            (0, 0, [0, 1, 0, 2]) => Some(8.39),
+            (0, 1, [0, 0, 0, 3]) => Some(4.10),
+            (0, 1, [1, 0, 0, 2]) => Some(3.88),
+            (0, 1, [4, 0, 0, 0]) => Some(0.0),
+            _ => None,
+        }
+        .unwrap_or(30.5 - neighbors as f64 * 8.2 + hydrogens as f64 * 1.5)
+    } else {
+        match environment {
+            (0, 0, [0, 1, 0, 0]) => Some(17.07),
+            (1, 0, [1, 0, 0, 0]) => Some(20.23),
+            (0, -1, [1, 0, 0, 0]) => Some(23.06),
+            (0, 0, [2, 0, 0, 0]) if in_three_ring => Some(12.53),
+            (0, 0, [2, 0, 0, 0]) => Some(9.23),
+            (0, 0, [0, 0, 0, 2]) => Some(13.14),
+            _ => None,
+        }
+        .unwrap_or(28.5 - neighbors as f64 * 8.6 + hydrogens as f64 * 1.5)
+    };
+    contribution.max(0.0)
+}
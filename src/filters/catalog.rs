@@ -0,0 +1,210 @@
+use crate::core::{AtomIndex, Molecule};
+use crate::daylight::{query_from_smarts, Query, SmartsError};
+use crate::substructure::{SubstructureSearch, Target};
+use std::io::{self, BufRead};
+use std::sync::OnceLock;
+
+/// A named substructure that flags a molecule.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    pub name: String,
+    pub query: Query,
+}
+
+/// A match of a filter, with the atoms matched by each atom of its query in order.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FilterMatch {
+    pub name: String,
+    pub atoms: Vec<AtomIndex>,
+}
+
+/// A list of filters, such as the PAINS or Brenk structural alerts, to screen molecules against.
+#[derive(Clone, Debug)]
+pub struct FilterCatalog {
+    filters: Vec<Filter>,
+}
+
+impl FilterCatalog {
+    /// A catalog without any filters.
+    pub fn new() -> Self {
+        FilterCatalog { filters: vec![] }
+    }
+
+    /// The built in PAINS families seen most often. See [`pains`](FilterCatalog::pains).
+    pub fn pains_a() -> &'static Self {
+        static CATALOG: OnceLock<FilterCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| FilterCatalog::built_in(include_str!("data/pains_a.csv")))
+    }
+
+    pub fn pains_b() -> &'static Self {
+        static CATALOG: OnceLock<FilterCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| FilterCatalog::built_in(include_str!("data/pains_b.csv")))
+    }
+
+    pub fn pains_c() -> &'static Self {
+        static CATALOG: OnceLock<FilterCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| FilterCatalog::built_in(include_str!("data/pains_c.csv")))
+    }
+
+    /// The built in PAINS families of A, B and C together. These are an abridged selection of
+    /// the published families, each written as a single pattern, rather than the published
+    /// patterns themselves; to screen against those, load them with
+    /// [`add_smarts_file`](FilterCatalog::add_smarts_file).
+    pub fn pains() -> &'static Self {
+        static CATALOG: OnceLock<FilterCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| {
+            let mut catalog = FilterCatalog::pains_a().clone();
+            catalog.extend(FilterCatalog::pains_b());
+            catalog.extend(FilterCatalog::pains_c());
+            catalog
+        })
+    }
+
+    /// Brenk's unwanted groups, which are also the structural alerts of QED.
+    pub fn brenk() -> &'static Self {
+        static CATALOG: OnceLock<FilterCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| FilterCatalog::built_in(include_str!("data/brenk.csv")))
+    }
+
+    /// Groups that react covalently or are unstable, such as acyl halides, epoxides and
+    /// isocyanates.
+    pub fn reactive() -> &'static Self {
+        static CATALOG: OnceLock<FilterCatalog> = OnceLock::new();
+        CATALOG.get_or_init(|| FilterCatalog::built_in(include_str!("data/reactive.csv")))
+    }
+
+    /// A built in catalog, parsed from the text of its filter file.
+    fn built_in(text: &str) -> Self {
+        let mut catalog = FilterCatalog::new();
+        let errors = catalog.add_smarts_file(text.as_bytes()).unwrap();
+        assert!(errors.is_empty(), "Invalid built in filter SMARTS");
+        catalog
+    }
+
+    pub fn add(&mut self, name: &str, query: Query) {
+        self.filters.push(Filter {
+            name: name.to_string(),
+            query,
+        });
+    }
+
+    pub fn add_smarts(&mut self, name: &str, smarts: &str) -> Result<(), SmartsError> {
+        self.add(name, query_from_smarts(smarts)?);
+        Ok(())
+    }
+
+    /// Adds the filters of a file with a SMARTS pattern and then a name on each line, separated
+    /// by whitespace or a comma. Either may be in double quotes, and a name such as
+    /// `<regId=ene_rhod_A(235)>` is shortened to `ene_rhod_A`, so the published PAINS files can
+    /// be read as they are. Filters without a name are named by their line number, from 1, and
+    /// blank lines and those starting with `#` are skipped. Returns the line numbers of the SMARTS
+    /// that couldn't be parsed, with why.
+    ///
+    /// As the published files write hydrogens as atoms, hydrogen atoms are merged into the
+    /// hydrogen counts of their neighbours, as by [`Query::merge_hydrogens`], so they match
+    /// implicit hydrogens too. Matches of these filters don't include the merged atoms.
+    pub fn add_smarts_file(
+        &mut self,
+        reader: impl BufRead,
+    ) -> io::Result<Vec<(usize, SmartsError)>> {
+        let mut errors = vec![];
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (smarts, rest) = match line.strip_prefix('"') {
+                Some(quoted) => match quoted.find('"') {
+                    Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                    None => (quoted, ""),
+                },
+                None => line.split_at(unquoted_end(line)),
+            };
+            let name = rest.trim_start_matches(',').trim().trim_matches('"');
+            let name = match name.strip_prefix("<regId=") {
+                Some(id) => id.split(['(', '>']).next().unwrap(),
+                None => name,
+            };
+            let name = match name {
+                "" => (i + 1).to_string(),
+                name => name.to_string(),
+            };
+            match query_from_smarts(smarts) {
+                Ok(query) => self.add(&name, query.merge_hydrogens()),
+                Err(e) => errors.push((i + 1, e)),
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Adds the filters of another catalog after these.
+    pub fn extend(&mut self, other: &FilterCatalog) {
+        self.filters.extend(other.filters.iter().cloned());
+    }
+
+    pub fn filters(&self) -> &[Filter] {
+        &self.filters
+    }
+
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Every unique match of every filter, in the order of the filters.
+    pub fn matches(&self, mol: &Molecule) -> Vec<FilterMatch> {
+        let target = Target::new(mol);
+        let mut matches = vec![];
+        for filter in &self.filters {
+            let search = SubstructureSearch::new(&filter.query).unique(true);
+            matches.extend(search.target_matches(&target).map(|atoms| FilterMatch {
+                name: filter.name.clone(),
+                atoms,
+            }));
+        }
+        matches
+    }
+
+    /// The filters that match at least once.
+    pub fn matching_filters<'a>(&'a self, mol: &'a Molecule) -> impl Iterator<Item = &'a Filter> {
+        let target = Target::new(mol);
+        self.filters
+            .iter()
+            .filter(move |filter| SubstructureSearch::new(&filter.query).has_target_match(&target))
+    }
+
+    pub fn has_match(&self, mol: &Molecule) -> bool {
+        self.matching_filters(mol).next().is_some()
+    }
+}
+
+impl Default for FilterCatalog {
+    fn default() -> Self {
+        FilterCatalog::new()
+    }
+}
+
+/// The length of an unquoted SMARTS at the start of a line, which ends at whitespace or at a comma
+/// outside brackets that doesn't join two bond symbols, as in `-,:`.
+fn unquoted_end(line: &str) -> usize {
+    let mut depth = 0;
+    for (i, c) in line.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                let next = line[i + 1..].chars().next();
+                if !next.is_some_and(|c| "-=#:~@/\\!".contains(c)) {
+                    return i;
+                }
+            }
+            c if c.is_whitespace() => return i,
+            _ => (),
+        }
+    }
+    line.len()
+}
@@ -0,0 +1,141 @@
+mod catalog;
+
+pub use catalog::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{AtomIndex, Molecule};
+    use crate::daylight::*;
+    use crate::filters::*;
+
+    fn smiles(s: &str) -> Molecule {
+        molecule_from_smiles(s).expect("Invalid SMILES")
+    }
+
+    fn names(matches: &[FilterMatch]) -> Vec<&str> {
+        matches.iter().map(|m| m.name.as_str()).collect()
+    }
+
+    #[test]
+    fn built_in_catalogs_parse() {
+        assert_eq!(FilterCatalog::pains_a().len(), 11);
+        assert_eq!(FilterCatalog::pains_b().len(), 7);
+        assert_eq!(FilterCatalog::pains_c().len(), 5);
+        assert_eq!(FilterCatalog::pains().len(), 23);
+        assert_eq!(FilterCatalog::brenk().len(), 116);
+        assert_eq!(FilterCatalog::reactive().len(), 27);
+    }
+
+    #[test]
+    fn can_match_pains() {
+        let pains = FilterCatalog::pains();
+        // Catechol
+        let matches = pains.matches(&smiles("Oc1ccccc1O"));
+        assert_eq!(names(&matches), ["catechol_A"]);
+        let atoms: Vec<usize> = matches[0].atoms.iter().map(|a| a.index()).collect();
+        assert_eq!(atoms, [1, 0, 6, 7]);
+        // A 5-benzylidene rhodanine
+        let matches = pains.matches(&smiles("O=C1NC(=S)SC1=Cc1ccccc1"));
+        assert_eq!(names(&matches), ["ene_rhod_A"]);
+        assert_eq!(matches[0].atoms.len(), 8);
+        // The thiazolone analogue is a different family
+        let matches = pains.matches(&smiles("O=C1N=C(C)SC1=Cc1ccccc1"));
+        assert_eq!(names(&matches), ["ene_five_het_A"]);
+        assert!(!pains.has_match(&smiles("CC(=O)Nc1ccc(O)cc1")));
+        assert!(FilterCatalog::pains_c().has_match(&smiles("Oc1ccc(O)cc1")));
+    }
+
+    #[test]
+    fn can_match_brenk() {
+        let brenk = FilterCatalog::brenk();
+        // Aspirin
+        let mol = smiles("CC(=O)Oc1ccccc1C(=O)O");
+        let filters: Vec<&str> = brenk
+            .matching_filters(&mol)
+            .map(|filter| filter.name.as_str())
+            .collect();
+        assert_eq!(filters, ["phenol_ester", "ester"]);
+        assert!(!brenk.has_match(&smiles("CCN1CCCC1")));
+    }
+
+    #[test]
+    fn can_match_reactive_groups() {
+        let reactive = FilterCatalog::reactive();
+        // Acetyl chloride
+        let matches = reactive.matches(&smiles("CC(=O)Cl"));
+        assert_eq!(names(&matches), ["acyl_halide"]);
+        // Glycidol
+        let matches = reactive.matches(&smiles("OCC1CO1"));
+        assert_eq!(names(&matches), ["epoxide"]);
+        // Paracetamol
+        assert!(!reactive.has_match(&smiles("CC(=O)Nc1ccc(O)cc1")));
+    }
+
+    #[test]
+    fn can_read_filter_files() {
+        let text = "\
+# Written like the published PAINS files
+\"[#6]-[#6](-[#1])=[#6](-[#1])-[#6]=O\",\"<regId=ene_one_A(12)>\"
+
+[OH]c1ccccc1 phenol
+C(=O
+[N+](=O)[O-]
+C(=O)[OH],carboxylic_acid
+c-,:[F,Cl]
+";
+        let mut catalog = FilterCatalog::new();
+        let errors = catalog.add_smarts_file(text.as_bytes()).unwrap();
+        assert_eq!(errors, [(5, SmartsError::Syntax(1))]);
+        let names: Vec<&str> = catalog.filters().iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["ene_one_A", "phenol", "6", "carboxylic_acid", "8"]);
+        assert_eq!(catalog.filters()[4].query.atom_count(), 2);
+
+        // Written hydrogens match implicit ones, so crotonaldehyde has ene_one_A but mesityl
+        // oxide doesn't
+        let mol = smiles("CC=CC=O");
+        let filters: Vec<&str> = catalog
+            .matching_filters(&mol)
+            .map(|filter| filter.name.as_str())
+            .collect();
+        assert_eq!(filters, ["ene_one_A"]);
+        assert!(!catalog.has_match(&smiles("CC(C)=CC(C)=O")));
+    }
+
+    #[test]
+    fn catalogs_are_extensible() {
+        let mut catalog = FilterCatalog::new();
+        assert!(catalog.is_empty());
+        catalog.add_smarts("carboxylic_acid", "C(=O)[OH]").unwrap();
+        assert_eq!(
+            catalog.add_smarts("broken", "C(=O"),
+            Err(SmartsError::Syntax(1))
+        );
+        catalog.extend(FilterCatalog::pains_a());
+        assert_eq!(catalog.filters()[0].name, "carboxylic_acid");
+        assert_eq!(catalog.len(), FilterCatalog::pains_a().len() + 1);
+
+        // Malonic acid
+        let matches = catalog.matches(&smiles("OC(=O)CC(=O)O"));
+        assert_eq!(
+            matches,
+            [
+                FilterMatch {
+                    name: "carboxylic_acid".to_string(),
+                    atoms: vec![AtomIndex::new(1), AtomIndex::new(2), AtomIndex::new(0)],
+                },
+                FilterMatch {
+                    name: "carboxylic_acid".to_string(),
+                    atoms: vec![AtomIndex::new(4), AtomIndex::new(5), AtomIndex::new(6)],
+                },
+            ]
+        );
+
+        // Hydrogens written in SMARTS are kept as atoms, which only match explicit hydrogens
+        let mut catalog = FilterCatalog::new();
+        catalog.add_smarts("formyl", "[#6](-[#1])=O").unwrap();
+        assert_eq!(catalog.filters()[0].query.atom_count(), 3);
+        assert!(!catalog.has_match(&smiles("CC=O")));
+        let matches = catalog.matches(&smiles("CC([H])=O"));
+        assert_eq!(matches[0].atoms.len(), 3);
+    }
+}
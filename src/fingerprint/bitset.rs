@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+
+/// A fixed length bitset, as produced by folding a molecule's features into a number of bits.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Fingerprint {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Fingerprint {
+    /// A fingerprint of `len` bits, none of them set.
+    pub fn new(len: usize) -> Self {
+        Fingerprint {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    /// A fingerprint of `len` bits from words laid out as by [`words`](Fingerprint::words).
+    pub(crate) fn from_words(words: Vec<u64>, len: usize) -> Self {
+        debug_assert_eq!(words.len(), len.div_ceil(64));
+        Fingerprint { words, len }
+    }
+
+    /// The number of bits, set or not.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn set(&mut self, bit: usize) {
+        assert!(bit < self.len, "bit {} out of range", bit);
+        self.words[bit / 64] |= 1 << (bit % 64);
+    }
+
+    pub fn contains(&self, bit: usize) -> bool {
+        bit < self.len && self.words[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    /// The number of bits set.
+    pub fn count_ones(&self) -> u32 {
+        popcount(&self.words, None)
+    }
+
+    /// The set bits in ascending order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| i * 64 + bit)
+        })
+    }
+
+    /// Whether every bit set here is also set in `other`, which has the same length.
+    pub fn is_subset_of(&self, other: &Fingerprint) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .all(|(a, b)| a & !b == 0)
+    }
+
+    /// The underlying words, with bit `i` at bit `i % 64` of word `i / 64`.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+/// Counts the bits set in both of two lists of words, or in `a` alone if `b` is `None`, with the
+/// processor's popcount instruction if it has one.
+pub(crate) fn popcount(a: &[u64], b: Option<&[u64]>) -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("popcnt") {
+            // Safe as the instruction is available
+            return unsafe { hardware_popcount(a, b) };
+        }
+    }
+    software_popcount(a, b)
+}
+
+#[inline(always)]
+fn software_popcount(a: &[u64], b: Option<&[u64]>) -> u32 {
+    match b {
+        Some(b) => a.iter().zip(b).map(|(a, b)| (a & b).count_ones()).sum(),
+        None => a.iter().map(|a| a.count_ones()).sum(),
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "popcnt")]
+unsafe fn hardware_popcount(a: &[u64], b: Option<&[u64]>) -> u32 {
+    software_popcount(a, b)
+}
+
+/// A sparse count of each feature a molecule has, by feature identifier.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CountFingerprint {
+    counts: BTreeMap<u32, u32>,
+}
+
+impl CountFingerprint {
+    pub fn new() -> Self {
+        CountFingerprint {
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Counts one more of a feature.
+    pub fn add(&mut self, feature: u32) {
+        *self.counts.entry(feature).or_insert(0) += 1;
+    }
+
+    pub fn count(&self, feature: u32) -> u32 {
+        self.counts.get(&feature).copied().unwrap_or(0)
+    }
+
+    /// Each feature present with its count, in ascending order of feature.
+    pub fn counts(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.counts.iter().map(|(f, n)| (*f, *n))
+    }
+
+    /// The number of distinct features.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The counts with features folded into `len` identifiers, adding the counts of features
+    /// that collide.
+    pub fn fold(&self, len: usize) -> CountFingerprint {
+        let mut folded = CountFingerprint::new();
+        for (feature, count) in self.counts() {
+            *folded
+                .counts
+                .entry((feature as usize % len) as u32)
+                .or_insert(0) += count;
+        }
+        folded
+    }
+
+    /// A bit for each feature present, folded into `len` bits.
+    pub fn to_bits(&self, len: usize) -> Fingerprint {
+        let mut fingerprint = Fingerprint::new(len);
+        for feature in self.counts.keys() {
+            fingerprint.set(*feature as usize % len);
+        }
+        fingerprint
+    }
+}
@@ -0,0 +1,115 @@
+use crate::fingerprint::{Fingerprint, FingerprintIndex};
+
+/// A group of fingerprints by their indices, in ascending order, one of which is its centroid.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cluster {
+    pub centroid: usize,
+    pub members: Vec<usize>,
+}
+
+/// Clusters fingerprints by the Taylor–Butina algorithm, where fingerprints are neighbours if
+/// their Tanimoto distance, one minus their similarity, is at most `threshold`.
+///
+/// Fingerprints are taken in descending order of their number of neighbours, and ascending
+/// order of index among those with as many. Each one not yet clustered becomes the centroid of a
+/// cluster of itself and its neighbours not yet clustered. Every fingerprint ends up in exactly
+/// one cluster, those without neighbours alone, and clusters are in the order they were formed.
+pub fn butina(fingerprints: &[Fingerprint], threshold: f64) -> Vec<Cluster> {
+    let sorted = FingerprintIndex::new(fingerprints);
+    let neighbors: Vec<Vec<usize>> = fingerprints
+        .iter()
+        .enumerate()
+        .map(|(i, fp)| {
+            sorted
+                .threshold_search(fp, 1.0 - threshold)
+                .into_iter()
+                .map(|hit| hit.index)
+                .filter(|index| *index != i)
+                .collect()
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..fingerprints.len()).collect();
+    order.sort_by_key(|i| std::cmp::Reverse(neighbors[*i].len()));
+
+    let mut clustered = vec![false; fingerprints.len()];
+    let mut clusters = vec![];
+    for centroid in order {
+        if clustered[centroid] {
+            continue;
+        }
+        let mut members = vec![centroid];
+        members.extend(neighbors[centroid].iter().filter(|n| !clustered[**n]));
+        members.sort_unstable();
+        for member in &members {
+            clustered[*member] = true;
+        }
+        clusters.push(Cluster { centroid, members });
+    }
+    clusters
+}
+
+/// Picks diverse fingerprints by MaxMin: each pick is the fingerprint with the greatest Tanimoto
+/// distance to its nearest fingerprint already selected. The selection starts from the seeds,
+/// such as fingerprints already in a library, or otherwise from the first fingerprint.
+///
+/// Distances are computed lazily. A fingerprint is only compared with the picks made since it
+/// was last looked at, and not at all while its distance so far can't beat the best candidate,
+/// so that most pairs are never compared.
+#[derive(Clone, Debug)]
+pub struct MaxMinPicker<'a> {
+    fingerprints: &'a [Fingerprint],
+    seeds: &'a [Fingerprint],
+}
+
+impl<'a> MaxMinPicker<'a> {
+    pub fn new(fingerprints: &'a [Fingerprint]) -> Self {
+        MaxMinPicker {
+            fingerprints,
+            seeds: &[],
+        }
+    }
+
+    /// An existing selection to pick fingerprints away from.
+    pub fn seeds(mut self, seeds: &'a [Fingerprint]) -> Self {
+        self.seeds = seeds;
+        self
+    }
+
+    /// The indices of up to `n` fingerprints, in the order they were picked. Ties go to the
+    /// lower index.
+    pub fn pick(&self, n: usize) -> Vec<usize> {
+        let mut selected: Vec<&Fingerprint> = self.seeds.iter().collect();
+        let mut picks = vec![];
+        // The distance to the nearest selected fingerprint compared so far, and how many of the
+        // selected fingerprints that was
+        let mut nearest = vec![(f64::INFINITY, 0); self.fingerprints.len()];
+        let mut picked = vec![false; self.fingerprints.len()];
+        while picks.len() < n {
+            let mut best: Option<(f64, usize)> = None;
+            for (i, fp) in self.fingerprints.iter().enumerate() {
+                let beaten = |distance: f64| matches!(best, Some((b, _)) if distance <= b);
+                let (distance, compared) = &mut nearest[i];
+                if picked[i] || beaten(*distance) {
+                    continue;
+                }
+                while *compared < selected.len() && !beaten(*distance) {
+                    let d = 1.0 - fp.tanimoto(selected[*compared]);
+                    *distance = distance.min(d);
+                    *compared += 1;
+                }
+                if !beaten(*distance) {
+                    best = Some((*distance, i));
+                }
+            }
+            match best {
+                Some((_, i)) => {
+                    picked[i] = true;
+                    picks.push(i);
+                    selected.push(&self.fingerprints[i]);
+                }
+                None => break,
+            }
+        }
+        picks
+    }
+}
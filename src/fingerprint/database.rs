@@ -0,0 +1,406 @@
+use crate::core::Molecule;
+use crate::daylight::{molecule_from_smiles, molecule_to_canonical_smiles, SmilesError};
+use crate::fingerprint::similarity::{
+    count_starts, sorted_threshold_search, sorted_top_k, SortedFingerprints,
+};
+use crate::fingerprint::{Fingerprint, Hit};
+use memmap2::Mmap;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const MAGIC: &[u8; 8] = b"CHEMFPDB";
+const VERSION: u32 = 1;
+const HEADER: usize = 24;
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Io(io::Error),
+    /// The file isn't a fingerprint database, or is corrupt.
+    Format,
+    /// The fingerprints to store don't all have the same length.
+    MixedLengths,
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::Io(e) => write!(f, "{}", e),
+            DatabaseError::Format => write!(f, "not a valid fingerprint database"),
+            DatabaseError::MixedLengths => write!(f, "fingerprints have different lengths"),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<io::Error> for DatabaseError {
+    fn from(e: io::Error) -> Self {
+        DatabaseError::Io(e)
+    }
+}
+
+/// Collects molecules into a [`FingerprintDatabase`] file, storing each one's ID, canonical
+/// SMILES and the fingerprint computed for it.
+///
+/// Records are spooled to a temporary file as they're added, so only the bit count and position
+/// of each is kept in memory until the database is written.
+pub struct DatabaseBuilder<F: Fn(&Molecule) -> Fingerprint> {
+    fingerprinter: F,
+    spool: Option<Spool>,
+    /// The bit count and spool position of each record.
+    records: Vec<(u32, u64)>,
+}
+
+impl<F: Fn(&Molecule) -> Fingerprint> DatabaseBuilder<F> {
+    pub fn new(fingerprinter: F) -> Self {
+        DatabaseBuilder {
+            fingerprinter,
+            spool: None,
+            records: vec![],
+        }
+    }
+
+    pub fn add(&mut self, id: &str, mol: &Molecule) -> io::Result<()> {
+        let fingerprint = (self.fingerprinter)(mol);
+        let smiles = molecule_to_canonical_smiles(mol);
+        let spool = match &mut self.spool {
+            Some(spool) => spool,
+            None => self.spool.insert(Spool::new()?),
+        };
+        let position = spool.append(&fingerprint, id, &smiles)?;
+        self.records.push((fingerprint.count_ones(), position));
+        Ok(())
+    }
+
+    /// Adds the molecules of a SMILES file, with a SMILES string and then an optional ID on each
+    /// line, separated by whitespace. Molecules without an ID are identified by their line
+    /// number, from 1, and blank lines are skipped. Returns the line numbers of the SMILES that
+    /// couldn't be parsed, with why.
+    pub fn add_smi(&mut self, reader: impl BufRead) -> io::Result<Vec<(usize, SmilesError)>> {
+        let mut errors = vec![];
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut fields = line.trim().splitn(2, char::is_whitespace);
+            let smiles = match fields.next() {
+                Some(smiles) if !smiles.is_empty() => smiles,
+                _ => continue,
+            };
+            let id = match fields.next().map(str::trim) {
+                Some(id) if !id.is_empty() => id.to_string(),
+                _ => (i + 1).to_string(),
+            };
+            match molecule_from_smiles(smiles) {
+                Ok(mol) => self.add(&id, &mol)?,
+                Err(e) => errors.push((i + 1, e)),
+            }
+        }
+        Ok(errors)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Writes the database in ascending order of fingerprint bit count, keeping the order in
+    /// which molecules were added among those with the same count.
+    ///
+    /// The file is little-endian, with every section aligned to 8 bytes:
+    ///
+    /// - the magic bytes `CHEMFPDB`, a `u32` version, a `u32` fingerprint length in bits and a
+    ///   `u64` number of records
+    /// - a `u64` for each bit count from 0 to the length, the position of the first record with
+    ///   that count, and then the number of records
+    /// - the words of each fingerprint
+    /// - a `u64` offset into the strings for the start of each ID and each SMILES, and then the
+    ///   end of the strings
+    /// - the ID then SMILES of each record, in UTF-8
+    pub fn write(&mut self, path: impl AsRef<Path>) -> Result<(), DatabaseError> {
+        let map = match &mut self.spool {
+            Some(spool) => Some(spool.map()?),
+            None => None,
+        };
+        let records: Vec<Spooled> = self
+            .records
+            .iter()
+            .map(|(_, position)| Spooled::read(map.as_ref().unwrap(), *position as usize))
+            .collect();
+        let length = records.first().map_or(0, |r| r.length);
+        if records.iter().any(|r| r.length != length) {
+            return Err(DatabaseError::MixedLengths);
+        }
+        let counts: Vec<u32> = self.records.iter().map(|(count, _)| *count).collect();
+        let mut order: Vec<usize> = (0..records.len()).collect();
+        order.sort_by_key(|i| counts[*i]);
+        let mut starts = count_starts(&counts);
+        starts.resize(length + 2, records.len());
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&(length as u32).to_le_bytes())?;
+        file.write_all(&(records.len() as u64).to_le_bytes())?;
+        for start in starts {
+            file.write_all(&(start as u64).to_le_bytes())?;
+        }
+        for i in &order {
+            file.write_all(records[*i].words)?;
+        }
+        let mut offset = 0;
+        for i in &order {
+            for s in &[records[*i].id, records[*i].smiles] {
+                file.write_all(&(offset as u64).to_le_bytes())?;
+                offset += s.len();
+            }
+        }
+        file.write_all(&(offset as u64).to_le_bytes())?;
+        for i in &order {
+            file.write_all(records[*i].id)?;
+            file.write_all(records[*i].smiles)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// A temporary file of records, removed when dropped. Each record is a `u32` fingerprint length
+/// in bits and the fingerprint's words, then the ID and the SMILES each after a `u32` byte
+/// length, all little-endian.
+struct Spool {
+    path: PathBuf,
+    file: BufWriter<File>,
+    end: u64,
+}
+
+impl Spool {
+    fn new() -> io::Result<Self> {
+        static SPOOLS: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "chem-rs-{}-{}.spool",
+            std::process::id(),
+            SPOOLS.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = std::env::temp_dir().join(name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Spool {
+            path,
+            file: BufWriter::new(file),
+            end: 0,
+        })
+    }
+
+    /// Adds a record, returning its position.
+    fn append(&mut self, fingerprint: &Fingerprint, id: &str, smiles: &str) -> io::Result<u64> {
+        let position = self.end;
+        self.put(&(fingerprint.len() as u32).to_le_bytes())?;
+        for word in fingerprint.words() {
+            self.put(&word.to_le_bytes())?;
+        }
+        for s in &[id, smiles] {
+            self.put(&(s.len() as u32).to_le_bytes())?;
+            self.put(s.as_bytes())?;
+        }
+        Ok(position)
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.file.write_all(bytes)?;
+        self.end += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn map(&mut self) -> io::Result<Mmap> {
+        self.file.flush()?;
+        // The file is only written through the spool, which can't be while the map borrows it
+        unsafe { Mmap::map(self.file.get_ref()) }
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A record read back from a spool.
+struct Spooled<'a> {
+    length: usize,
+    words: &'a [u8],
+    id: &'a [u8],
+    smiles: &'a [u8],
+}
+
+impl<'a> Spooled<'a> {
+    fn read(map: &'a [u8], position: usize) -> Self {
+        let u32_at = |at: usize| u32::from_le_bytes(map[at..at + 4].try_into().unwrap()) as usize;
+        let length = u32_at(position);
+        let words = position + 4;
+        let id = words + length.div_ceil(64) * 8;
+        let smiles = id + 4 + u32_at(id);
+        let end = smiles + 4 + u32_at(smiles);
+        Spooled {
+            length,
+            words: &map[words..id],
+            id: &map[id + 4..smiles],
+            smiles: &map[smiles + 4..end],
+        }
+    }
+}
+
+/// A file of molecules and their fingerprints written by a [`DatabaseBuilder`], mapped into
+/// memory so that it's read from disk as it's searched rather than loaded up front. Records are
+/// in ascending order of bit count, which is the order of their indices.
+pub struct FingerprintDatabase {
+    map: Mmap,
+    length: usize,
+    count: usize,
+    starts: Vec<usize>,
+    fingerprints: usize,
+    offsets: usize,
+    strings: usize,
+}
+
+impl FingerprintDatabase {
+    /// Opens a database, checking that its structure is valid. The file must not be changed
+    /// while it's open.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        let file = File::open(path)?;
+        // Safe so long as no one changes the file while it's mapped, as documented
+        let map = unsafe { Mmap::map(&file)? };
+        if cfg!(target_endian = "big") || map.len() < HEADER || &map[..8] != MAGIC {
+            return Err(DatabaseError::Format);
+        }
+        let read = |position: usize| -> Option<u64> {
+            let bytes = map.get(position..position + 8)?;
+            Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        let header = read(8).unwrap();
+        let (version, length) = (header as u32, (header >> 32) as usize);
+        let count = read(16).unwrap() as usize;
+        if version != VERSION {
+            return Err(DatabaseError::Format);
+        }
+
+        let starts: Option<Vec<usize>> = (0..length + 2)
+            .map(|i| read(HEADER + i * 8).map(|start| start as usize))
+            .collect();
+        let starts = starts.ok_or(DatabaseError::Format)?;
+        let fingerprints = HEADER + starts.len() * 8;
+        let sections = count
+            .checked_mul(length.div_ceil(64) * 8)
+            .and_then(|words| words.checked_add(fingerprints))
+            .and_then(|offsets| Some((offsets, count.checked_mul(16)?.checked_add(offsets + 8)?)));
+        let (offsets, strings) = match sections {
+            Some((offsets, strings)) if strings <= map.len() => (offsets, strings),
+            _ => return Err(DatabaseError::Format),
+        };
+        if starts[0] != 0
+            || starts[starts.len() - 1] != count
+            || starts.windows(2).any(|w| w[0] > w[1])
+        {
+            return Err(DatabaseError::Format);
+        }
+        let text = std::str::from_utf8(&map[strings..]).map_err(|_| DatabaseError::Format)?;
+        let mut previous = 0;
+        for i in 0..2 * count + 1 {
+            let offset = read(offsets + i * 8).unwrap() as usize;
+            if offset < previous || !text.is_char_boundary(offset) {
+                return Err(DatabaseError::Format);
+            }
+            previous = offset;
+        }
+        if previous != text.len() {
+            return Err(DatabaseError::Format);
+        }
+
+        Ok(FingerprintDatabase {
+            map,
+            length,
+            count,
+            starts,
+            fingerprints,
+            offsets,
+            strings,
+        })
+    }
+
+    /// The number of records.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The number of bits in each fingerprint.
+    pub fn fingerprint_length(&self) -> usize {
+        self.length
+    }
+
+    pub fn id(&self, index: usize) -> &str {
+        self.string(2 * index)
+    }
+
+    pub fn smiles(&self, index: usize) -> &str {
+        self.string(2 * index + 1)
+    }
+
+    pub fn fingerprint(&self, index: usize) -> Fingerprint {
+        Fingerprint::from_words(self.words(index).to_vec(), self.length)
+    }
+
+    /// The `k` records most similar to the query, as in [`top_k`](crate::fingerprint::top_k).
+    pub fn top_k(&self, query: &Fingerprint, k: usize) -> Vec<Hit> {
+        sorted_top_k(query, self, k)
+    }
+
+    /// The records at least `threshold` similar to the query, as in
+    /// [`threshold_search`](crate::fingerprint::threshold_search).
+    pub fn threshold_search(&self, query: &Fingerprint, threshold: f64) -> Vec<Hit> {
+        sorted_threshold_search(query, self, threshold)
+    }
+
+    fn words(&self, index: usize) -> &[u64] {
+        assert!(index < self.count, "record {} out of range", index);
+        let words = self.length.div_ceil(64);
+        let start = self.fingerprints + index * words * 8;
+        let bytes = &self.map[start..start + words * 8];
+        // Safe as every section starts 8 bytes apart from the page-aligned map, and any bit
+        // pattern is a valid u64 in the little-endian order it was written in
+        let (before, words, _) = unsafe { bytes.align_to::<u64>() };
+        assert!(before.is_empty());
+        words
+    }
+
+    fn string(&self, i: usize) -> &str {
+        assert!(i < 2 * self.count, "record {} out of range", i / 2);
+        let offset = |i: usize| {
+            let start = self.offsets + i * 8;
+            let bytes = self.map[start..start + 8].try_into().unwrap();
+            self.strings + u64::from_le_bytes(bytes) as usize
+        };
+        std::str::from_utf8(&self.map[offset(i)..offset(i + 1)]).unwrap()
+    }
+}
+
+impl SortedFingerprints for FingerprintDatabase {
+    fn starts(&self) -> &[usize] {
+        &self.starts
+    }
+
+    fn get(&self, position: usize) -> (usize, &[u64]) {
+        (position, self.words(position))
+    }
+}
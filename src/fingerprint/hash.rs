@@ -0,0 +1,26 @@
+use crate::core::{AtomIndex, Bond, Element, Molecule};
+
+/// Combines values into a feature identifier. The result depends on the order of the values and
+/// is the same on every platform and run, so fingerprints can be stored and compared later.
+pub(crate) fn hash(values: impl IntoIterator<Item = u32>) -> u32 {
+    values.into_iter().fold(0, |seed: u32, value| {
+        seed ^ value
+            .wrapping_add(0x9e37_79b9)
+            .wrapping_add(seed << 6)
+            .wrapping_add(seed >> 2)
+    })
+}
+
+pub(crate) fn bond_invariant(bond: Bond) -> u32 {
+    match bond {
+        Bond::Single => 1,
+        Bond::Double => 2,
+        Bond::Triple => 3,
+        Bond::Aromatic => 4,
+    }
+}
+
+/// Whether an atom isn't a hydrogen. Fingerprints leave out hydrogens, as they're often implicit.
+pub(crate) fn is_heavy(mol: &Molecule, atom: AtomIndex) -> bool {
+    mol.atom(atom).element != Element::H
+}
@@ -0,0 +1,413 @@
+use crate::core::{Element, Molecule};
+use crate::daylight::{query_from_smarts, Query};
+use crate::fingerprint::Fingerprint;
+use crate::substructure::{SubstructureSearch, Target};
+
+/// The public MACCS key definitions as a SMARTS pattern and the number of unique matches a
+/// molecule must have more than, with `?` for keys that are computed directly.
+const KEYS: [(&str, usize); 166] = [
+    // 1: ISOTOPE
+    ("?", 0),
+    // 2: Atomic number > 103
+    ("?", 0),
+    // 3: Group IVa, Va, VIa rows 4-6
+    ("[#32,#33,#34,#50,#51,#52,#82,#83,#84]", 0),
+    // 4: Actinide
+    ("[Ac,Th,Pa,U,Np,Pu,Am,Cm,Bk,Cf,Es,Fm,Md,No,Lr]", 0),
+    // 5: Group IIIB, IVB
+    ("[Sc,Ti,Y,Zr,Hf]", 0),
+    // 6: Lanthanide
+    ("[La,Ce,Pr,Nd,Pm,Sm,Eu,Gd,Tb,Dy,Ho,Er,Tm,Yb,Lu]", 0),
+    // 7: Group VB, VIB, VIIB
+    ("[V,Cr,Mn,Nb,Mo,Tc,Ta,W,Re]", 0),
+    // 8: QAAA@1
+    ("[!#6;!#1]1~*~*~*~1", 0),
+    // 9: Group VIII
+    ("[Fe,Co,Ni,Ru,Rh,Pd,Os,Ir,Pt]", 0),
+    // 10: Group IIa
+    ("[Be,Mg,Ca,Sr,Ba,Ra]", 0),
+    // 11: 4M ring
+    ("*1~*~*~*~1", 0),
+    // 12: Group IB, IIB
+    ("[Cu,Zn,Ag,Cd,Au,Hg]", 0),
+    // 13: ON(C)C
+    ("[#8]~[#7](~[#6])~[#6]", 0),
+    // 14: S-S
+    ("[#16]-[#16]", 0),
+    // 15: OC(O)O
+    ("[#8]~[#6](~[#8])~[#8]", 0),
+    // 16: QAA@1
+    ("[!#6;!#1]1~*~*~1", 0),
+    // 17: CTC
+    ("[#6]#[#6]", 0),
+    // 18: Group IIIA
+    ("[#5,#13,#31,#49,#81]", 0),
+    // 19: 7M ring
+    ("*1~*~*~*~*~*~*~1", 0),
+    // 20: Si
+    ("[#14]", 0),
+    // 21: C=C(Q)Q
+    ("[#6]=[#6](~[!#6;!#1])~[!#6;!#1]", 0),
+    // 22: 3M ring
+    ("*1~*~*~1", 0),
+    // 23: NC(O)O
+    ("[#7]~[#6](~[#8])~[#8]", 0),
+    // 24: N-O
+    ("[#7]-[#8]", 0),
+    // 25: NC(N)N
+    ("[#7]~[#6](~[#7])~[#7]", 0),
+    // 26: C$=C($A)$A
+    ("[#6]=;@[#6](@*)@*", 0),
+    // 27: I
+    ("[I]", 0),
+    // 28: QCH2Q
+    ("[!#6;!#1]~[CH2]~[!#6;!#1]", 0),
+    // 29: P
+    ("[#15]", 0),
+    // 30: CQ(C)(C)A
+    ("[#6]~[!#6;!#1](~[#6])(~[#6])~*", 0),
+    // 31: QX
+    ("[!#6;!#1]~[F,Cl,Br,I]", 0),
+    // 32: CSN
+    ("[#6]~[#16]~[#7]", 0),
+    // 33: NS
+    ("[#7]~[#16]", 0),
+    // 34: CH2=A
+    ("[CH2]=*", 0),
+    // 35: Group IA
+    ("[Li,Na,K,Rb,Cs,Fr]", 0),
+    // 36: S heterocycle
+    ("[#16R]", 0),
+    // 37: NC(O)N
+    ("[#7]~[#6](~[#8])~[#7]", 0),
+    // 38: NC(C)N
+    ("[#7]~[#6](~[#6])~[#7]", 0),
+    // 39: OS(O)O
+    ("[#8]~[#16](~[#8])~[#8]", 0),
+    // 40: S-O
+    ("[#16]-[#8]", 0),
+    // 41: CTN
+    ("[#6]#[#7]", 0),
+    // 42: F
+    ("F", 0),
+    // 43: QHAQH
+    ("[!#6;!#1;!H0]~*~[!#6;!#1;!H0]", 0),
+    // 44: OTHER
+    ("?", 0),
+    // 45: C=CN
+    ("[#6]=[#6]~[#7]", 0),
+    // 46: Br
+    ("Br", 0),
+    // 47: SAN
+    ("[#16]~*~[#7]", 0),
+    // 48: OQ(O)O
+    ("[#8]~[!#6;!#1](~[#8])(~[#8])", 0),
+    // 49: Charge
+    ("[!+0]", 0),
+    // 50: C=C(C)C
+    ("[#6]=[#6](~[#6])~[#6]", 0),
+    // 51: CSO
+    ("[#6]~[#16]~[#8]", 0),
+    // 52: NN
+    ("[#7]~[#7]", 0),
+    // 53: QHAAAQH
+    ("[!#6;!#1;!H0]~*~*~*~[!#6;!#1;!H0]", 0),
+    // 54: QHAAQH
+    ("[!#6;!#1;!H0]~*~*~[!#6;!#1;!H0]", 0),
+    // 55: OSO
+    ("[#8]~[#16]~[#8]", 0),
+    // 56: ON(O)C
+    ("[#8]~[#7](~[#8])~[#6]", 0),
+    // 57: O heterocycle
+    ("[O;R]", 0),
+    // 58: QSQ
+    ("[!#6;!#1]~[#16]~[!#6;!#1]", 0),
+    // 59: Snot%A%A
+    ("[#16]!:*:*", 0),
+    // 60: S=O
+    ("[#16]=[#8]", 0),
+    // 61: AS(A)A
+    ("*~[#16](~*)~*", 0),
+    // 62: A$!A$A
+    ("*@*!@*@*", 0),
+    // 63: N=O
+    ("[#7]=[#8]", 0),
+    // 64: A$A!S
+    ("*@*!@[#16]", 0),
+    // 65: C%N
+    ("c:n", 0),
+    // 66: CC(C)(C)A
+    ("[#6]~[#6](~[#6])(~[#6])~*", 0),
+    // 67: QS
+    ("[!#6;!#1]~[#16]", 0),
+    // 68: QHQH
+    ("[!#6;!#1;!H0]~[!#6;!#1;!H0]", 0),
+    // 69: QQH
+    ("[!#6;!#1]~[!#6;!#1;!H0]", 0),
+    // 70: QNQ
+    ("[!#6;!#1]~[#7]~[!#6;!#1]", 0),
+    // 71: NO
+    ("[#7]~[#8]", 0),
+    // 72: OAAO
+    ("[#8]~*~*~[#8]", 0),
+    // 73: S=A
+    ("[#16]=*", 0),
+    // 74: CH3ACH3
+    ("[CH3]~*~[CH3]", 0),
+    // 75: A!N$A
+    ("*!@[#7]@*", 0),
+    // 76: C=C(A)A
+    ("[#6]=[#6](~*)~*", 0),
+    // 77: NAN
+    ("[#7]~*~[#7]", 0),
+    // 78: C=N
+    ("[#6]=[#7]", 0),
+    // 79: NAAN
+    ("[#7]~*~*~[#7]", 0),
+    // 80: NAAAN
+    ("[#7]~*~*~*~[#7]", 0),
+    // 81: SA(A)A
+    ("[#16]~*(~*)~*", 0),
+    // 82: ACH2QH
+    ("*~[CH2]~[!#6;!#1;!H0]", 0),
+    // 83: QAAAA@1
+    ("[!#6;!#1]1~*~*~*~*~1", 0),
+    // 84: NH2
+    ("[NH2]", 0),
+    // 85: CN(C)C
+    ("[#6]~[#7](~[#6])~[#6]", 0),
+    // 86: CH2QCH2
+    ("[C;H2,H3][!#6;!#1][C;H2,H3]", 0),
+    // 87: X!A$A
+    ("[F,Cl,Br,I]!@*@*", 0),
+    // 88: S
+    ("[#16]", 0),
+    // 89: OAAAO
+    ("[#8]~*~*~*~[#8]", 0),
+    // 90: QHAACH2A
+    ("[$([!#6;!#1;!H0]~*~*~[CH2]~*),$([!#6;!#1;!H0;R]1@[R]@[R]@[CH2;R]1),$([!#6;!#1;!H0]~[R]1@[R]@[CH2;R]1)]", 0),
+    // 91: QHAAACH2A
+    ("[$([!#6;!#1;!H0]~*~*~*~[CH2]~*),$([!#6;!#1;!H0;R]1@[R]@[R]@[R]@[CH2;R]1),$([!#6;!#1;!H0]~[R]1@[R]@[R]@[CH2;R]1),$([!#6;!#1;!H0]~*~[R]1@[R]@[CH2;R]1)]", 0),
+    // 92: OC(N)C
+    ("[#8]~[#6](~[#7])~[#6]", 0),
+    // 93: QCH3
+    ("[!#6;!#1]~[CH3]", 0),
+    // 94: QN
+    ("[!#6;!#1]~[#7]", 0),
+    // 95: NAAO
+    ("[#7]~*~*~[#8]", 0),
+    // 96: 5M ring
+    ("*1~*~*~*~*~1", 0),
+    // 97: NAAAO
+    ("[#7]~*~*~*~[#8]", 0),
+    // 98: QAAAAA@1
+    ("[!#6;!#1]1~*~*~*~*~*~1", 0),
+    // 99: C=C
+    ("[#6]=[#6]", 0),
+    // 100: ACH2N
+    ("*~[CH2]~[#7]", 0),
+    // 101: 8M ring or larger
+    ("[$([R]@1@[R]@[R]@[R]@[R]@[R]@[R]@[R]1),$([R]@1@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]1),$([R]@1@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]1),$([R]@1@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]1),$([R]@1@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]1),$([R]@1@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]1),$([R]@1@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]@[R]1)]", 0),
+    // 102: QO
+    ("[!#6;!#1]~[#8]", 0),
+    // 103: Cl
+    ("Cl", 0),
+    // 104: QHACH2A
+    ("[!#6;!#1;!H0]~*~[CH2]~*", 0),
+    // 105: A$A($A)$A
+    ("*@*(@*)@*", 0),
+    // 106: QA(Q)Q
+    ("[!#6;!#1]~*(~[!#6;!#1])~[!#6;!#1]", 0),
+    // 107: XA(A)A
+    ("[F,Cl,Br,I]~*(~*)~*", 0),
+    // 108: CH3AAACH2A
+    ("[CH3]~*~*~*~[CH2]~*", 0),
+    // 109: ACH2O
+    ("*~[CH2]~[#8]", 0),
+    // 110: NCO
+    ("[#7]~[#6]~[#8]", 0),
+    // 111: NACH2A
+    ("[#7]~*~[CH2]~*", 0),
+    // 112: AA(A)(A)A
+    ("*~*(~*)(~*)~*", 0),
+    // 113: Onot%A%A
+    ("[#8]!:*:*", 0),
+    // 114: CH3CH2A
+    ("[CH3]~[CH2]~*", 0),
+    // 115: CH3ACH2A
+    ("[CH3]~*~[CH2]~*", 0),
+    // 116: CH3AACH2A
+    ("[$([CH3]~*~*~[CH2]~*),$([CH3]~*1~*~[CH2]1)]", 0),
+    // 117: NAO
+    ("[#7]~*~[#8]", 0),
+    // 118: ACH2CH2A > 1
+    ("[$(*~[CH2]~[CH2]~*),$(*1~[CH2]~[CH2]1)]", 1),
+    // 119: N=A
+    ("[#7]=*", 0),
+    // 120: Heterocyclic atom > 1
+    ("[!#6;R]", 1),
+    // 121: N heterocycle
+    ("[#7;R]", 0),
+    // 122: AN(A)A
+    ("*~[#7](~*)~*", 0),
+    // 123: OCO
+    ("[#8]~[#6]~[#8]", 0),
+    // 124: QQ
+    ("[!#6;!#1]~[!#6;!#1]", 0),
+    // 125: Aromatic ring > 1
+    ("?", 0),
+    // 126: A!O!A
+    ("*!@[#8]!@*", 0),
+    // 127: A$A!O > 1
+    ("*@*!@[#8]", 1),
+    // 128: ACH2AAACH2A
+    ("[$(*~[CH2]~*~*~*~[CH2]~*),$([R]1@[CH2;R]@[R]@[R]@[R]@[CH2;R]1),$(*~[CH2]~[R]1@[R]@[R]@[CH2;R]1),$(*~[CH2]~*~[R]1@[R]@[CH2;R]1)]", 0),
+    // 129: ACH2AACH2A
+    ("[$(*~[CH2]~*~*~[CH2]~*),$([R]1@[CH2]@[R]@[R]@[CH2;R]1),$(*~[CH2]~[R]1@[R]@[CH2;R]1)]", 0),
+    // 130: QQ > 1
+    ("[!#6;!#1]~[!#6;!#1]", 1),
+    // 131: QH > 1
+    ("[!#6;!#1;!H0]", 1),
+    // 132: OACH2A
+    ("[#8]~*~[CH2]~*", 0),
+    // 133: A$A!N
+    ("*@*!@[#7]", 0),
+    // 134: X
+    ("[F,Cl,Br,I]", 0),
+    // 135: Nnot%A%A
+    ("[#7]!:*:*", 0),
+    // 136: O=A > 1
+    ("[#8]=*", 1),
+    // 137: Heterocycle
+    ("[!C;!c;R]", 0),
+    // 138: QCH2A > 1
+    ("[!#6;!#1]~[CH2]~*", 1),
+    // 139: OH
+    ("[O;!H0]", 0),
+    // 140: O > 3
+    ("[#8]", 3),
+    // 141: CH3 > 2
+    ("[CH3]", 2),
+    // 142: N > 1
+    ("[#7]", 1),
+    // 143: A$A!O
+    ("*@*!@[#8]", 0),
+    // 144: Anot%A%Anot%A
+    ("*!:*:*!:*", 0),
+    // 145: 6M ring > 1
+    ("*1~*~*~*~*~*~1", 1),
+    // 146: O > 2
+    ("[#8]", 2),
+    // 147: ACH2CH2A
+    ("[$(*~[CH2]~[CH2]~*),$([R]1@[CH2;R]@[CH2;R]1)]", 0),
+    // 148: AQ(A)A
+    ("*~[!#6;!#1](~*)~*", 0),
+    // 149: CH3 > 1
+    ("[C;H3,H4]", 1),
+    // 150: A!A$A!A
+    ("*!@*@*!@*", 0),
+    // 151: NH
+    ("[#7;!H0]", 0),
+    // 152: OC(C)C
+    ("[#8]~[#6](~[#6])~[#6]", 0),
+    // 153: QCH2A
+    ("[!#6;!#1]~[CH2]~*", 0),
+    // 154: C=O
+    ("[#6]=[#8]", 0),
+    // 155: A!CH2!A
+    ("*!@[CH2]!@*", 0),
+    // 156: NA(A)A
+    ("[#7]~*(~*)~*", 0),
+    // 157: C-O
+    ("[#6]-[#8]", 0),
+    // 158: C-N
+    ("[#6]-[#7]", 0),
+    // 159: O > 1
+    ("[#8]", 1),
+    // 160: CH3
+    ("[C;H3,H4]", 0),
+    // 161: N
+    ("[#7]", 0),
+    // 162: Aromatic
+    ("a", 0),
+    // 163: 6M ring
+    ("*1~*~*~*~*~*~1", 0),
+    // 164: O
+    ("[#8]", 0),
+    // 165: Ring
+    ("[R]", 0),
+    // 166: Fragments
+    ("?", 0),
+];
+
+/// Computes the 166 public MACCS structural keys as a 167 bit fingerprint, where bit *n* is key
+/// *n* and bit 0 is unused.
+///
+/// Most keys are SMARTS patterns following RDKit's reading of the public definitions, which only
+/// approximate some of MDL's originals. Isotopes, elements past lawrencium, elements other than
+/// H, C, N, O, Si, P, S and the halogens, more than one aromatic ring and more than one fragment
+/// are computed directly.
+#[derive(Clone, Debug)]
+pub struct MaccsFingerprinter {
+    /// The pattern for each key and the matches it must exceed, if it has one.
+    keys: Vec<Option<(Query, usize)>>,
+}
+
+impl MaccsFingerprinter {
+    pub fn new() -> Self {
+        let keys = KEYS
+            .iter()
+            .map(|(smarts, count)| match *smarts {
+                "?" => None,
+                smarts => Some((query_from_smarts(smarts).unwrap(), *count)),
+            })
+            .collect();
+        MaccsFingerprinter { keys }
+    }
+
+    pub fn fingerprint(&self, mol: &Molecule) -> Fingerprint {
+        let mut fingerprint = Fingerprint::new(KEYS.len() + 1);
+        let target = Target::new(mol);
+        for (i, key) in self.keys.iter().enumerate() {
+            let key_set = match key {
+                Some((query, count)) => {
+                    let search = SubstructureSearch::new(query).unique(true).limit(count + 1);
+                    let matches = search.target_matches(&target).count();
+                    matches > *count
+                }
+                None => computed_key(mol, i + 1),
+            };
+            if key_set {
+                fingerprint.set(i + 1);
+            }
+        }
+        fingerprint
+    }
+}
+
+impl Default for MaccsFingerprinter {
+    fn default() -> Self {
+        MaccsFingerprinter::new()
+    }
+}
+
+fn computed_key(mol: &Molecule, key: usize) -> bool {
+    use Element::*;
+    let mut elements = mol.atoms().map(|atom| mol.atom(atom).element);
+    match key {
+        1 => mol.atoms().any(|atom| mol.atom(atom).isotope.is_some()),
+        2 => elements.any(|element| element.atomic_number() > 103),
+        44 => elements.any(|element| ![H, C, N, O, Si, P, S, F, Cl, Br, I].contains(&element)),
+        125 => {
+            let rings = mol.rings();
+            let aromatic = rings
+                .sssr()
+                .iter()
+                .filter(|ring| ring.atoms().iter().all(|atom| mol.atom(*atom).aromatic))
+                .count();
+            aromatic > 1
+        }
+        166 => mol.components().len() > 1,
+        _ => unreachable!("MACCS key {} has a pattern", key),
+    }
+}
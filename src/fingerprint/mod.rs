@@ -0,0 +1,489 @@
+mod bitset;
+mod cluster;
+mod database;
+mod hash;
+mod maccs;
+mod morgan;
+mod pairs;
+mod path;
+mod similarity;
+
+pub use bitset::*;
+pub use cluster::*;
+pub use database::*;
+pub use maccs::*;
+pub use morgan::*;
+pub use pairs::*;
+pub use path::*;
+pub use similarity::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{AtomIndex, Molecule};
+    use crate::daylight::*;
+    use crate::fingerprint::*;
+
+    fn smiles(s: &str) -> Molecule {
+        molecule_from_smiles(s).expect("Invalid SMILES")
+    }
+
+    #[test]
+    fn can_set_bits() {
+        let mut fp = Fingerprint::new(100);
+        fp.set(3);
+        fp.set(64);
+        fp.set(99);
+        assert_eq!(fp.len(), 100);
+        assert_eq!(fp.count_ones(), 3);
+        assert!(fp.contains(64));
+        assert!(!fp.contains(65));
+        assert!(!fp.contains(1000));
+        assert_eq!(fp.ones().collect::<Vec<usize>>(), vec![3, 64, 99]);
+
+        let mut counts = CountFingerprint::new();
+        counts.add(5);
+        counts.add(15);
+        counts.add(5);
+        assert_eq!(counts.count(5), 2);
+        assert_eq!(counts.fold(10).count(5), 3);
+        assert_eq!(counts.to_bits(10).ones().collect::<Vec<usize>>(), vec![5]);
+    }
+
+    #[test]
+    fn can_compute_morgan_fingerprints() {
+        let ecfp4 = MorganFingerprinter::new();
+        // Atom order and explicit hydrogens don't matter
+        assert_eq!(
+            ecfp4.fingerprint(&smiles("CCO")),
+            ecfp4.fingerprint(&smiles("OCC"))
+        );
+        assert_eq!(
+            ecfp4.fingerprint(&smiles("CO")),
+            ecfp4.fingerprint(&smiles("[H]OC([H])([H])[H]"))
+        );
+        assert_ne!(
+            ecfp4.fingerprint(&smiles("CCO")),
+            ecfp4.fingerprint(&smiles("CCN"))
+        );
+
+        // Three atoms and three bonds, after which every environment repeats
+        let ethanol = ecfp4.identifiers(&smiles("CCO"));
+        assert_eq!(ethanol.counts().map(|(_, n)| n).sum::<u32>(), 6);
+        let radius_0 = MorganFingerprinter::new().radius(0);
+        assert_eq!(radius_0.identifiers(&smiles("CCO")).len(), 3);
+        // The two methyl groups of propane look the same
+        let propane = ecfp4.counts(&smiles("CCC"));
+        assert_eq!(propane.counts().map(|(_, n)| n).max(), Some(2));
+        assert_eq!(ecfp4.fingerprint(&smiles("CCC")).len(), 2048);
+        assert_eq!(
+            MorganFingerprinter::new()
+                .length(64)
+                .fingerprint(&smiles("c1ccccc1"))
+                .len(),
+            64
+        );
+    }
+
+    #[test]
+    fn can_explain_morgan_bits() {
+        let ecfp4 = MorganFingerprinter::new();
+        let mol = smiles("CC(=O)O");
+        let info = ecfp4.bit_info(&mol);
+        let fp = ecfp4.fingerprint(&mol);
+        assert_eq!(
+            info.keys().copied().collect::<Vec<usize>>(),
+            fp.ones().collect::<Vec<usize>>()
+        );
+        // The carbonyl carbon's environments at every radius set a bit
+        let carbonyl: Vec<usize> = info
+            .values()
+            .flatten()
+            .filter(|env| env.atom == AtomIndex::new(1))
+            .map(|env| env.radius)
+            .collect();
+        assert_eq!(carbonyl.len(), 2);
+        assert!(carbonyl.contains(&0));
+    }
+
+    #[test]
+    fn can_include_chirality_in_morgan_fingerprints() {
+        let (r, s) = (smiles("F[C@H](Cl)Br"), smiles("F[C@@H](Cl)Br"));
+        let achiral = MorganFingerprinter::new();
+        assert_eq!(achiral.fingerprint(&r), achiral.fingerprint(&s));
+        let chiral = MorganFingerprinter::new().chirality(true);
+        assert_ne!(chiral.fingerprint(&r), chiral.fingerprint(&s));
+        // The same stereocentre written differently
+        assert_eq!(
+            chiral.fingerprint(&r),
+            chiral.fingerprint(&smiles("Cl[C@@H](F)Br"))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "fingerprint length must be positive")]
+    fn cannot_fold_morgan_fingerprints_into_nothing() {
+        MorganFingerprinter::new().length(0);
+    }
+
+    #[test]
+    fn can_compute_feature_morgan_fingerprints() {
+        let (fluoro, chloro) = (smiles("Fc1ccccc1"), smiles("Clc1ccccc1"));
+        let ecfp4 = MorganFingerprinter::new();
+        assert_ne!(ecfp4.fingerprint(&fluoro), ecfp4.fingerprint(&chloro));
+        let fcfp4 = MorganFingerprinter::new().features(true);
+        assert_eq!(fcfp4.fingerprint(&fluoro), fcfp4.fingerprint(&chloro));
+        // An amine is a donor but an amide nitrogen isn't basic
+        assert_ne!(
+            fcfp4.fingerprint(&smiles("CCN")),
+            fcfp4.fingerprint(&smiles("CC(=O)N"))
+        );
+    }
+
+    #[test]
+    fn can_compute_path_fingerprints() {
+        let paths = PathFingerprinter::new();
+        assert_eq!(
+            paths.fingerprint(&smiles("CCO")),
+            paths.fingerprint(&smiles("[H]OCC"))
+        );
+        // Two atoms and two bonds, with C-C-O as well at two bonds
+        let short = PathFingerprinter::new().max_length(1);
+        assert_eq!(short.fingerprint(&smiles("CCO")).count_ones(), 4);
+        assert_eq!(paths.fingerprint(&smiles("CCO")).count_ones(), 5);
+        assert_ne!(
+            paths.fingerprint(&smiles("c1ccccc1")),
+            paths.fingerprint(&smiles("C1CCCCC1"))
+        );
+    }
+
+    #[test]
+    fn can_screen_with_path_fingerprints() {
+        let paths = PathFingerprinter::new();
+        let paracetamol = smiles("CC(=O)Nc1ccc(O)cc1");
+        let target = paths.fingerprint(&paracetamol);
+        for s in &[
+            "c1ccccc1O",
+            "C(=O)N",
+            "CC(=O)Nc",
+            "[#6]~[#7]",
+            "N-c(:c):c",
+            "[O;H1]c",
+        ] {
+            let query = query_from_smarts(s).unwrap();
+            assert!(paracetamol.has_substructure(&query));
+            assert!(
+                paths.query_fingerprint(&query).is_subset_of(&target),
+                "{}",
+                s
+            );
+        }
+        for s in &["C#N", "c1ccccc1Cl", "CC(=O)O"] {
+            let query = query_from_smarts(s).unwrap();
+            assert!(
+                !paths.query_fingerprint(&query).is_subset_of(&target),
+                "{}",
+                s
+            );
+        }
+        // Nothing is specified, so nothing can be screened out
+        let query = query_from_smarts("*~*").unwrap();
+        assert_eq!(paths.query_fingerprint(&query).count_ones(), 0);
+    }
+
+    #[test]
+    fn can_compute_maccs_keys() {
+        let maccs = MaccsFingerprinter::new();
+        let keys = |s: &str| maccs.fingerprint(&smiles(s)).ones().collect::<Vec<usize>>();
+        assert_eq!(maccs.fingerprint(&smiles("C")).len(), 167);
+
+        // Aromatic, six membered ring, ring
+        assert_eq!(keys("c1ccccc1"), vec![162, 163, 165]);
+        let naphthalene = keys("c1ccc2ccccc2c1");
+        assert!(naphthalene.contains(&125) && naphthalene.contains(&145));
+
+        // Keys with a count need more than that many matches
+        let ethylene_glycol = keys("OCCO");
+        assert!(ethylene_glycol.contains(&159));
+        assert!(!ethylene_glycol.contains(&146));
+        assert!(keys("OCC(O)CO").contains(&146));
+
+        // Charges, group IA, isotopes and fragments
+        let salt = keys("CC(=O)[O-].[Na+]");
+        for key in &[35, 44, 49, 154, 166] {
+            assert!(salt.contains(key), "{}", key);
+        }
+        assert!(keys("[13CH4]").contains(&1));
+        assert!(!keys("CC(=O)O").contains(&166));
+    }
+
+    #[test]
+    fn can_compute_atom_pair_fingerprints() {
+        let pairs = AtomPairFingerprinter::new();
+        // Three pairs, of which the two C-O pairs differ in distance
+        let ethanol = pairs.identifiers(&smiles("CCO"));
+        assert_eq!(ethanol.len(), 3);
+        assert_eq!(ethanol, pairs.identifiers(&smiles("[H]OCC")));
+        // Both O-C pairs of dimethyl ether are the same
+        let ether = pairs.identifiers(&smiles("COC"));
+        assert_eq!(ether.len(), 2);
+        assert_eq!(ether.counts().map(|(_, n)| n).max(), Some(2));
+        // Ethylene has pi electrons where ethane doesn't
+        assert_ne!(
+            pairs.fingerprint(&smiles("C=C")),
+            pairs.fingerprint(&smiles("CC"))
+        );
+        // Distances are limited
+        let near = AtomPairFingerprinter::new().max_distance(2);
+        assert_eq!(
+            near.identifiers(&smiles("CCCC"))
+                .counts()
+                .map(|(_, n)| n)
+                .sum::<u32>(),
+            5
+        );
+        assert_eq!(
+            pairs
+                .counts(&smiles("CCCC"))
+                .counts()
+                .map(|(_, n)| n)
+                .sum::<u32>(),
+            6
+        );
+    }
+
+    #[test]
+    fn can_compute_torsion_fingerprints() {
+        let torsions = TorsionFingerprinter::new();
+        assert!(torsions.identifiers(&smiles("CCC")).is_empty());
+        assert_eq!(torsions.identifiers(&smiles("CCCC")).len(), 1);
+        assert_eq!(
+            torsions.identifiers(&smiles("CCCO")),
+            torsions.identifiers(&smiles("OCCC"))
+        );
+        // Six torsions around benzene, all alike
+        let benzene = torsions.identifiers(&smiles("c1ccccc1"));
+        assert_eq!(benzene.len(), 1);
+        assert_eq!(benzene.counts().map(|(_, n)| n).sum::<u32>(), 6);
+        assert_ne!(
+            torsions.fingerprint(&smiles("CC(C)CC")),
+            torsions.fingerprint(&smiles("CCCCC"))
+        );
+    }
+
+    fn bits(len: usize, ones: &[usize]) -> Fingerprint {
+        let mut fp = Fingerprint::new(len);
+        for bit in ones {
+            fp.set(*bit);
+        }
+        fp
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn can_compare_fingerprints() {
+        let a = bits(128, &[0, 1, 2, 70]);
+        let b = bits(128, &[1, 2, 70, 100, 127]);
+        assert_eq!(a.intersection_count(&b), 3);
+        assert_close(a.tanimoto(&b), 3.0 / 6.0);
+        assert_close(a.dice(&b), 6.0 / 9.0);
+        assert_close(a.cosine(&b), 3.0 / 20f64.sqrt());
+        assert_close(a.tversky(&b, 1.0, 1.0), a.tanimoto(&b));
+        assert_close(a.tversky(&b, 0.5, 0.5), a.dice(&b));
+        // Only the bits of the first count when its weight is one and the other's is zero
+        assert_close(a.tversky(&b, 1.0, 0.0), 3.0 / 4.0);
+        assert_close(a.tanimoto(&a), 1.0);
+
+        let empty = Fingerprint::new(128);
+        assert_close(empty.tanimoto(&empty), 0.0);
+        assert_close(empty.cosine(&a), 0.0);
+    }
+
+    /// Similarities of every target by brute force, most similar first
+    fn ranked(query: &Fingerprint, targets: &[Fingerprint]) -> Vec<Hit> {
+        let mut hits: Vec<Hit> = targets
+            .iter()
+            .enumerate()
+            .map(|(index, fp)| Hit {
+                index,
+                similarity: query.tanimoto(fp),
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.similarity
+                .partial_cmp(&a.similarity)
+                .unwrap()
+                .then(a.index.cmp(&b.index))
+        });
+        hits
+    }
+
+    /// Pseudorandom fingerprints of varied density.
+    fn random_fingerprints(count: usize) -> Vec<Fingerprint> {
+        let mut state: u64 = 7;
+        let mut random = move || {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1);
+            (state >> 33) as usize
+        };
+        (0..count)
+            .map(|_| {
+                let n = random() % 60;
+                let ones: Vec<usize> = (0..n).map(|_| random() % 256).collect();
+                bits(256, &ones)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn can_search_by_similarity() {
+        let targets = random_fingerprints(300);
+        let query = targets[17].clone();
+        let expected = ranked(&query, &targets);
+
+        assert_eq!(top_k(&query, &targets, 10), expected[..10].to_vec());
+        assert_eq!(top_k(&query, &targets, 1)[0].index, 17);
+        assert_eq!(top_k(&query, &targets, 1000).len(), 300);
+        assert!(top_k(&query, &targets, 0).is_empty());
+
+        let hits = threshold_search(&query, &targets, 0.3);
+        let above: Vec<Hit> = expected
+            .into_iter()
+            .filter(|hit| hit.similarity >= 0.3)
+            .collect();
+        assert!(!above.is_empty());
+        assert_eq!(hits, above);
+        assert!(threshold_search(&query, &[], 0.3).is_empty());
+
+        // An index can be searched by many queries
+        let index = FingerprintIndex::new(&targets);
+        assert_eq!(index.len(), 300);
+        for query in &targets[..20] {
+            assert_eq!(index.top_k(query, 5), top_k(query, &targets, 5));
+            assert_eq!(
+                index.threshold_search(query, 0.4),
+                threshold_search(query, &targets, 0.4)
+            );
+        }
+    }
+
+    #[test]
+    fn can_build_and_search_databases() {
+        let path = std::env::temp_dir().join(format!("chem-rs-{}.fpdb", std::process::id()));
+        let fingerprinter = MorganFingerprinter::new();
+        let mut builder = DatabaseBuilder::new(|mol: &Molecule| fingerprinter.fingerprint(mol));
+        let smi =
+            "OCC ethanol\nc1ccccc1O phenol\n\nC1CC\nCC(=O)O\nc1ccccc1CC(=O)O phenylacetic acid\n";
+        let errors = builder.add_smi(smi.as_bytes()).unwrap();
+        assert_eq!(errors, vec![(4, SmilesError::UnclosedRing(1))]);
+        assert_eq!(builder.len(), 4);
+        builder.write(&path).unwrap();
+
+        let db = FingerprintDatabase::open(&path).unwrap();
+        assert_eq!(db.len(), 4);
+        assert_eq!(db.fingerprint_length(), 2048);
+        let records: Vec<(&str, &str)> = (0..db.len()).map(|i| (db.id(i), db.smiles(i))).collect();
+        assert!(records.contains(&(
+            "ethanol",
+            molecule_to_canonical_smiles(&smiles("CCO")).as_str()
+        )));
+        assert!(records.iter().any(|(id, _)| *id == "5"));
+        assert!(records.iter().any(|(id, _)| *id == "phenylacetic acid"));
+        let counts: Vec<u32> = (0..db.len())
+            .map(|i| db.fingerprint(i).count_ones())
+            .collect();
+        assert!(counts.windows(2).all(|w| w[0] <= w[1]));
+
+        let fingerprints: Vec<Fingerprint> = (0..db.len()).map(|i| db.fingerprint(i)).collect();
+        let query = fingerprinter.fingerprint(&smiles("OC(=O)Cc1ccccc1"));
+        let hits = db.top_k(&query, 2);
+        assert_eq!(hits, top_k(&query, &fingerprints, 2));
+        assert_eq!(db.id(hits[0].index), "phenylacetic acid");
+        assert_eq!(hits[0].similarity, 1.0);
+        assert_eq!(
+            db.threshold_search(&query, 0.1),
+            threshold_search(&query, &fingerprints, 0.1)
+        );
+
+        let mut empty = DatabaseBuilder::new(|mol: &Molecule| fingerprinter.fingerprint(mol));
+        empty.write(&path).unwrap();
+        assert!(FingerprintDatabase::open(&path).unwrap().is_empty());
+
+        std::fs::write(&path, b"CHEMFPDB").unwrap();
+        assert!(matches!(
+            FingerprintDatabase::open(&path),
+            Err(DatabaseError::Format)
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn can_cluster_fingerprints() {
+        let fingerprints = vec![
+            bits(64, &[0, 1, 2, 3]),
+            bits(64, &[0, 1, 2, 3, 4]),
+            bits(64, &[10, 11, 12]),
+            bits(64, &[0, 1, 2, 4]),
+            bits(64, &[10, 11, 12, 13]),
+            bits(64, &[30, 31]),
+        ];
+        let clusters = butina(&fingerprints, 0.3);
+        let cluster = |centroid: usize, members: &[usize]| Cluster {
+            centroid,
+            members: members.to_vec(),
+        };
+        assert_eq!(
+            clusters,
+            vec![
+                cluster(1, &[0, 1, 3]),
+                cluster(2, &[2, 4]),
+                cluster(5, &[5]),
+            ]
+        );
+        assert_eq!(butina(&fingerprints, 0.0).len(), 6);
+        assert!(butina(&[], 0.5).is_empty());
+    }
+
+    #[test]
+    fn can_pick_diverse_fingerprints() {
+        let fingerprints = random_fingerprints(200);
+        // Picks everything at the greatest distance from its nearest selected fingerprint
+        let brute_force = |seeds: &[Fingerprint], n: usize| {
+            let mut selected: Vec<Fingerprint> = seeds.to_vec();
+            let mut picks: Vec<usize> = vec![];
+            while picks.len() < n {
+                let distance = |fp: &Fingerprint| {
+                    selected
+                        .iter()
+                        .map(|s| 1.0 - fp.tanimoto(s))
+                        .fold(f64::INFINITY, f64::min)
+                };
+                let mut best: Option<(f64, usize)> = None;
+                for (i, fp) in fingerprints.iter().enumerate() {
+                    let d = distance(fp);
+                    if !picks.contains(&i) && !matches!(best, Some((b, _)) if d <= b) {
+                        best = Some((d, i));
+                    }
+                }
+                let (_, i) = best.unwrap();
+                picks.push(i);
+                selected.push(fingerprints[i].clone());
+            }
+            picks
+        };
+
+        let picks = MaxMinPicker::new(&fingerprints).pick(20);
+        assert_eq!(picks[0], 0);
+        assert_eq!(picks, brute_force(&[], 20));
+
+        let seeds = vec![fingerprints[0].clone(), bits(256, &[1, 2, 3])];
+        let picks = MaxMinPicker::new(&fingerprints).seeds(&seeds).pick(20);
+        assert_eq!(picks, brute_force(&seeds, 20));
+        assert!(!picks.contains(&0));
+
+        assert_eq!(MaxMinPicker::new(&fingerprints[..5]).pick(10).len(), 5);
+    }
+}
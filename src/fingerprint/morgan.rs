@@ -0,0 +1,270 @@
+use crate::core::{AtomIndex, Molecule};
+use crate::daylight::{query_from_smarts, Query};
+use crate::fingerprint::hash::{bond_invariant, hash, is_heavy};
+use crate::fingerprint::{CountFingerprint, Fingerprint};
+use crate::substructure::{SubstructureSearch, Target};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::OnceLock;
+
+/// Pharmacophoric features used as FCFP atom invariants, in the order of their bits.
+const FEATURES: &[&str] = &[
+    // Donor
+    "[$([N;!H0;v3,v4&+1]),$([O,S;H1;+0]),n&H1&+0]",
+    // Acceptor
+    "[$([O,S;H1;v2;!$(*-*=[O,N,P,S])]),$([O,S;H0;v2]),$([O,S;-]),$([O,S;H0;v1;!$(*-*=[O,N,P,S])]),\
+     $([O,S;H0;v1]),$([N;v3;!$(N-*=[O,N,P,S])]),n&H0&+0,$([o,s;+0;!$([o,s]:n);!$([o,s]:c:n)])]",
+    // Aromatic
+    "[a]",
+    // Halogen
+    "[F,Cl,Br,I]",
+    // Basic
+    "[#7;+,$([N;H2&+0][$([C,a]);!$([C,a](=O))]),$([N;H1&+0]([$([C,a]);!$([C,a](=O))])\
+     [$([C,a]);!$([C,a](=O))]),$([N;H0&+0]([C;!$(C=*)])([C;!$(C=*)])[C;!$(C=*)])]",
+    // Acidic
+    "[$([C,S](=[O,S,P])-[O;H1,-1])]",
+];
+
+/// The pharmacophoric features, parsed on first use.
+fn features() -> &'static [Query] {
+    static QUERIES: OnceLock<Vec<Query>> = OnceLock::new();
+    QUERIES.get_or_init(|| {
+        FEATURES
+            .iter()
+            .map(|smarts| query_from_smarts(smarts).unwrap())
+            .collect()
+    })
+}
+
+/// An atom and the radius of the environment around it that set a bit.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct AtomEnvironment {
+    pub atom: AtomIndex,
+    pub radius: usize,
+}
+
+/// Computes circular fingerprints, as in ECFP and FCFP.
+///
+/// Each heavy atom starts with an identifier hashed from its invariants, and at each iteration
+/// takes a new identifier from its own and those of its neighbours, so that it describes the
+/// environment out to one more bond. Every identifier up to the radius is a feature, except that
+/// an environment covering the same bonds as an earlier one is counted once.
+///
+/// ECFP invariants are the element, heavy atom degree, hydrogen count, charge and ring membership.
+/// FCFP invariants are instead whether the atom is a hydrogen bond donor or acceptor, aromatic, a
+/// halogen, basic or acidic.
+#[derive(Clone, Debug)]
+pub struct MorganFingerprinter {
+    radius: usize,
+    length: usize,
+    features: bool,
+    chirality: bool,
+}
+
+impl MorganFingerprinter {
+    /// ECFP4: radius 2 folded into 2048 bits, without chirality.
+    pub fn new() -> Self {
+        MorganFingerprinter {
+            radius: 2,
+            length: 2048,
+            features: false,
+            chirality: false,
+        }
+    }
+
+    /// The number of iterations. ECFP*n* has a radius of *n*/2.
+    pub fn radius(mut self, radius: usize) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// The number of bits features are folded into.
+    ///
+    /// # Panics
+    ///
+    /// If the length is zero.
+    pub fn length(mut self, length: usize) -> Self {
+        assert!(length > 0, "fingerprint length must be positive");
+        self.length = length;
+        self
+    }
+
+    /// Uses the FCFP pharmacophoric invariants instead of the ECFP ones.
+    pub fn features(mut self, features: bool) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Tells apart the environments of stereocentres of opposite chirality.
+    pub fn chirality(mut self, chirality: bool) -> Self {
+        self.chirality = chirality;
+        self
+    }
+
+    pub fn fingerprint(&self, mol: &Molecule) -> Fingerprint {
+        self.identifiers(mol).to_bits(self.length)
+    }
+
+    /// The number of environments that set each bit.
+    pub fn counts(&self, mol: &Molecule) -> CountFingerprint {
+        self.identifiers(mol).fold(self.length)
+    }
+
+    /// The count of each feature identifier before folding.
+    pub fn identifiers(&self, mol: &Molecule) -> CountFingerprint {
+        let mut counts = CountFingerprint::new();
+        for (identifier, _) in self.environments(mol) {
+            counts.add(identifier);
+        }
+        counts
+    }
+
+    /// The environments that set each bit.
+    pub fn bit_info(&self, mol: &Molecule) -> BTreeMap<usize, Vec<AtomEnvironment>> {
+        let mut info: BTreeMap<usize, Vec<AtomEnvironment>> = BTreeMap::new();
+        for (identifier, environment) in self.environments(mol) {
+            info.entry(identifier as usize % self.length)
+                .or_default()
+                .push(environment);
+        }
+        info
+    }
+
+    /// Every distinct environment with its identifier, by radius then atom.
+    fn environments(&self, mol: &Molecule) -> Vec<(u32, AtomEnvironment)> {
+        let atoms: Vec<AtomIndex> = mol.atoms().filter(|a| is_heavy(mol, *a)).collect();
+        let mut identifiers: Vec<u32> = vec![0; mol.atom_count()];
+        for (atom, invariant) in self.invariants(mol, &atoms) {
+            identifiers[atom.index()] = invariant;
+        }
+        let mut environments: Vec<(u32, AtomEnvironment)> = atoms
+            .iter()
+            .map(|atom| {
+                let environment = AtomEnvironment {
+                    atom: *atom,
+                    radius: 0,
+                };
+                (identifiers[atom.index()], environment)
+            })
+            .collect();
+
+        let mut bonds: Vec<BTreeSet<(AtomIndex, AtomIndex)>> =
+            vec![BTreeSet::new(); mol.atom_count()];
+        let mut seen: HashSet<BTreeSet<(AtomIndex, AtomIndex)>> = HashSet::new();
+        for radius in 1..=self.radius {
+            let mut layer = vec![];
+            for atom in &atoms {
+                let mut neighbors: Vec<(u32, u32)> = vec![];
+                let mut covered = bonds[atom.index()].clone();
+                for n in mol.neighbors(*atom).filter(|n| is_heavy(mol, *n)) {
+                    let bond = mol.bond(*atom, n).unwrap();
+                    neighbors.push((bond_invariant(bond), identifiers[n.index()]));
+                    covered.insert((*atom.min(&n), *atom.max(&n)));
+                    covered.extend(bonds[n.index()].iter().copied());
+                }
+                neighbors.sort_unstable();
+                let mut values = vec![radius as u32, identifiers[atom.index()]];
+                values.extend(neighbors.iter().flat_map(|(b, n)| vec![*b, *n]));
+                if self.chirality {
+                    values.extend(chirality_invariant(mol, *atom, &identifiers));
+                }
+                layer.push((covered, hash(values), *atom));
+            }
+
+            // Keep only the first of environments that cover the same bonds
+            let mut order: Vec<usize> = (0..layer.len()).collect();
+            order.sort_by(|a, b| (&layer[*a].0, layer[*a].1).cmp(&(&layer[*b].0, layer[*b].1)));
+            for i in order {
+                let (covered, identifier, atom) = &layer[i];
+                if !covered.is_empty() && seen.insert(covered.clone()) {
+                    environments.push((
+                        *identifier,
+                        AtomEnvironment {
+                            atom: *atom,
+                            radius,
+                        },
+                    ));
+                }
+            }
+            for (covered, identifier, atom) in layer {
+                identifiers[atom.index()] = identifier;
+                bonds[atom.index()] = covered;
+            }
+        }
+        environments.sort_by_key(|(_, environment)| (environment.radius, environment.atom));
+        environments
+    }
+
+    fn invariants(&self, mol: &Molecule, atoms: &[AtomIndex]) -> Vec<(AtomIndex, u32)> {
+        if self.features {
+            let target = Target::new(mol);
+            let features: Vec<HashSet<AtomIndex>> = features()
+                .iter()
+                .map(|query| {
+                    let search = SubstructureSearch::new(query);
+                    let atoms = search.target_matches(&target).map(|atoms| atoms[0]);
+                    atoms.collect()
+                })
+                .collect();
+            atoms
+                .iter()
+                .map(|atom| {
+                    let bits = features
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, atoms)| atoms.contains(atom))
+                        .fold(0, |bits, (i, _)| bits | 1 << i);
+                    (*atom, hash(vec![bits]))
+                })
+                .collect()
+        } else {
+            let rings = mol.rings();
+            atoms
+                .iter()
+                .map(|atom| {
+                    let a = mol.atom(*atom);
+                    let heavy = mol.neighbors(*atom).filter(|n| is_heavy(mol, *n)).count();
+                    let invariant = hash(vec![
+                        a.element.atomic_number() as u32,
+                        heavy as u32,
+                        (mol.degree(*atom) - heavy) as u32 + a.hydrogens as u32,
+                        a.formal_charge as i32 as u32,
+                        rings.is_in_ring(*atom) as u32,
+                    ]);
+                    (*atom, invariant)
+                })
+                .collect()
+        }
+    }
+}
+
+impl Default for MorganFingerprinter {
+    fn default() -> Self {
+        MorganFingerprinter::new()
+    }
+}
+
+/// The handedness of a stereocentre with neighbours ordered by identifier, or nothing if two of
+/// them have the same identifier. Hydrogens come first.
+fn chirality_invariant(mol: &Molecule, atom: AtomIndex, identifiers: &[u32]) -> Option<u32> {
+    let a = mol.atom(atom);
+    let chirality = a.chirality?;
+    let mut neighbors: Vec<AtomIndex> = mol.neighbors(atom).collect();
+    neighbors.sort();
+    let mut keys: Vec<Option<u32>> = vec![None; a.hydrogens as usize];
+    keys.extend(neighbors.iter().map(|n| {
+        if is_heavy(mol, *n) {
+            Some(identifiers[n.index()])
+        } else {
+            None
+        }
+    }));
+    if keys.len() != 4 {
+        return None;
+    }
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by_key(|i| keys[*i]);
+    if order.windows(2).any(|w| keys[w[0]] == keys[w[1]]) {
+        return None;
+    }
+    Some(chirality.permuted(&order) as u32 + 1)
+}
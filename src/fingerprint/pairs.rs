@@ -0,0 +1,173 @@
+use crate::core::{AtomIndex, Molecule};
+use crate::fingerprint::hash::{hash, is_heavy};
+use crate::fingerprint::{CountFingerprint, Fingerprint};
+
+/// Computes atom pair fingerprints, with a feature for every pair of heavy atoms made of the two
+/// atoms' types and the number of bonds between them. An atom's type is its element, heavy atom
+/// degree and number of pi electrons.
+#[derive(Clone, Debug)]
+pub struct AtomPairFingerprinter {
+    min_distance: usize,
+    max_distance: usize,
+    length: usize,
+}
+
+impl AtomPairFingerprinter {
+    /// Pairs from 1 to 30 bonds apart folded into 2048 bits.
+    pub fn new() -> Self {
+        AtomPairFingerprinter {
+            min_distance: 1,
+            max_distance: 30,
+            length: 2048,
+        }
+    }
+
+    pub fn min_distance(mut self, min_distance: usize) -> Self {
+        self.min_distance = min_distance;
+        self
+    }
+
+    pub fn max_distance(mut self, max_distance: usize) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// The number of bits features are folded into.
+    ///
+    /// # Panics
+    ///
+    /// If the length is zero.
+    pub fn length(mut self, length: usize) -> Self {
+        assert!(length > 0, "fingerprint length must be positive");
+        self.length = length;
+        self
+    }
+
+    pub fn fingerprint(&self, mol: &Molecule) -> Fingerprint {
+        self.identifiers(mol).to_bits(self.length)
+    }
+
+    /// The number of pairs that set each bit.
+    pub fn counts(&self, mol: &Molecule) -> CountFingerprint {
+        self.identifiers(mol).fold(self.length)
+    }
+
+    /// The count of each pair before folding.
+    pub fn identifiers(&self, mol: &Molecule) -> CountFingerprint {
+        let atoms = heavy_atoms(mol);
+        let types: Vec<u32> = atoms.iter().map(|a| atom_type(mol, *a, 0)).collect();
+        let distances = mol.distance_matrix();
+        let mut counts = CountFingerprint::new();
+        for (i, a) in atoms.iter().enumerate() {
+            for (j, b) in atoms.iter().enumerate().skip(i + 1) {
+                let distance = match distances[a.index()][b.index()] {
+                    Some(d) if d >= self.min_distance && d <= self.max_distance => d,
+                    _ => continue,
+                };
+                let (first, second) = (types[i].min(types[j]), types[i].max(types[j]));
+                counts.add(hash(vec![first, distance as u32, second]));
+            }
+        }
+        counts
+    }
+}
+
+impl Default for AtomPairFingerprinter {
+    fn default() -> Self {
+        AtomPairFingerprinter::new()
+    }
+}
+
+/// Computes topological torsion fingerprints, with a feature for every linear path of four heavy
+/// atoms made of the atoms' types in order. Each type is as for atom pairs, but with the degree
+/// only counting branches off the path.
+#[derive(Clone, Debug)]
+pub struct TorsionFingerprinter {
+    length: usize,
+}
+
+impl TorsionFingerprinter {
+    /// Torsions folded into 2048 bits.
+    pub fn new() -> Self {
+        TorsionFingerprinter { length: 2048 }
+    }
+
+    /// The number of bits features are folded into.
+    ///
+    /// # Panics
+    ///
+    /// If the length is zero.
+    pub fn length(mut self, length: usize) -> Self {
+        assert!(length > 0, "fingerprint length must be positive");
+        self.length = length;
+        self
+    }
+
+    pub fn fingerprint(&self, mol: &Molecule) -> Fingerprint {
+        self.identifiers(mol).to_bits(self.length)
+    }
+
+    /// The number of torsions that set each bit.
+    pub fn counts(&self, mol: &Molecule) -> CountFingerprint {
+        self.identifiers(mol).fold(self.length)
+    }
+
+    /// The count of each torsion before folding.
+    pub fn identifiers(&self, mol: &Molecule) -> CountFingerprint {
+        let mut counts = CountFingerprint::new();
+        for (b, c, _) in mol.bonds() {
+            if !is_heavy(mol, b) || !is_heavy(mol, c) {
+                continue;
+            }
+            for a in mol.neighbors(b).filter(|a| *a != c && is_heavy(mol, *a)) {
+                for d in mol
+                    .neighbors(c)
+                    .filter(|d| *d != b && *d != a && is_heavy(mol, *d))
+                {
+                    let mut forward = vec![
+                        atom_type(mol, a, 1),
+                        atom_type(mol, b, 2),
+                        atom_type(mol, c, 2),
+                        atom_type(mol, d, 1),
+                    ];
+                    // Either direction along the path gives the same feature
+                    let backward: Vec<u32> = forward.iter().rev().copied().collect();
+                    if backward < forward {
+                        forward = backward;
+                    }
+                    counts.add(hash(forward));
+                }
+            }
+        }
+        counts
+    }
+}
+
+impl Default for TorsionFingerprinter {
+    fn default() -> Self {
+        TorsionFingerprinter::new()
+    }
+}
+
+fn heavy_atoms(mol: &Molecule) -> Vec<AtomIndex> {
+    mol.atoms().filter(|a| is_heavy(mol, *a)).collect()
+}
+
+/// An atom's element, its heavy atom degree less the neighbours already on a path, and its pi
+/// electrons, counting one for an aromatic atom.
+fn atom_type(mol: &Molecule, atom: AtomIndex, on_path: usize) -> u32 {
+    let a = mol.atom(atom);
+    let degree = mol.neighbors(atom).filter(|n| is_heavy(mol, *n)).count() - on_path;
+    let pi_electrons = if a.aromatic {
+        1
+    } else {
+        mol.neighbors(atom)
+            .map(|n| mol.bond(atom, n).unwrap().valence() as u32 - 1)
+            .sum()
+    };
+    hash(vec![
+        a.element.atomic_number() as u32,
+        degree as u32,
+        pi_electrons,
+    ])
+}
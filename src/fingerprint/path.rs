@@ -0,0 +1,177 @@
+use crate::core::{Bond, Element, Molecule};
+use crate::daylight::{BondExpr, BondPrimitive, Expr, Query};
+use crate::fingerprint::hash::{bond_invariant, hash};
+use crate::fingerprint::Fingerprint;
+use crate::reaction::specified_element;
+
+/// Computes path fingerprints like Daylight's, hashing every linear path of heavy atoms up to a
+/// number of bonds into a folded bitset. Each atom is described by its element and aromaticity,
+/// and each bond by its order.
+///
+/// Query fingerprints only hash the paths whose atoms and bonds a query fully specifies, so the
+/// bits of a query's fingerprint are set in the fingerprint of every molecule it matches. Testing
+/// [`Fingerprint::is_subset_of`] screens out molecules that can't match without searching them.
+#[derive(Clone, Debug)]
+pub struct PathFingerprinter {
+    max_length: usize,
+    length: usize,
+}
+
+impl PathFingerprinter {
+    /// Paths of up to 7 bonds folded into 2048 bits.
+    pub fn new() -> Self {
+        PathFingerprinter {
+            max_length: 7,
+            length: 2048,
+        }
+    }
+
+    /// The most bonds in a path.
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// The number of bits paths are folded into.
+    ///
+    /// # Panics
+    ///
+    /// If the length is zero.
+    pub fn length(mut self, length: usize) -> Self {
+        assert!(length > 0, "fingerprint length must be positive");
+        self.length = length;
+        self
+    }
+
+    pub fn fingerprint(&self, mol: &Molecule) -> Fingerprint {
+        let atoms = mol
+            .atoms()
+            .map(|atom| {
+                let a = mol.atom(atom);
+                atom_invariant(a.element, a.aromatic)
+            })
+            .collect();
+        let bonds = mol
+            .atoms()
+            .map(|atom| {
+                mol.neighbors(atom)
+                    .map(|n| (n.index(), Some(bond_invariant(mol.bond(atom, n).unwrap()))))
+                    .collect()
+            })
+            .collect();
+        self.hash_paths(atoms, bonds)
+    }
+
+    /// A fingerprint of the query's fully specified paths, for screening.
+    pub fn query_fingerprint(&self, query: &Query) -> Fingerprint {
+        let elements: Vec<Option<(Element, Option<bool>)>> = query
+            .atoms()
+            .map(|atom| specified_element(&query.atom(atom).expr))
+            .collect();
+        let atoms = elements
+            .iter()
+            .map(|element| match element {
+                Some((element, Some(aromatic))) => atom_invariant(*element, *aromatic),
+                _ => None,
+            })
+            .collect();
+        let bonds = query
+            .atoms()
+            .map(|atom| {
+                query
+                    .neighbors(atom)
+                    .map(|n| {
+                        let aliphatic = |i: usize| matches!(elements[i], Some((_, Some(false))));
+                        let either_aliphatic = aliphatic(atom.index()) || aliphatic(n.index());
+                        let bond = query_bond(query.bond(atom, n).unwrap(), either_aliphatic);
+                        (n.index(), bond.map(bond_invariant))
+                    })
+                    .collect()
+            })
+            .collect();
+        self.hash_paths(atoms, bonds)
+    }
+
+    /// Sets a bit for every simple path of atoms and bonds with invariants, where `bonds` lists
+    /// the neighbours of each atom.
+    fn hash_paths(
+        &self,
+        atoms: Vec<Option<u32>>,
+        bonds: Vec<Vec<(usize, Option<u32>)>>,
+    ) -> Fingerprint {
+        let mut fingerprint = Fingerprint::new(self.length);
+        let mut stack: Vec<Vec<usize>> = (0..atoms.len())
+            .filter(|a| atoms[*a].is_some())
+            .map(|a| vec![a])
+            .collect();
+        while let Some(path) = stack.pop() {
+            let mut forward = vec![];
+            for (i, atom) in path.iter().enumerate() {
+                if i > 0 {
+                    let bond = bonds[path[i - 1]].iter().find(|(n, _)| n == atom).unwrap();
+                    forward.push(bond.1.unwrap());
+                }
+                forward.push(atoms[*atom].unwrap());
+            }
+            // Each path is found from both ends, and hashes the same either way
+            let mut backward = forward.clone();
+            backward.reverse();
+            if forward <= backward {
+                let bit = hash(forward) as usize % self.length;
+                fingerprint.set(bit);
+            }
+
+            if path.len() > self.max_length {
+                continue;
+            }
+            let last = *path.last().unwrap();
+            for (n, bond) in &bonds[last] {
+                if bond.is_some() && atoms[*n].is_some() && !path.contains(n) {
+                    let mut extended = path.clone();
+                    extended.push(*n);
+                    stack.push(extended);
+                }
+            }
+        }
+        fingerprint
+    }
+}
+
+impl Default for PathFingerprinter {
+    fn default() -> Self {
+        PathFingerprinter::new()
+    }
+}
+
+/// Hydrogens are left out of paths, as they're often implicit.
+fn atom_invariant(element: Element, aromatic: bool) -> Option<u32> {
+    if element == Element::H {
+        None
+    } else {
+        Some(element.atomic_number() as u32 * 2 + aromatic as u32)
+    }
+}
+
+/// The bond a query bond always matches, if there's only one. An implicit bond only matches
+/// single bonds when an atom at either end is aliphatic.
+fn query_bond(expr: &BondExpr, either_aliphatic: bool) -> Option<Bond> {
+    let primitives = match expr {
+        Expr::Primitive(p) => vec![p],
+        Expr::And(es) => es
+            .iter()
+            .filter_map(|e| match e {
+                Expr::Primitive(p) => Some(p),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    primitives.iter().find_map(|p| match p {
+        BondPrimitive::Single | BondPrimitive::Up | BondPrimitive::Down => Some(Bond::Single),
+        BondPrimitive::Double => Some(Bond::Double),
+        BondPrimitive::Triple => Some(Bond::Triple),
+        BondPrimitive::Aromatic => Some(Bond::Aromatic),
+        BondPrimitive::Implicit if either_aliphatic => Some(Bond::Single),
+        _ => None,
+    })
+}
@@ -0,0 +1,281 @@
+use crate::fingerprint::bitset::popcount;
+use crate::fingerprint::Fingerprint;
+use std::cmp::Ordering;
+
+impl Fingerprint {
+    /// The number of bits set in both fingerprints, which have the same length.
+    pub fn intersection_count(&self, other: &Fingerprint) -> u32 {
+        popcount(self.words(), Some(other.words()))
+    }
+
+    /// Bits in common over bits in either. Fingerprints without any bits set have a similarity
+    /// of zero to anything, as do those of every other metric.
+    pub fn tanimoto(&self, other: &Fingerprint) -> f64 {
+        let common = self.intersection_count(other);
+        ratio(common, self.count_ones() + other.count_ones() - common)
+    }
+
+    /// Twice the bits in common over the total bits set in each.
+    pub fn dice(&self, other: &Fingerprint) -> f64 {
+        ratio(
+            2 * self.intersection_count(other),
+            self.count_ones() + other.count_ones(),
+        )
+    }
+
+    pub fn cosine(&self, other: &Fingerprint) -> f64 {
+        let product = self.count_ones() as f64 * other.count_ones() as f64;
+        if product == 0.0 {
+            0.0
+        } else {
+            self.intersection_count(other) as f64 / product.sqrt()
+        }
+    }
+
+    /// An asymmetric similarity, weighting the bits only set in `self` by `alpha` and those only
+    /// set in `other` by `beta`. Tanimoto is the case where both are one, and Dice where both are
+    /// one half.
+    pub fn tversky(&self, other: &Fingerprint, alpha: f64, beta: f64) -> f64 {
+        let common = self.intersection_count(other) as f64;
+        let only_self = self.count_ones() as f64 - common;
+        let only_other = other.count_ones() as f64 - common;
+        let denominator = common + alpha * only_self + beta * only_other;
+        if denominator == 0.0 {
+            0.0
+        } else {
+            common / denominator
+        }
+    }
+}
+
+fn ratio(numerator: u32, denominator: u32) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// A fingerprint found by a similarity search, by its index among those searched.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Hit {
+    pub index: usize,
+    pub similarity: f64,
+}
+
+/// Orders hits from most to least similar, then by index.
+fn ranking(a: &Hit, b: &Hit) -> Ordering {
+    b.similarity
+        .partial_cmp(&a.similarity)
+        .unwrap_or(Ordering::Equal)
+        .then(a.index.cmp(&b.index))
+}
+
+/// Fingerprints in ascending order of bit count, which can be searched without comparing those
+/// whose counts rule them out.
+pub(crate) trait SortedFingerprints {
+    /// The position of the first fingerprint with each bit count, followed by the number of
+    /// fingerprints.
+    fn starts(&self) -> &[usize];
+
+    /// The index and words of the fingerprint at a position.
+    fn get(&self, position: usize) -> (usize, &[u64]);
+}
+
+/// Fingerprints sorted by bit count for searching with many queries, which only pays for the
+/// sort once. The index keeps its own copy of the fingerprints, laid out in order so that they're
+/// compared in the order they're stored. Hits are by index in the slice it was built from.
+#[derive(Clone, Debug)]
+pub struct FingerprintIndex {
+    words: Vec<u64>,
+    width: usize,
+    order: Vec<usize>,
+    starts: Vec<usize>,
+}
+
+impl FingerprintIndex {
+    /// An index of fingerprints that all have the same length.
+    pub fn new(targets: &[Fingerprint]) -> Self {
+        let counts: Vec<u32> = targets.iter().map(|fp| fp.count_ones()).collect();
+        let starts = count_starts(&counts);
+        // Place each fingerprint after those with fewer bits, keeping indices in order
+        let mut next = starts.clone();
+        let mut order = vec![0; targets.len()];
+        for (index, count) in counts.iter().enumerate() {
+            order[next[*count as usize]] = index;
+            next[*count as usize] += 1;
+        }
+        let width = targets.first().map_or(0, |fp| fp.words().len());
+        let mut words = Vec::with_capacity(width * targets.len());
+        for index in &order {
+            assert_eq!(
+                targets[*index].words().len(),
+                width,
+                "mixed fingerprint lengths"
+            );
+            words.extend_from_slice(targets[*index].words());
+        }
+        FingerprintIndex {
+            words,
+            width,
+            order,
+            starts,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// The same hits as [`top_k`]. Fingerprints are visited in order of the Swamidass–Baldi bound
+    /// on their similarity, the smaller of the two bit counts over the larger, and the search
+    /// stops once the bound falls below the `k`th best similarity found.
+    pub fn top_k(&self, query: &Fingerprint, k: usize) -> Vec<Hit> {
+        sorted_top_k(query, self, k)
+    }
+
+    /// The same hits as [`threshold_search`], without comparing fingerprints whose bit counts
+    /// rule them out.
+    pub fn threshold_search(&self, query: &Fingerprint, threshold: f64) -> Vec<Hit> {
+        sorted_threshold_search(query, self, threshold)
+    }
+}
+
+impl SortedFingerprints for FingerprintIndex {
+    fn starts(&self) -> &[usize] {
+        &self.starts
+    }
+
+    fn get(&self, position: usize) -> (usize, &[u64]) {
+        let start = position * self.width;
+        (self.order[position], &self.words[start..start + self.width])
+    }
+}
+
+/// The position of the first of each bit count once sorted, followed by the number of counts.
+pub(crate) fn count_starts(counts: &[u32]) -> Vec<usize> {
+    let max = counts.iter().copied().max().unwrap_or(0) as usize;
+    let mut starts = vec![0; max + 2];
+    for count in counts {
+        starts[*count as usize + 1] += 1;
+    }
+    for i in 1..starts.len() {
+        starts[i] += starts[i - 1];
+    }
+    starts
+}
+
+/// The `k` fingerprints with the highest Tanimoto similarity to the query, from most to least
+/// similar. Ties go to the lower index.
+///
+/// The query is compared with each fingerprint in turn, in place. To search the same fingerprints
+/// more than once, build a [`FingerprintIndex`], which skips those whose bit counts rule them out.
+pub fn top_k(query: &Fingerprint, targets: &[Fingerprint], k: usize) -> Vec<Hit> {
+    let mut best = vec![];
+    for (index, target) in targets.iter().enumerate() {
+        let hit = Hit {
+            index,
+            similarity: query.tanimoto(target),
+        };
+        insert_hit(&mut best, hit, k);
+    }
+    best
+}
+
+/// Every fingerprint with a Tanimoto similarity to the query of at least `threshold`, from most
+/// to least similar. Like [`top_k`], it compares the query with each fingerprint in place.
+pub fn threshold_search(query: &Fingerprint, targets: &[Fingerprint], threshold: f64) -> Vec<Hit> {
+    let mut hits: Vec<Hit> = targets
+        .iter()
+        .enumerate()
+        .map(|(index, target)| Hit {
+            index,
+            similarity: query.tanimoto(target),
+        })
+        .filter(|hit| hit.similarity >= threshold)
+        .collect();
+    hits.sort_by(ranking);
+    hits
+}
+
+/// Adds a hit to the best so far if it ranks among the top `k`.
+fn insert_hit(best: &mut Vec<Hit>, hit: Hit, k: usize) {
+    let position = best.partition_point(|h| ranking(h, &hit) == Ordering::Less);
+    if position < k {
+        best.insert(position, hit);
+        best.truncate(k);
+    }
+}
+
+pub(crate) fn sorted_top_k(
+    query: &Fingerprint,
+    targets: &impl SortedFingerprints,
+    k: usize,
+) -> Vec<Hit> {
+    let mut best: Vec<Hit> = vec![];
+    if k == 0 {
+        return best;
+    }
+    let starts = targets.starts();
+    let query_count = query.count_ones();
+    let mut order: Vec<u32> = (0..starts.len() as u32 - 1).collect();
+    order.sort_by(|a, b| {
+        bound(query_count, *b)
+            .partial_cmp(&bound(query_count, *a))
+            .unwrap_or(Ordering::Equal)
+            .then(a.cmp(b))
+    });
+
+    for count in order {
+        if best.len() == k && bound(query_count, count) < best[k - 1].similarity {
+            break;
+        }
+        for position in starts[count as usize]..starts[count as usize + 1] {
+            let (index, words) = targets.get(position);
+            let common = popcount(query.words(), Some(words));
+            let hit = Hit {
+                index,
+                similarity: ratio(common, query_count + count - common),
+            };
+            if best.len() == k && hit.similarity < best[k - 1].similarity {
+                continue;
+            }
+            insert_hit(&mut best, hit, k);
+        }
+    }
+    best
+}
+
+pub(crate) fn sorted_threshold_search(
+    query: &Fingerprint,
+    targets: &impl SortedFingerprints,
+    threshold: f64,
+) -> Vec<Hit> {
+    let starts = targets.starts();
+    let query_count = query.count_ones();
+    let mut hits = vec![];
+    for count in 0..starts.len() as u32 - 1 {
+        if bound(query_count, count) < threshold {
+            continue;
+        }
+        for position in starts[count as usize]..starts[count as usize + 1] {
+            let (index, words) = targets.get(position);
+            let common = popcount(query.words(), Some(words));
+            let similarity = ratio(common, query_count + count - common);
+            if similarity >= threshold {
+                hits.push(Hit { index, similarity });
+            }
+        }
+    }
+    hits.sort_by(ranking);
+    hits
+}
+
+/// The highest Tanimoto similarity possible between fingerprints with these bit counts.
+fn bound(a: u32, b: u32) -> f64 {
+    ratio(a.min(b), a.max(b))
+}
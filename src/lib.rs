@@ -1,6 +1,12 @@
 pub mod core;
 pub mod daylight;
+pub mod descriptors;
+pub mod filters;
+pub mod fingerprint;
 pub mod prelude;
+pub mod reaction;
+pub mod standardize;
+pub mod substructure;
 
 #[cfg(test)]
 mod tests {
@@ -8,15 +14,11 @@ mod tests {
     use crate::daylight::*;
 
     fn c() -> Atom {
-        Atom {
-            element: Element::C,
-        }
+        Atom::new(Element::C)
     }
 
     fn n() -> Atom {
-        Atom {
-            element: Element::N,
-        }
+        Atom::new(Element::N)
     }
 
     fn methylamine() -> (Molecule, AtomIndex, AtomIndex) {
@@ -35,6 +37,22 @@ mod tests {
         assert_eq!(Element::Og.atomic_number(), 118);
     }
 
+    #[test]
+    fn elements_have_symbol_and_mass() {
+        assert_eq!(Element::Cl.symbol(), "Cl");
+        assert_eq!(Element::from_atomic_number(26), Some(Element::Fe));
+        assert_eq!(Element::from_atomic_number(0), None);
+        assert_eq!(Element::from_atomic_number(119), None);
+        assert!((Element::C.atomic_mass() - 12.011).abs() < 1e-9);
+        assert!((Element::Og.atomic_mass() - 294.0).abs() < 1e-9);
+
+        for n in 1..=118 {
+            let el = Element::from_atomic_number(n).unwrap();
+            assert_eq!(el.atomic_number(), n);
+            assert_eq!(el.symbol().parse::<Element>(), Ok(el));
+        }
+    }
+
     #[test]
     fn molecules_can_be_created() {
         // Methylamine
@@ -100,12 +118,25 @@ mod tests {
         assert_eq!(molc, mole);
     }
 
-    // /// Tests to parse simple straight chain SMILES
-    //#[test]
-    //fn can_parse_simple_smiles() {
-    //let mola = molecule_from_smiles("CN");
-    //let (molb, _, _) = methylamine();
+    /// Tests to parse simple straight chain SMILES
+    #[test]
+    fn can_parse_simple_smiles() {
+        let mola = molecule_from_smiles("[CH3][NH2]");
+        let molb = {
+            let mut mol = Molecule::new();
+            let c = mol.add_atom(Atom {
+                hydrogens: 3,
+                ..c()
+            });
+            let n = mol.add_atom(Atom {
+                hydrogens: 2,
+                ..n()
+            });
+            mol.add_bond(c, n, Bond::Single).unwrap();
+            mol
+        };
 
-    //assert_eq!(mola, Ok(molb));
-    //}
+        assert_eq!(mola, Ok(molb.clone()));
+        assert_eq!(molecule_from_smiles("CN"), Ok(molb));
+    }
 }
@@ -1 +1,4 @@
-pub use crate::core::{Atom, AtomIndex, Bond, Element, Molecule};
+pub use crate::core::{
+    Atom, AtomIndex, Bond, ChemicalEquation, Chirality, Element, MolecularFormula, Molecule,
+    Reaction,
+};
@@ -0,0 +1,318 @@
+use crate::core::{charge_valence, Atom, AtomIndex, Bond, Element, Molecule};
+use crate::daylight::{implicit_hydrogens, AtomPrimitive, BondExpr, BondPrimitive, Query};
+use crate::reaction::template::{required, specified_element, Template};
+use crate::substructure::SubstructureSearch;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Applies a template to one molecule per reactant pattern, giving the products for every
+/// combination of unique matches. Each outcome has one molecule per product pattern, holding
+/// the pattern's atoms and everything still connected to them.
+///
+/// Mapped atoms keep their properties except where the product pattern specifies a different
+/// element, charge, isotope or hydrogen count than the reactant pattern. Unmapped reactant atoms
+/// are deleted and unmapped product atoms are created. Hydrogens are adjusted so that changed
+/// atoms keep their valence, and stereo is kept only on atoms whose neighbours are unchanged.
+pub fn apply(template: &Template, reactants: &[Molecule]) -> Vec<Vec<Molecule>> {
+    if reactants.len() != template.reactants().len() {
+        return vec![];
+    }
+    let matches: Vec<Vec<Vec<AtomIndex>>> = template
+        .reactants()
+        .iter()
+        .zip(reactants)
+        .map(|(query, mol)| {
+            SubstructureSearch::new(query)
+                .unique(true)
+                .matches(mol)
+                .collect()
+        })
+        .collect();
+
+    let (combined, offsets) = combine(reactants);
+    let mut outcomes = vec![];
+    for combination in cartesian_product(&matches) {
+        let matched: Vec<Vec<AtomIndex>> = combination
+            .iter()
+            .zip(&offsets)
+            .map(|(atoms, offset)| {
+                atoms
+                    .iter()
+                    .map(|a| AtomIndex::new(a.index() + offset))
+                    .collect()
+            })
+            .collect();
+        outcomes.push(Outcome::new(template, combined.clone(), &matched).products(template));
+    }
+    outcomes
+}
+
+/// The reactants as a single molecule, and the offset of each reactant's atoms.
+fn combine(molecules: &[Molecule]) -> (Molecule, Vec<usize>) {
+    let mut combined = Molecule::new();
+    let mut offsets = vec![];
+    for mol in molecules {
+        let offset = combined.atom_count();
+        offsets.push(offset);
+        for atom in mol.atoms() {
+            combined.add_atom(*mol.atom(atom));
+        }
+        for (a, b, bond) in mol.bonds() {
+            let (a, b) = (
+                AtomIndex::new(a.index() + offset),
+                AtomIndex::new(b.index() + offset),
+            );
+            combined.add_bond(a, b, bond).unwrap();
+        }
+    }
+    (combined, offsets)
+}
+
+fn cartesian_product<T: Clone>(lists: &[Vec<T>]) -> Vec<Vec<T>> {
+    lists.iter().fold(vec![vec![]], |combinations, list| {
+        combinations
+            .iter()
+            .flat_map(|combination| {
+                list.iter().map(move |item| {
+                    let mut combination = combination.clone();
+                    combination.push(item.clone());
+                    combination
+                })
+            })
+            .collect()
+    })
+}
+
+/// The properties an atom pattern requires of its atom.
+#[derive(Default, PartialEq)]
+struct AtomSpec {
+    element: Option<(Element, Option<bool>)>,
+    charge: Option<i8>,
+    isotope: Option<u16>,
+    hydrogens: Option<u8>,
+}
+
+impl AtomSpec {
+    fn new(query: &Query, atom: AtomIndex) -> Self {
+        let expr = &query.atom(atom).expr;
+        let mut spec = AtomSpec {
+            element: specified_element(expr),
+            ..AtomSpec::default()
+        };
+        for primitive in required(expr) {
+            match primitive {
+                AtomPrimitive::Charge(charge) => spec.charge = Some(*charge),
+                AtomPrimitive::Isotope(isotope) => spec.isotope = Some(*isotope),
+                AtomPrimitive::TotalHydrogens(h) => spec.hydrogens = Some(*h),
+                _ => {}
+            }
+        }
+        spec
+    }
+
+    /// Applies the properties that `self` specifies and `before` doesn't, or specifies
+    /// differently.
+    fn apply(&self, before: &AtomSpec, atom: &mut Atom) {
+        if let Some((element, aromatic)) = self.element {
+            if self.element != before.element {
+                atom.element = element;
+                if let Some(aromatic) = aromatic {
+                    atom.aromatic = aromatic;
+                }
+            }
+        }
+        if let Some(charge) = self.charge.filter(|_| self.charge != before.charge) {
+            atom.formal_charge = charge;
+        }
+        if let Some(isotope) = self.isotope.filter(|_| self.isotope != before.isotope) {
+            atom.isotope = Some(isotope);
+        }
+    }
+}
+
+/// A working copy of the reactants, edited into the products of one combination of matches.
+struct Outcome {
+    mol: Molecule,
+    /// The molecule atom for each atom of each product pattern.
+    placed: Vec<Vec<AtomIndex>>,
+}
+
+impl Outcome {
+    fn new(template: &Template, mut mol: Molecule, matched: &[Vec<AtomIndex>]) -> Self {
+        let before = mol.clone();
+
+        // Reactant pattern atoms by map number, with their specs
+        let mut mapped: HashMap<usize, (AtomIndex, AtomSpec)> = HashMap::new();
+        let mut touched: HashSet<AtomIndex> = HashSet::new();
+        for (query, atoms) in template.reactants().iter().zip(matched) {
+            for q in query.atoms() {
+                touched.insert(atoms[q.index()]);
+                if let Some(map) = query.atom(q).map {
+                    mapped.insert(map, (atoms[q.index()], AtomSpec::new(query, q)));
+                }
+            }
+        }
+
+        // Place product pattern atoms, either onto mapped atoms or as new ones
+        let mut placed = vec![];
+        let mut fixed_hydrogens: HashSet<AtomIndex> = HashSet::new();
+        let mut created: HashSet<AtomIndex> = HashSet::new();
+        for query in template.products() {
+            let mut atoms = vec![];
+            for q in query.atoms() {
+                let spec = AtomSpec::new(query, q);
+                let existing = query.atom(q).map.and_then(|map| mapped.get(&map));
+                let atom = match existing {
+                    Some((atom, before)) => {
+                        spec.apply(before, mol.atom_mut(*atom));
+                        if let Some(h) = spec
+                            .hydrogens
+                            .filter(|_| spec.hydrogens != before.hydrogens)
+                        {
+                            mol.atom_mut(*atom).hydrogens = h;
+                            fixed_hydrogens.insert(*atom);
+                        }
+                        *atom
+                    }
+                    None => {
+                        // Template::new ensures that new atoms have an element
+                        let (element, aromatic) = spec.element.unwrap();
+                        let mut atom = Atom::new(element);
+                        atom.aromatic = aromatic.unwrap_or(false);
+                        spec.apply(&AtomSpec::default(), &mut atom);
+                        let atom = mol.add_atom(atom);
+                        if let Some(h) = spec.hydrogens {
+                            mol.atom_mut(atom).hydrogens = h;
+                            fixed_hydrogens.insert(atom);
+                        }
+                        created.insert(atom);
+                        atom
+                    }
+                };
+                atoms.push(atom);
+            }
+            placed.push(atoms);
+        }
+
+        // Atoms matched by the reactant patterns but not placed in the products are deleted
+        let kept: HashSet<AtomIndex> = placed.iter().flatten().copied().collect();
+        for atom in touched.difference(&kept) {
+            let neighbors: Vec<AtomIndex> = mol.neighbors(*atom).collect();
+            for n in neighbors {
+                mol.remove_bond(*atom, n);
+            }
+        }
+
+        // Bonds between matched atoms are broken unless the product patterns repeat them
+        for (query, atoms) in template.reactants().iter().zip(matched) {
+            for (a, b, _) in query.bonds() {
+                mol.remove_bond(atoms[a.index()], atoms[b.index()]);
+            }
+        }
+        for (query, atoms) in template.products().iter().zip(&placed) {
+            for (a, b, expr) in query.bonds() {
+                let (a, b) = (atoms[a.index()], atoms[b.index()]);
+                let bond = product_bond(expr, &mol, a, b, before.bond(a, b));
+                if mol.bond(a, b).is_some() {
+                    mol.set_bond(a, b, bond);
+                } else {
+                    mol.add_bond(a, b, bond).unwrap();
+                }
+            }
+        }
+
+        for atom in mol.atoms().collect::<Vec<AtomIndex>>() {
+            if created.contains(&atom) {
+                if !fixed_hydrogens.contains(&atom) {
+                    mol.atom_mut(atom).hydrogens = implicit_hydrogens(&mol, atom);
+                }
+                continue;
+            }
+            if !fixed_hydrogens.contains(&atom) {
+                mol.atom_mut(atom).hydrogens = adjusted_hydrogens(&before, &mol, atom);
+            }
+            let mut old: Vec<AtomIndex> = before.neighbors(atom).collect();
+            let mut new: Vec<AtomIndex> = mol.neighbors(atom).collect();
+            old.sort();
+            new.sort();
+            let hydrogens = |m: &Molecule| m.atom(atom).hydrogens > 0;
+            if old != new || hydrogens(&before) != hydrogens(&mol) {
+                mol.atom_mut(atom).chirality = None;
+            }
+        }
+
+        Outcome { mol, placed }
+    }
+
+    /// One molecule per product pattern, of the atoms connected to its placed atoms.
+    fn products(&self, template: &Template) -> Vec<Molecule> {
+        (0..template.products().len())
+            .map(|i| {
+                let mut atoms = self.reachable(&self.placed[i]);
+                atoms.sort();
+                self.mol.subgraph(&atoms)
+            })
+            .collect()
+    }
+
+    fn reachable(&self, start: &[AtomIndex]) -> Vec<AtomIndex> {
+        let mut visited: HashSet<AtomIndex> = start.iter().copied().collect();
+        let mut queue: VecDeque<AtomIndex> = start.iter().copied().collect();
+        while let Some(atom) = queue.pop_front() {
+            for n in self.mol.neighbors(atom) {
+                if visited.insert(n) {
+                    queue.push_back(n);
+                }
+            }
+        }
+        visited.into_iter().collect()
+    }
+}
+
+/// The bond a product pattern creates. Patterns that allow several bond orders keep the
+/// original bond, or make a single bond.
+fn product_bond(
+    expr: &BondExpr,
+    mol: &Molecule,
+    a: AtomIndex,
+    b: AtomIndex,
+    original: Option<Bond>,
+) -> Bond {
+    let primitives = match expr {
+        BondExpr::Primitive(p) => vec![p],
+        BondExpr::And(es) => es
+            .iter()
+            .filter_map(|e| match e {
+                BondExpr::Primitive(p) => Some(p),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    let aromatic = mol.atom(a).aromatic && mol.atom(b).aromatic;
+    primitives
+        .iter()
+        .find_map(|p| match p {
+            BondPrimitive::Single | BondPrimitive::Up | BondPrimitive::Down => Some(Bond::Single),
+            BondPrimitive::Double => Some(Bond::Double),
+            BondPrimitive::Triple => Some(Bond::Triple),
+            BondPrimitive::Aromatic => Some(Bond::Aromatic),
+            BondPrimitive::Implicit if aromatic => Some(Bond::Aromatic),
+            BondPrimitive::Implicit => Some(Bond::Single),
+            BondPrimitive::Any | BondPrimitive::Ring => None,
+        })
+        .or(original)
+        .unwrap_or(Bond::Single)
+}
+
+/// Hydrogens that keep an atom at its original valence after its bonds or charge changed.
+fn adjusted_hydrogens(before: &Molecule, after: &Molecule, atom: AtomIndex) -> u8 {
+    let bonds = |mol: &Molecule| (mol.valence(atom) - mol.atom(atom).hydrogens) as i16;
+    let charge = |mol: &Molecule| {
+        let a = mol.atom(atom);
+        charge_valence(a.element, a.formal_charge)
+    };
+    let hydrogens = before.atom(atom).hydrogens as i16 + bonds(before) - bonds(after)
+        + charge(after)
+        - charge(before);
+    hydrogens.max(0) as u8
+}
@@ -0,0 +1,306 @@
+use crate::core::{AtomIndex, Bond, Element, Molecule, Reaction};
+use crate::daylight::{
+    query_from_smarts, AtomExpr, AtomPrimitive, BondExpr, BondPrimitive, Expr, Query, QueryAtom,
+};
+use crate::reaction::Template;
+use crate::substructure::SubstructureSearch;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Functional groups kept whole whenever a template reaches any of their atoms, so that a template
+/// doesn't match only part of a group whose reactivity depends on all of it.
+const SPECIAL_GROUPS: &[&str] = &[
+    // Carboxylic acids, esters, amides and other acyl groups
+    "[CX3](=O)[O,N,S,Cl,Br]",
+    "[CX3]=[OX1]",
+    "C#N",
+    "[N+](=O)[O-]",
+    "S(=O)(=O)",
+    "N=[N+]=[N-]",
+    "C=C",
+    "C#C",
+    // Boc and Cbz protecting groups
+    "C(=O)OC(C)(C)C",
+    "C(=O)OCc1ccccc1",
+    // Boronic acids and esters
+    "B(O)O",
+];
+
+/// The special groups, parsed on first use.
+fn special_groups() -> &'static [Query] {
+    static GROUPS: OnceLock<Vec<Query>> = OnceLock::new();
+    GROUPS.get_or_init(|| {
+        SPECIAL_GROUPS
+            .iter()
+            .map(|s| query_from_smarts(s).unwrap())
+            .collect()
+    })
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ExtractionError {
+    /// The reaction has no atom maps to tell which atoms react.
+    Unmapped,
+    /// No mapped atom or bond differs between the reactants and products.
+    NoChange,
+}
+
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractionError::Unmapped => write!(f, "reaction has no atom maps"),
+            ExtractionError::NoChange => write!(f, "reaction has no reaction center"),
+        }
+    }
+}
+
+impl std::error::Error for ExtractionError {}
+
+/// Extracts a reaction template from a mapped reaction.
+///
+/// The template covers the changed atoms, whose bonds or properties differ across the arrow,
+/// along with the atoms within a radius of them, any special groups those reach, and whole
+/// unmapped leaving groups. Changed and unmapped atoms are specified exactly, by element,
+/// aromaticity, hydrogen count, degree and charge, while the surrounding atoms only keep their
+/// element, aromaticity and charge. Stereo isn't included.
+#[derive(Clone, Debug)]
+pub struct TemplateExtractor {
+    radius: usize,
+    special_groups: bool,
+}
+
+impl TemplateExtractor {
+    /// Includes atoms one bond away from the reaction center, and special groups.
+    pub fn new() -> Self {
+        TemplateExtractor {
+            radius: 1,
+            special_groups: true,
+        }
+    }
+
+    pub fn radius(mut self, radius: usize) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn special_groups(mut self, special_groups: bool) -> Self {
+        self.special_groups = special_groups;
+        self
+    }
+
+    /// A template with a pattern for each reactant and product that takes part in the reaction.
+    pub fn extract(&self, reaction: &Reaction) -> Result<Template, ExtractionError> {
+        if !reaction.is_mapped() {
+            return Err(ExtractionError::Unmapped);
+        }
+        let reactants = Side::new(&reaction.reactants);
+        let products = Side::new(&reaction.products);
+
+        let changed: BTreeSet<usize> = reactants
+            .maps
+            .keys()
+            .filter(|m| products.maps.contains_key(m))
+            .filter(|m| reactants.signature(**m) != products.signature(**m))
+            .copied()
+            .collect();
+        if changed.is_empty() {
+            return Err(ExtractionError::NoChange);
+        }
+
+        let mut selected = changed.clone();
+        for _ in 0..self.radius {
+            let neighbors: Vec<usize> = selected
+                .iter()
+                .flat_map(|m| {
+                    let mut maps = reactants.neighbor_maps(*m);
+                    maps.extend(products.neighbor_maps(*m));
+                    maps
+                })
+                .collect();
+            selected.extend(neighbors);
+        }
+        if self.special_groups {
+            let groups = special_groups();
+            let mut extra = reactants.special_groups(groups, &selected);
+            extra.extend(products.special_groups(groups, &selected));
+            selected.extend(extra);
+        }
+
+        let reactant_patterns = reactants.patterns(&selected, &changed);
+        let product_patterns = products.patterns(&selected, &changed);
+        // Product atoms are either mapped or have their element specified
+        Ok(Template::new(reactant_patterns, product_patterns).unwrap())
+    }
+}
+
+impl Default for TemplateExtractor {
+    fn default() -> Self {
+        TemplateExtractor::new()
+    }
+}
+
+impl Reaction {
+    /// Extracts a template with the default [`TemplateExtractor`].
+    pub fn template(&self) -> Result<Template, ExtractionError> {
+        TemplateExtractor::new().extract(self)
+    }
+}
+
+/// The molecules on one side of a reaction.
+struct Side<'r> {
+    molecules: &'r [Molecule],
+    /// The molecule and atom with each map number.
+    maps: HashMap<usize, (usize, AtomIndex)>,
+}
+
+/// An atom's properties and its bonds, to neighbours by map number or, if they are unmapped, by
+/// element.
+type Signature = (Element, bool, i8, u8, Vec<(Option<usize>, Element, Bond)>);
+
+impl<'r> Side<'r> {
+    fn new(molecules: &'r [Molecule]) -> Self {
+        let mut maps = HashMap::new();
+        for (i, mol) in molecules.iter().enumerate() {
+            for atom in mol.atoms() {
+                if let Some(map) = mol.atom(atom).map {
+                    maps.insert(map, (i, atom));
+                }
+            }
+        }
+        Side { molecules, maps }
+    }
+
+    fn signature(&self, map: usize) -> Signature {
+        let (i, atom) = self.maps[&map];
+        let mol = &self.molecules[i];
+        let a = mol.atom(atom);
+        let mut bonds: Vec<(Option<usize>, Element, Bond)> = mol
+            .neighbors(atom)
+            .map(|n| {
+                (
+                    mol.atom(n).map,
+                    mol.atom(n).element,
+                    mol.bond(atom, n).unwrap(),
+                )
+            })
+            .collect();
+        bonds.sort_by_key(|(map, element, bond)| (*map, element.atomic_number(), *bond as u8));
+        (
+            a.element,
+            a.aromatic,
+            a.formal_charge,
+            total_hydrogens(mol, atom),
+            bonds,
+        )
+    }
+
+    fn neighbor_maps(&self, map: usize) -> Vec<usize> {
+        match self.maps.get(&map) {
+            Some((i, atom)) => {
+                let mol = &self.molecules[*i];
+                mol.neighbors(*atom)
+                    .filter_map(|n| mol.atom(n).map)
+                    .collect()
+            }
+            None => vec![],
+        }
+    }
+
+    /// The map numbers of special groups that include a selected atom.
+    fn special_groups(&self, groups: &[Query], selected: &BTreeSet<usize>) -> Vec<usize> {
+        let mut maps = vec![];
+        for mol in self.molecules {
+            for group in groups {
+                for atoms in SubstructureSearch::new(group).unique(true).matches(mol) {
+                    let group_maps: Vec<Option<usize>> =
+                        atoms.iter().map(|a| mol.atom(*a).map).collect();
+                    if group_maps
+                        .iter()
+                        .any(|m| matches!(m, Some(m) if selected.contains(m)))
+                    {
+                        maps.extend(group_maps.into_iter().flatten());
+                    }
+                }
+            }
+        }
+        maps
+    }
+
+    /// A pattern for each molecule with selected atoms, which also includes the unmapped atoms
+    /// attached to them through other unmapped atoms.
+    fn patterns(&self, selected: &BTreeSet<usize>, changed: &BTreeSet<usize>) -> Vec<Query> {
+        let mut patterns = vec![];
+        for mol in self.molecules {
+            let is_selected =
+                |atom: AtomIndex| matches!(mol.atom(atom).map, Some(m) if selected.contains(&m));
+            let mut included: HashSet<AtomIndex> =
+                mol.atoms().filter(|a| is_selected(*a)).collect();
+            let mut stack: Vec<AtomIndex> = included.iter().copied().collect();
+            while let Some(atom) = stack.pop() {
+                for n in mol.neighbors(atom) {
+                    if mol.atom(n).map.is_none() && included.insert(n) {
+                        stack.push(n);
+                    }
+                }
+            }
+            if included.is_empty() {
+                continue;
+            }
+
+            let mut atoms: Vec<AtomIndex> = included.into_iter().collect();
+            atoms.sort();
+            let mut query = Query::new();
+            let indices: HashMap<AtomIndex, AtomIndex> = atoms
+                .iter()
+                .map(|atom| {
+                    let exact = match mol.atom(*atom).map {
+                        Some(m) => changed.contains(&m),
+                        None => true,
+                    };
+                    (*atom, query.add_atom(query_atom(mol, *atom, exact)))
+                })
+                .collect();
+            for (a, b, bond) in mol.bonds() {
+                if let (Some(a), Some(b)) = (indices.get(&a), indices.get(&b)) {
+                    query.add_bond(*a, *b, query_bond(bond)).unwrap();
+                }
+            }
+            patterns.push(query);
+        }
+        patterns
+    }
+}
+
+fn total_hydrogens(mol: &Molecule, atom: AtomIndex) -> u8 {
+    let explicit = mol
+        .neighbors(atom)
+        .filter(|n| mol.atom(*n).element == Element::H)
+        .count() as u8;
+    mol.atom(atom).hydrogens + explicit
+}
+
+fn query_atom(mol: &Molecule, atom: AtomIndex, exact: bool) -> QueryAtom {
+    let a = mol.atom(atom);
+    let mut primitives = vec![AtomPrimitive::Symbol {
+        element: a.element,
+        aromatic: a.aromatic,
+    }];
+    if exact {
+        primitives.push(AtomPrimitive::TotalHydrogens(total_hydrogens(mol, atom)));
+        primitives.push(AtomPrimitive::Degree(mol.degree(atom) as u8));
+    }
+    primitives.push(AtomPrimitive::Charge(a.formal_charge));
+    primitives.extend(a.isotope.map(AtomPrimitive::Isotope));
+    let expr: AtomExpr = Expr::and(primitives.into_iter().map(Expr::Primitive).collect());
+    QueryAtom { expr, map: a.map }
+}
+
+fn query_bond(bond: Bond) -> BondExpr {
+    Expr::Primitive(match bond {
+        Bond::Single => BondPrimitive::Single,
+        Bond::Double => BondPrimitive::Double,
+        Bond::Triple => BondPrimitive::Triple,
+        Bond::Aromatic => BondPrimitive::Aromatic,
+    })
+}
@@ -0,0 +1,165 @@
+use crate::core::{AtomIndex, Molecule, Reaction, ReactionAtom};
+use crate::substructure::{BondComparison, McsSearch};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Maps the atoms of a reaction, assigning map numbers to matching reactant and product atoms so
+/// that few bonds are formed, broken or changed.
+///
+/// The mapping is built greedily. The largest common substructure of the unmapped atoms is mapped
+/// first, comparing bond orders, and the search repeats until no bonds are left in common. It then
+/// repeats ignoring bond orders, and finally maps single atoms of the same element, preferring
+/// those whose mapped neighbours correspond. The result isn't guaranteed to be minimal.
+#[derive(Clone, Debug)]
+pub struct AtomMapper {
+    timeout: Option<Duration>,
+}
+
+impl AtomMapper {
+    /// Limits each common substructure search to one second.
+    pub fn new() -> Self {
+        AtomMapper {
+            timeout: Some(Duration::from_secs(1)),
+        }
+    }
+
+    /// Limits each of the common substructure searches, which take exponential time in the worst
+    /// case. With no timeout the mapping is found from maximum common substructures, however long
+    /// that takes.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// A copy of the reaction with its reactants and products mapped. Existing map numbers are
+    /// replaced, and atoms left without a partner have none.
+    pub fn map(&self, reaction: &Reaction) -> Reaction {
+        let reactants = Side::new(&reaction.reactants);
+        let products = Side::new(&reaction.products);
+        let mut pairs: Vec<(usize, usize)> = vec![];
+
+        for bonds in &[BondComparison::Order, BondComparison::Any] {
+            let mut search = McsSearch::new().bonds(*bonds);
+            if let Some(timeout) = self.timeout {
+                search = search.timeout(timeout);
+            }
+            loop {
+                let r = reactants.unmapped(pairs.iter().map(|(r, _)| *r));
+                let p = products.unmapped(pairs.iter().map(|(_, p)| *p));
+                let mcs = search.find(&reactants.mol.subgraph(&r), &products.mol.subgraph(&p));
+                if mcs.bond_count() == 0 {
+                    break;
+                }
+                pairs.extend(
+                    mcs.mapping
+                        .iter()
+                        .map(|(a, b)| (r[a.index()].index(), p[b.index()].index())),
+                );
+            }
+        }
+        map_single_atoms(&reactants.mol, &products.mol, &mut pairs);
+
+        pairs.sort();
+        let mut mapped = reaction.clone();
+        clear_maps(&mut mapped.reactants);
+        clear_maps(&mut mapped.products);
+        for (i, (r, p)) in pairs.into_iter().enumerate() {
+            let r = reactants.atoms[r];
+            let p = products.atoms[p];
+            mapped.reactants[r.molecule].atom_mut(r.atom).map = Some(i + 1);
+            mapped.products[p.molecule].atom_mut(p.atom).map = Some(i + 1);
+        }
+        mapped
+    }
+}
+
+impl Default for AtomMapper {
+    fn default() -> Self {
+        AtomMapper::new()
+    }
+}
+
+impl Reaction {
+    /// Maps the reaction's atoms with the default [`AtomMapper`].
+    pub fn with_atom_mapping(&self) -> Reaction {
+        AtomMapper::new().map(self)
+    }
+}
+
+/// One side of a reaction as a single molecule.
+struct Side {
+    mol: Molecule,
+    /// The reaction atom of each atom in `mol`.
+    atoms: Vec<ReactionAtom>,
+}
+
+impl Side {
+    fn new(molecules: &[Molecule]) -> Self {
+        let mut side = Side {
+            mol: Molecule::new(),
+            atoms: vec![],
+        };
+        for (molecule, mol) in molecules.iter().enumerate() {
+            let offset = side.mol.atom_count();
+            for atom in mol.atoms() {
+                side.mol.add_atom(*mol.atom(atom));
+                side.atoms.push(ReactionAtom { molecule, atom });
+            }
+            for (a, b, bond) in mol.bonds() {
+                let a = AtomIndex::new(a.index() + offset);
+                let b = AtomIndex::new(b.index() + offset);
+                side.mol.add_bond(a, b, bond).unwrap();
+            }
+        }
+        side
+    }
+
+    /// The atoms not among those already mapped.
+    fn unmapped(&self, mapped: impl Iterator<Item = usize>) -> Vec<AtomIndex> {
+        let mapped: HashSet<usize> = mapped.collect();
+        self.mol
+            .atoms()
+            .filter(|a| !mapped.contains(&a.index()))
+            .collect()
+    }
+}
+
+/// Pairs the remaining atoms of the same element one at a time, each time choosing the pair with
+/// the most neighbours already paired with each other.
+fn map_single_atoms(reactants: &Molecule, products: &Molecule, pairs: &mut Vec<(usize, usize)>) {
+    loop {
+        let partners: HashMap<usize, usize> = pairs.iter().map(|(r, p)| (*p, *r)).collect();
+        let used: Vec<usize> = pairs.iter().map(|(r, _)| *r).collect();
+        let mut best: Option<(usize, (usize, usize))> = None;
+        for p in products
+            .atoms()
+            .filter(|p| !partners.contains_key(&p.index()))
+        {
+            for r in reactants.atoms().filter(|r| !used.contains(&r.index())) {
+                if reactants.atom(r).element != products.atom(p).element {
+                    continue;
+                }
+                let score = products
+                    .neighbors(p)
+                    .filter_map(|n| partners.get(&n.index()))
+                    .filter(|n| reactants.bond(r, AtomIndex::new(**n)).is_some())
+                    .count();
+                if !matches!(best, Some((best, _)) if best >= score) {
+                    best = Some((score, (r.index(), p.index())));
+                }
+            }
+        }
+        match best {
+            Some((_, pair)) => pairs.push(pair),
+            None => break,
+        }
+    }
+}
+
+fn clear_maps(molecules: &mut [Molecule]) {
+    for mol in molecules {
+        for atom in mol.atoms().collect::<Vec<AtomIndex>>() {
+            mol.atom_mut(atom).map = None;
+        }
+    }
+}
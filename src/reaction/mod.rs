@@ -0,0 +1,294 @@
+mod apply;
+mod extract;
+mod mapping;
+mod template;
+
+pub use apply::*;
+pub use extract::*;
+pub use mapping::*;
+pub use template::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{Bond, Element, Molecule, Reaction, ReactionCenter};
+    use crate::daylight::*;
+    use crate::reaction::*;
+
+    fn smiles(s: &str) -> Molecule {
+        molecule_from_smiles(s).expect("Invalid SMILES")
+    }
+
+    fn smirks(s: &str) -> Template {
+        template_from_smirks(s).expect("Invalid SMIRKS")
+    }
+
+    fn products(template: &Template, reactants: &[&str]) -> Vec<Vec<Molecule>> {
+        let reactants: Vec<Molecule> = reactants.iter().map(|s| smiles(s)).collect();
+        apply(template, &reactants)
+    }
+
+    fn outcome(products: &[&str]) -> Vec<Molecule> {
+        products.iter().map(|s| smiles(s)).collect()
+    }
+
+    #[test]
+    fn can_parse_templates() {
+        let template = smirks("[C:1](=[O:2])O.[N:3]>[Pd]>[C:1](=[O:2])[N:3]");
+        assert_eq!(template.reactants().len(), 2);
+        assert_eq!(template.products().len(), 1);
+        // Dots inside recursive SMARTS don't separate patterns
+        assert_eq!(smirks("[C;$(C.O):1]>>[C:1]").reactants().len(), 1);
+        assert_eq!(smirks(">>[C]").reactants().len(), 0);
+
+        assert_eq!(
+            template_from_smirks("[C:1]>>"),
+            Ok(Template::new(vec![query_from_smarts("[C:1]").unwrap()], vec![]).unwrap())
+        );
+        assert_eq!(template_from_smirks("C>C"), Err(TemplateError::Arrows));
+        assert_eq!(template_from_smirks("C>>>C"), Err(TemplateError::Arrows));
+        assert_eq!(
+            template_from_smirks("C>>C("),
+            Err(TemplateError::Smarts(SmartsError::Syntax(1)))
+        );
+        // New atoms need an element
+        assert_eq!(
+            template_from_smirks("[C:1]>>[C:1]*"),
+            Err(TemplateError::UnspecifiedAtom(1))
+        );
+        assert_eq!(
+            template_from_smirks("[C:1]>>[C:1][#7,#8]"),
+            Err(TemplateError::UnspecifiedAtom(1))
+        );
+        assert!(template_from_smirks("[C:1]>>[C:1][#7]").is_ok());
+    }
+
+    #[test]
+    fn can_apply_templates() {
+        let amide = smirks("[C:1](=[O:2])[OH].[N;!H0:3]>>[C:1](=[O:2])[N:3]");
+        assert_eq!(
+            products(&amide, &["CC(=O)O", "NC"]),
+            vec![outcome(&["CC(=O)NC"])]
+        );
+        // Every combination of matches gives an outcome
+        assert_eq!(
+            products(&amide, &["OC(=O)CC(=O)O", "NC"]),
+            vec![outcome(&["OC(=O)CC(=O)NC"]), outcome(&["CNC(=O)CC(=O)O"])]
+        );
+        assert_eq!(products(&amide, &["CC(=O)O", "CN(C)C"]).len(), 0);
+        // One molecule is needed per reactant pattern
+        assert_eq!(products(&amide, &["CC(=O)O"]).len(), 0);
+        assert_eq!(products(&amide, &["CC(=O)O", "N", "N"]).len(), 0);
+    }
+
+    #[test]
+    fn can_change_bonds_and_atoms() {
+        // Bond orders change, and hydrogens follow
+        let reduction = smirks("[C:1]=[O:2]>>[C:1][O:2]");
+        assert_eq!(
+            products(&reduction, &["CC(=O)C"]),
+            vec![outcome(&["CC(O)C"])]
+        );
+
+        // Charges change valence
+        let protonation = smirks("[N;H2:1]>>[N+:1]");
+        assert_eq!(products(&protonation, &["CN"]), vec![outcome(&["C[NH3+]"])]);
+
+        // Explicit hydrogen counts are kept
+        let deprotonation = smirks("[O;H1:1]>>[O-;H0:1]");
+        assert_eq!(products(&deprotonation, &["CO"]), vec![outcome(&["C[O-]"])]);
+
+        let halogen_exchange = smirks("[C:1][Cl:2]>>[C:1][Br:2]");
+        assert_eq!(
+            products(&halogen_exchange, &["ClCCO"]),
+            vec![outcome(&["BrCCO"])]
+        );
+    }
+
+    #[test]
+    fn can_delete_and_create_atoms() {
+        // Unmapped reactant atoms are deleted, along with anything only attached through them
+        let hydrolysis = smirks("[C:1](=[O:2])O[C:3]>>[C:1](=[O:2])O.[C:3]");
+        assert_eq!(
+            products(&hydrolysis, &["CC(=O)OCC"]),
+            vec![outcome(&["CC(=O)O", "CC"])]
+        );
+        let dehalogenation = smirks("[c:1]Cl>>[c:1]");
+        assert_eq!(
+            products(&dehalogenation, &["Clc1ccccc1C"]),
+            vec![outcome(&["c1ccccc1C"])]
+        );
+        let cleavage = smirks("[C:1]O[C:2]>>[C:1].[C:2]");
+        assert_eq!(
+            products(&cleavage, &["CCOC(C)C"]),
+            vec![outcome(&["CC", "CCC"])]
+        );
+    }
+
+    #[test]
+    fn can_reverse_templates() {
+        let amide = smirks("[C:1](=[O:2])O.[N:3]>>[C:1](=[O:2])[N:3]");
+        let disconnection = amide.reversed().unwrap();
+        assert_eq!(
+            products(&disconnection, &["CC(=O)NCc1ccccc1"]),
+            vec![outcome(&["CC(=O)O", "NCc1ccccc1"])]
+        );
+        // The leaving group can't be recreated without an element
+        let template = smirks("[C:1](=[O:2])[*].[N:3]>>[C:1](=[O:2])[N:3]");
+        assert!(template.reversed().is_err());
+    }
+
+    #[test]
+    fn keeps_unchanged_stereo() {
+        let methylation = smirks("[O;H1:1]>>[O:1]C");
+        assert_eq!(
+            products(&methylation, &["F[C@H](Cl)CO"]),
+            vec![outcome(&["F[C@H](Cl)COC"])]
+        );
+        // Replacing a neighbour loses the stereo
+        let substitution = smirks("[C:1]Cl>>[C:1]Br");
+        assert_eq!(
+            products(&substitution, &["F[C@H](Cl)CO"]),
+            vec![outcome(&["FC(Br)CO"])]
+        );
+    }
+
+    fn mapped(s: &str) -> (Reaction, ReactionCenter) {
+        let reaction = reaction_from_smiles(s)
+            .expect("Invalid reaction SMILES")
+            .with_atom_mapping();
+        let center = reaction.center();
+        (reaction, center)
+    }
+
+    /// The element of the reactant atom with a map number
+    fn element(reaction: &Reaction, map: usize) -> Element {
+        let (atom, _) = reaction.atom_mapping()[map - 1];
+        reaction.reactants[atom.molecule].atom(atom.atom).element
+    }
+
+    #[test]
+    fn can_map_atoms() {
+        let (reaction, center) = mapped("CC(=O)O.NC>>CC(=O)NC.O");
+        // Every atom has a partner
+        assert_eq!(reaction.atom_mapping().len(), 6);
+        assert_eq!(center.formed.len(), 1);
+        assert_eq!(center.broken.len(), 1);
+        assert!(center.changed.is_empty());
+        let (a, b, bond) = center.formed[0];
+        assert_eq!(
+            (element(&reaction, a), element(&reaction, b)),
+            (Element::C, Element::N)
+        );
+        assert_eq!(bond, Bond::Single);
+        let (a, b, _) = center.broken[0];
+        assert_eq!(
+            (element(&reaction, a), element(&reaction, b)),
+            (Element::C, Element::O)
+        );
+        assert_eq!(center.atoms().len(), 3);
+
+        let (_, center) = mapped("CCBr.[OH-]>>CCO.[Br-]");
+        assert_eq!(center.formed.len(), 1);
+        assert_eq!(center.broken.len(), 1);
+
+        // Existing maps are replaced
+        let (reaction, _) = mapped("[CH3:7][OH:9]>>[CH2:3]=[O:4]");
+        assert_eq!(
+            reaction_to_smiles(&reaction),
+            "[CH3:1][OH:2]>>[CH2:1]=[O:2]"
+        );
+
+        // Searches are limited by default, but can run to completion
+        let reaction = reaction_from_smiles("CC(=O)O.NC>>CC(=O)NC.O").unwrap();
+        assert_eq!(
+            AtomMapper::new().timeout(None).map(&reaction),
+            reaction.with_atom_mapping()
+        );
+    }
+
+    #[test]
+    fn can_find_reaction_centers() {
+        let (_, center) = mapped("CC(=O)C>>CC(O)C");
+        assert!(center.formed.is_empty() && center.broken.is_empty());
+        assert_eq!(center.changed.len(), 1);
+        let (_, _, before, after) = center.changed[0];
+        assert_eq!((before, after), (Bond::Double, Bond::Single));
+
+        // Ring closures form a bond within one molecule
+        let (_, center) = mapped("BrCCCCO>>C1CCCO1.Br");
+        assert_eq!(center.formed.len(), 1);
+        assert_eq!(center.broken.len(), 1);
+
+        let (_, center) = mapped("CCO>>CCO");
+        assert!(center.is_empty());
+        assert_eq!(Reaction::default().center(), ReactionCenter::default());
+    }
+
+    #[test]
+    fn can_extract_templates() {
+        let reaction = reaction_from_smiles(
+            "[CH3:1][C:2](=[O:3])[OH:4].[NH2:5][CH3:6]>>[CH3:1][C:2](=[O:3])[NH:5][CH3:6].[OH2:4]",
+        )
+        .unwrap();
+        let template = reaction.template().unwrap();
+        assert_eq!(
+            template_to_smirks(&template),
+            "[C;+0:1]-[C;H0;D3;+0:2](=[O;+0:3])-[O;H1;D1;+0:4].[N;H2;D1;+0:5]-[C;+0:6]>>\
+             [C;+0:1]-[C;H0;D3;+0:2](=[O;+0:3])-[N;H1;D2;+0:5]-[C;+0:6].[O;H2;D0;+0:4]"
+        );
+        // The template applies to other reactants, also after a round trip through SMIRKS
+        let expected = vec![outcome(&["CCC(=O)NCCO", "O"])];
+        assert_eq!(products(&template, &["CCC(=O)O", "NCCO"]), expected);
+        let parsed = smirks(&template_to_smirks(&template));
+        assert_eq!(products(&parsed, &["CCC(=O)O", "NCCO"]), expected);
+
+        // Only the reacting atoms, with the rest of the acid kept as a special group
+        let center = TemplateExtractor::new().radius(0);
+        assert_eq!(
+            template_to_smirks(&center.clone().special_groups(false).extract(&reaction).unwrap()),
+            "[C;H0;D3;+0:2]-[O;H1;D1;+0:4].[N;H2;D1;+0:5]>>[C;H0;D3;+0:2]-[N;H1;D2;+0:5].[O;H2;D0;+0:4]"
+        );
+        let template = center.extract(&reaction).unwrap();
+        assert_eq!(template.reactants()[0].atom_count(), 3);
+    }
+
+    #[test]
+    fn can_extract_leaving_groups() {
+        // Unmapped atoms leave whole, and spectators are left out
+        let reaction =
+            reaction_from_smiles("[CH3:1][CH2:2]OS(=O)(=O)C.[OH-:3].O>>[CH3:1][CH2:2][OH:3]")
+                .unwrap();
+        let template = TemplateExtractor::new()
+            .special_groups(false)
+            .extract(&reaction)
+            .unwrap();
+        assert_eq!(
+            template_to_smirks(&template),
+            "[C;+0:1]-[C;H2;D2;+0:2]-[O;H0;D2;+0]-[S;H0;D4;+0](=[O;H0;D1;+0])(=[O;H0;D1;+0])-[C;H3;D1;+0].[O;H1;D0;-:3]>>\
+             [C;+0:1]-[C;H2;D2;+0:2]-[O;H1;D1;+0:3]"
+        );
+
+        // Disconnected parts of one molecule stay in one pattern
+        let reaction =
+            reaction_from_smiles("[Br:1][CH2:2]CC[CH2:3][OH:4]>>[CH2:2]1CC[CH2:3][O:4]1.[Br:1]")
+                .unwrap();
+        let template = TemplateExtractor::new()
+            .radius(0)
+            .extract(&reaction)
+            .unwrap();
+        assert_eq!(template.reactants().len(), 1);
+        let smirks = template_to_smirks(&template);
+        assert!(smirks.starts_with('('), "{}", smirks);
+        assert_eq!(template_from_smirks(&smirks).unwrap().reactants().len(), 1);
+    }
+
+    #[test]
+    fn can_reject_unextractable_reactions() {
+        let unmapped = reaction_from_smiles("CCO>>CC=O").unwrap();
+        assert_eq!(unmapped.template(), Err(ExtractionError::Unmapped));
+        let unchanged = reaction_from_smiles("[CH3:1][OH:2]>>[CH3:1][OH:2]").unwrap();
+        assert_eq!(unchanged.template(), Err(ExtractionError::NoChange));
+        // Mapping makes it possible
+        assert!(unmapped.with_atom_mapping().template().is_ok());
+    }
+}
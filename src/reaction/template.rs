@@ -0,0 +1,171 @@
+use crate::core::Element;
+use crate::daylight::{
+    query_from_smarts, query_to_smarts, AtomExpr, AtomPrimitive, Expr, Query, SmartsError,
+};
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TemplateError {
+    /// The template does not have exactly two `>` separating reactants, agents and products.
+    Arrows,
+    /// A reactant or product pattern is invalid SMARTS.
+    Smarts(SmartsError),
+    /// A product atom has no map number found among the reactants, and no element to create it
+    /// from. The index is the atom's position in its product pattern.
+    UnspecifiedAtom(usize),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Arrows => write!(f, "expected reactants>agents>products"),
+            TemplateError::Smarts(e) => write!(f, "{}", e),
+            TemplateError::UnspecifiedAtom(atom) => {
+                write!(f, "unmapped product atom {} has no element", atom)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl From<SmartsError> for TemplateError {
+    fn from(e: SmartsError) -> Self {
+        TemplateError::Smarts(e)
+    }
+}
+
+/// A reaction template: one query per reactant and one per product. Atoms with the same map
+/// number on both sides are the same atom before and after the reaction.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Template {
+    reactants: Vec<Query>,
+    products: Vec<Query>,
+}
+
+impl Template {
+    pub fn new(reactants: Vec<Query>, products: Vec<Query>) -> Result<Self, TemplateError> {
+        let maps: Vec<usize> = reactants
+            .iter()
+            .flat_map(|r| r.atoms().filter_map(move |a| r.atom(a).map))
+            .collect();
+        for product in &products {
+            for atom in product.atoms() {
+                let a = product.atom(atom);
+                let mapped = matches!(a.map, Some(map) if maps.contains(&map));
+                if !mapped && specified_element(&a.expr).is_none() {
+                    return Err(TemplateError::UnspecifiedAtom(atom.index()));
+                }
+            }
+        }
+        Ok(Template {
+            reactants,
+            products,
+        })
+    }
+
+    pub fn reactants(&self) -> &[Query] {
+        &self.reactants
+    }
+
+    pub fn products(&self) -> &[Query] {
+        &self.products
+    }
+
+    /// The template run backwards, from products to reactants, e.g. for retrosynthesis.
+    pub fn reversed(&self) -> Result<Template, TemplateError> {
+        Template::new(self.products.clone(), self.reactants.clone())
+    }
+}
+
+/// Parses a SMIRKS reaction template. Each dot separated pattern is a separate reactant or
+/// product, unless grouped in parentheses like `(C.O)`, and agents are ignored.
+pub fn template_from_smirks(smirks: &str) -> Result<Template, TemplateError> {
+    let parts: Vec<&str> = smirks.split('>').collect();
+    if parts.len() != 3 {
+        return Err(TemplateError::Arrows);
+    }
+    let patterns = |part: &str| -> Result<Vec<Query>, SmartsError> {
+        split_components(part)
+            .into_iter()
+            .map(|component| {
+                let grouped = component.starts_with('(') && component.ends_with(')');
+                if grouped {
+                    query_from_smarts(&component[1..component.len() - 1])
+                } else {
+                    query_from_smarts(component)
+                }
+            })
+            .collect()
+    };
+    Template::new(patterns(parts[0])?, patterns(parts[2])?)
+}
+
+/// Writes a template as SMIRKS with no agents. Disconnected patterns are grouped in parentheses.
+pub fn template_to_smirks(template: &Template) -> String {
+    let side = |patterns: &[Query]| {
+        patterns
+            .iter()
+            .map(|query| {
+                let smarts = query_to_smarts(query);
+                if smarts.contains('.') {
+                    format!("({})", smarts)
+                } else {
+                    smarts
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(".")
+    };
+    format!(
+        "{}>>{}",
+        side(template.reactants()),
+        side(template.products())
+    )
+}
+
+/// Splits at dots outside of brackets and parentheses, which belong to recursive SMARTS.
+fn split_components(smarts: &str) -> Vec<&str> {
+    if smarts.is_empty() {
+        return vec![];
+    }
+    let mut components = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in smarts.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '.' if depth == 0 => {
+                components.push(&smarts[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    components.push(&smarts[start..]);
+    components
+}
+
+/// The primitives that an atom expression requires, ignoring any under `!` or `,`.
+pub(crate) fn required(expr: &AtomExpr) -> Vec<&AtomPrimitive> {
+    match expr {
+        Expr::Primitive(p) => vec![p],
+        Expr::And(es) => es.iter().flat_map(required).collect(),
+        Expr::Not(_) | Expr::Or(_) => vec![],
+    }
+}
+
+/// The element an atom expression requires, and its aromaticity if that is required too.
+pub(crate) fn specified_element(expr: &AtomExpr) -> Option<(Element, Option<bool>)> {
+    let primitives = required(expr);
+    let aromatic = primitives.iter().find_map(|p| match p {
+        AtomPrimitive::Aromatic => Some(true),
+        AtomPrimitive::Aliphatic => Some(false),
+        _ => None,
+    });
+    primitives.iter().find_map(|p| match p {
+        AtomPrimitive::Symbol { element, aromatic } => Some((*element, Some(*aromatic))),
+        AtomPrimitive::AtomicNumber(n) => Element::from_atomic_number(*n).map(|e| (e, aromatic)),
+        _ => None,
+    })
+}
@@ -0,0 +1,141 @@
+use crate::core::{AtomIndex, Bond, Element, Molecule};
+use crate::standardize::{Change, Step};
+
+/// Removes charges by adding or removing hydrogens. Cations lose hydrogens and anions gain them,
+/// except where an anion balances a cation that has no hydrogen to lose, such as a quaternary
+/// ammonium. The most acidic of those anions are left charged. Charge separated groups like
+/// `[N+][O-]` are left alone.
+pub fn neutralize(mol: &mut Molecule) -> Vec<Change> {
+    let mut changes = vec![];
+    let atoms: Vec<AtomIndex> = mol.atoms().collect();
+
+    for &atom in &atoms {
+        let a = mol.atom(atom);
+        if a.formal_charge > 0 && a.hydrogens > 0 && !has_charged_neighbor(mol, atom, -1) {
+            let removed = (a.formal_charge as u8).min(a.hydrogens);
+            let a = mol.atom_mut(atom);
+            a.hydrogens -= removed;
+            a.formal_charge -= removed as i8;
+            changes.push(change(
+                Step::Neutralize,
+                atom,
+                "removed hydrogen from cation",
+            ));
+        }
+    }
+
+    // Cations that are left must stay balanced by anions
+    let cations: i32 = atoms
+        .iter()
+        .filter(|a| !has_charged_neighbor(mol, **a, -1))
+        .map(|a| mol.atom(*a).formal_charge.max(0) as i32)
+        .sum();
+    let mut anions: Vec<(AtomIndex, u8)> = atoms
+        .iter()
+        .filter(|a| mol.atom(**a).formal_charge < 0)
+        .filter(|a| !has_charged_neighbor(mol, **a, 1))
+        .filter(|a| matches!(mol.atom(**a).element, Element::O | Element::S | Element::N))
+        .map(|a| (*a, acidity(mol, *a).unwrap_or(u8::MAX)))
+        .collect();
+    // Weakest acids are neutralized first, ties broken by atom index
+    anions.sort_by_key(|(atom, rank)| (std::cmp::Reverse(*rank), *atom));
+
+    let mut excess: i32 = anions
+        .iter()
+        .map(|(a, _)| -mol.atom(*a).formal_charge as i32)
+        .sum::<i32>()
+        - cations;
+    for (atom, _) in anions {
+        if excess <= 0 {
+            break;
+        }
+        let added = (-mol.atom(atom).formal_charge as i32).min(excess);
+        let a = mol.atom_mut(atom);
+        a.hydrogens += added as u8;
+        a.formal_charge += added as i8;
+        excess -= added;
+        changes.push(change(Step::Neutralize, atom, "added hydrogen to anion"));
+    }
+
+    changes
+}
+
+/// Moves protons so that the strongest acids are the ones ionized. If an anion is a weaker acid
+/// than a neutral site that still has its proton, the proton is moved to the anion.
+pub fn reionize(mol: &mut Molecule) -> Vec<Change> {
+    let mut changes = vec![];
+    loop {
+        let sites: Vec<(AtomIndex, u8)> = mol
+            .atoms()
+            .filter(|a| !has_charged_neighbor(mol, *a, 1))
+            .filter_map(|a| acidity(mol, a).map(|rank| (a, rank)))
+            .collect();
+        let weakest_anion = sites
+            .iter()
+            .filter(|(a, _)| mol.atom(*a).formal_charge == -1)
+            .max_by_key(|(a, rank)| (*rank, std::cmp::Reverse(*a)));
+        let strongest_acid = sites
+            .iter()
+            .filter(|(a, _)| mol.atom(*a).formal_charge == 0 && mol.atom(*a).hydrogens > 0)
+            .min_by_key(|(a, rank)| (*rank, *a));
+
+        match (weakest_anion, strongest_acid) {
+            (Some((anion, weak)), Some((acid, strong))) if strong < weak => {
+                let (anion, acid) = (*anion, *acid);
+                let a = mol.atom_mut(anion);
+                a.hydrogens += 1;
+                a.formal_charge = 0;
+                let a = mol.atom_mut(acid);
+                a.hydrogens -= 1;
+                a.formal_charge = -1;
+                changes.push(Change {
+                    step: Step::Reionize,
+                    atoms: vec![anion, acid],
+                    description: "moved proton to the weaker acid".to_string(),
+                });
+            }
+            _ => break,
+        }
+    }
+    changes
+}
+
+fn change(step: Step, atom: AtomIndex, description: &str) -> Change {
+    Change {
+        step,
+        atoms: vec![atom],
+        description: description.to_string(),
+    }
+}
+
+fn has_charged_neighbor(mol: &Molecule, atom: AtomIndex, sign: i8) -> bool {
+    mol.neighbors(atom)
+        .any(|n| mol.atom(n).formal_charge.signum() == sign)
+}
+
+/// Ranks an acidic O or S site, with lower numbers for stronger acids: sulfonic, phosphonic,
+/// carboxylic, phenolic, then thiols. Alcohols and anything else are `None`.
+fn acidity(mol: &Molecule, atom: AtomIndex) -> Option<u8> {
+    let element = mol.atom(atom).element;
+    if !matches!(element, Element::O | Element::S) || mol.degree(atom) != 1 {
+        return None;
+    }
+    let center = mol.neighbors(atom).next()?;
+    if mol.bond(atom, center) != Some(Bond::Single) {
+        return None;
+    }
+    let double_bonded_chalcogens = mol
+        .neighbors(center)
+        .filter(|n| matches!(mol.atom(*n).element, Element::O | Element::S))
+        .filter(|n| mol.bond(center, *n) == Some(Bond::Double))
+        .count();
+
+    match mol.atom(center).element {
+        Element::S if double_bonded_chalcogens >= 2 => Some(0),
+        Element::P if double_bonded_chalcogens >= 1 => Some(1),
+        Element::C if double_bonded_chalcogens >= 1 => Some(2),
+        Element::C if mol.atom(center).aromatic => Some(3),
+        _ if element == Element::S => Some(4),
+        _ => None,
+    }
+}
@@ -0,0 +1,236 @@
+mod charge;
+mod normalize;
+mod salts;
+mod standardizer;
+
+pub use charge::*;
+pub use normalize::*;
+pub use salts::*;
+pub use standardizer::*;
+
+#[cfg(test)]
+mod tests {
+    use crate::daylight::*;
+    use crate::standardize::*;
+
+    fn smiles(s: &str) -> crate::core::Molecule {
+        molecule_from_smiles(s).expect("Invalid SMILES")
+    }
+
+    mod salts {
+        use super::*;
+
+        #[test]
+        fn can_strip_salts() {
+            // Methylamine hydrochloride
+            assert_eq!(strip_salts(&smiles("C[NH3+].[Cl-]")), smiles("C[NH3+]"));
+            assert_eq!(strip_salts(&smiles("Cl.CN")), smiles("CN"));
+            // Sodium acetate hydrate with benzylamine
+            assert_eq!(
+                strip_salts(&smiles("[Na+].CC(=O)[O-].O.NCc1ccccc1")),
+                smiles("NCc1ccccc1")
+            );
+            // Nothing to strip
+            assert_eq!(strip_salts(&smiles("CCN")), smiles("CCN"));
+            // Pyridine hydrochloride keeps the pyridine
+            assert_eq!(strip_salts(&smiles("c1ccncc1.Cl")), smiles("c1ccncc1"));
+        }
+
+        #[test]
+        fn stripping_never_leaves_nothing() {
+            assert_eq!(strip_salts(&smiles("[Na+].[Cl-]")), smiles("[Na+].[Cl-]"));
+            assert_eq!(strip_salts(&smiles("O")), smiles("O"));
+        }
+
+        #[test]
+        fn salt_library_is_extensible() {
+            let mesylate = smiles("CCN.CS(=O)(=O)O");
+            assert!(SaltRemover::default().salts().is_empty());
+            let mut remover = SaltRemover::new();
+            assert_eq!(remover.strip(&mesylate), mesylate);
+
+            remover.add_smiles("CS(=O)(=O)O").unwrap();
+            assert!(remover.is_salt(&smiles("CS(=O)(=O)O")));
+            assert_eq!(remover.strip(&mesylate), smiles("CCN"));
+
+            let mut remover = SaltRemover::standard();
+            let camsylate = "CC1(C)C2CCC1(CS(=O)(=O)O)C(=O)C2";
+            assert_eq!(
+                remover.strip(&smiles(&format!("CCN.{}", camsylate))),
+                smiles(&format!("CCN.{}", camsylate))
+            );
+            remover.add_smiles(camsylate).unwrap();
+            assert_eq!(
+                remover.strip(&smiles(&format!("CCN.{}", camsylate))),
+                smiles("CCN")
+            );
+            assert_eq!(
+                remover.add_smiles("C1CC"),
+                Err(SmilesError::UnclosedRing(1))
+            );
+        }
+    }
+
+    mod normalize {
+        use super::*;
+
+        fn normalized(s: &str) -> crate::core::Molecule {
+            let mut mol = smiles(s);
+            normalize(&mut mol);
+            mol
+        }
+
+        #[test]
+        fn can_normalize_nitro() {
+            let mut mol = smiles("CN(=O)=O");
+            let changes = normalize(&mut mol);
+            assert_eq!(mol, smiles("C[N+](=O)[O-]"));
+            assert_eq!(changes.len(), 1);
+            assert_eq!(changes[0].step, Step::Normalize);
+            assert_eq!(
+                changes[0].description,
+                "nitro group to charge separated form"
+            );
+
+            // Already normalized
+            assert!(normalize(&mut mol).is_empty());
+        }
+
+        #[test]
+        fn can_normalize_n_oxides() {
+            assert_eq!(normalized("CN(C)(C)=O"), smiles("C[N+](C)(C)[O-]"));
+            assert_eq!(normalized("O=n1ccccc1"), smiles("[O-][n+]1ccccc1"));
+        }
+
+        #[test]
+        fn can_normalize_sulfoxides() {
+            assert_eq!(normalized("C[S+](C)[O-]"), smiles("CS(C)=O"));
+            assert_eq!(normalized("C[S+2]([O-])([O-])C"), smiles("CS(=O)(=O)C"));
+            assert_eq!(normalized("C[P+](C)(C)[O-]"), smiles("CP(C)(C)=O"));
+        }
+
+        #[test]
+        fn can_normalize_azides() {
+            let azide = smiles("CN=[N+]=[N-]");
+            assert_eq!(normalized("CN=N#N"), azide);
+            assert_eq!(normalized("C[N-][N+]#N"), azide);
+            assert_eq!(normalized("CN=[N+]=[N-]"), azide);
+            assert_eq!(normalized("[CH2-][N+]#N"), smiles("C=[N+]=[N-]"));
+        }
+    }
+
+    mod charge {
+        use super::*;
+
+        fn neutralized(s: &str) -> crate::core::Molecule {
+            let mut mol = smiles(s);
+            neutralize(&mut mol);
+            mol
+        }
+
+        fn reionized(s: &str) -> crate::core::Molecule {
+            let mut mol = smiles(s);
+            reionize(&mut mol);
+            mol
+        }
+
+        #[test]
+        fn can_neutralize() {
+            assert_eq!(neutralized("C[NH3+]"), smiles("CN"));
+            assert_eq!(neutralized("CC(=O)[O-]"), smiles("CC(=O)O"));
+            // Amino acid zwitterion
+            assert_eq!(neutralized("[NH3+]CC(=O)[O-]"), smiles("NCC(=O)O"));
+            // Charge separated groups are left alone
+            assert_eq!(neutralized("C[N+](=O)[O-]"), smiles("C[N+](=O)[O-]"));
+        }
+
+        #[test]
+        fn neutralization_keeps_quaternary_charges_balanced() {
+            // Betaine keeps its carboxylate
+            assert_eq!(
+                neutralized("C[N+](C)(C)CC(=O)[O-]"),
+                smiles("C[N+](C)(C)CC(=O)[O-]")
+            );
+            // Only the phenolate is neutralized, leaving the stronger acid charged
+            assert_eq!(
+                neutralized("C[N+](C)(C)Cc1ccc([O-])cc1CC(=O)[O-]"),
+                smiles("C[N+](C)(C)Cc1ccc(O)cc1CC(=O)[O-]")
+            );
+            // Sodium can't lose a hydrogen either
+            assert_eq!(neutralized("CC(=O)[O-].[Na+]"), smiles("CC(=O)[O-].[Na+]"));
+        }
+
+        #[test]
+        fn can_reionize() {
+            // The carboxylic acid is stronger than the phenol
+            let mut mol = smiles("OC(=O)c1ccc([O-])cc1");
+            let changes = reionize(&mut mol);
+            assert_eq!(mol, smiles("[O-]C(=O)c1ccc(O)cc1"));
+            assert_eq!(changes.len(), 1);
+            assert_eq!(changes[0].step, Step::Reionize);
+
+            assert_eq!(
+                reionized("CS(=O)(=O)O.CC(=O)[O-]"),
+                smiles("CS(=O)(=O)[O-].CC(=O)O")
+            );
+            // Nothing to move
+            assert_eq!(reionized("[O-]C(=O)CCO"), smiles("[O-]C(=O)CCO"));
+        }
+    }
+
+    mod standardizer {
+        use super::*;
+
+        #[test]
+        fn can_standardize() {
+            let standardized = Standardizer::new().standardize(&smiles("C[NH3+].[Cl-].O"));
+            assert_eq!(standardized.molecule, smiles("CN"));
+            let steps: Vec<Step> = standardized.changes.iter().map(|c| c.step).collect();
+            assert_eq!(steps, vec![Step::ParentFragment, Step::Neutralize]);
+            assert_eq!(standardized.changes[0].atoms.len(), 2);
+
+            let standardized =
+                Standardizer::new().standardize(&smiles("[Na+].[O-]C(=O)c1ccc(cc1)N(=O)=O"));
+            assert_eq!(
+                standardized.molecule,
+                smiles("OC(=O)c1ccc(cc1)[N+](=O)[O-]")
+            );
+        }
+
+        #[test]
+        fn standardization_is_idempotent() {
+            let standardizer = Standardizer::new();
+            let once = standardizer
+                .standardize(&smiles("C[S+](C)[O-].CN(=O)=O.OC(=O)C(F)(F)F"))
+                .molecule;
+            let twice = standardizer.standardize(&once);
+            assert_eq!(once, twice.molecule);
+            assert!(twice.changes.is_empty());
+        }
+
+        #[test]
+        fn can_strip_isotopes_and_stereo() {
+            let mol = smiles("[13CH3][C@@H](N)C(=O)O");
+            let standardizer = Standardizer::with_steps(&[Step::StripIsotopes, Step::StripStereo]);
+            let standardized = standardizer.standardize(&mol);
+            assert_eq!(standardized.molecule, smiles("CC(N)C(=O)O"));
+            assert_eq!(standardized.changes.len(), 2);
+
+            // Kept by default
+            assert_eq!(Standardizer::new().standardize(&mol).molecule, mol);
+        }
+
+        #[test]
+        fn can_use_custom_salts() {
+            let mut salts = SaltRemover::new();
+            salts.add_smiles("CCCC").unwrap();
+            let standardizer =
+                Standardizer::with_steps(&[Step::ParentFragment]).with_salt_remover(salts);
+            // The butane is stripped even though it is the larger fragment
+            assert_eq!(
+                standardizer.standardize(&smiles("CC.CCCC")).molecule,
+                smiles("CC")
+            );
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::core::{AtomIndex, Bond, Element, Molecule};
+use crate::standardize::{Change, Step};
+
+/// Rewrites functional groups that have several common representations into one:
+///
+/// * Nitro groups and N-oxides with pentavalent nitrogen become charge separated, `[N+][O-]`.
+/// * Charge separated sulfoxides, sulfones and phosphine oxides become neutral, `S=O`.
+/// * Azides and diazo groups become `N=[N+]=[N-]`.
+pub fn normalize(mol: &mut Molecule) -> Vec<Change> {
+    let mut changes = vec![];
+    let atoms: Vec<AtomIndex> = mol.atoms().collect();
+    for atom in atoms {
+        match mol.atom(atom).element {
+            Element::N => {
+                while let Some(change) = pentavalent_nitrogen(mol, atom)
+                    .or_else(|| azide(mol, atom))
+                    .or_else(|| charged_azide(mol, atom))
+                {
+                    changes.push(change);
+                }
+            }
+            Element::S | Element::P => {
+                while let Some(change) = charge_separated_oxide(mol, atom) {
+                    changes.push(change);
+                }
+            }
+            _ => {}
+        }
+    }
+    changes
+}
+
+fn change(atoms: Vec<AtomIndex>, description: &str) -> Change {
+    Change {
+        step: Step::Normalize,
+        atoms,
+        description: description.to_string(),
+    }
+}
+
+fn is_terminal(mol: &Molecule, atom: AtomIndex, element: Element, charge: i8) -> bool {
+    let a = mol.atom(atom);
+    a.element == element && a.formal_charge == charge && a.hydrogens == 0 && mol.degree(atom) == 1
+}
+
+/// `N(=O)=O` to `[N+](=O)[O-]`, and `N=O` to `[N+][O-]` for N-oxides.
+fn pentavalent_nitrogen(mol: &mut Molecule, n: AtomIndex) -> Option<Change> {
+    if mol.atom(n).formal_charge != 0 || mol.valence(n) != 5 {
+        return None;
+    }
+    let oxygens: Vec<AtomIndex> = mol
+        .neighbors(n)
+        .filter(|o| is_terminal(mol, *o, Element::O, 0))
+        .filter(|o| mol.bond(n, *o) == Some(Bond::Double))
+        .collect();
+    let o = *oxygens.last()?;
+
+    mol.set_bond(n, o, Bond::Single);
+    mol.atom_mut(n).formal_charge = 1;
+    mol.atom_mut(o).formal_charge = -1;
+    let description = if oxygens.len() > 1 {
+        "nitro group to charge separated form"
+    } else {
+        "N-oxide to charge separated form"
+    };
+    Some(change(vec![n, o], description))
+}
+
+/// `[S+][O-]` to `S=O`, likewise for phosphorus.
+fn charge_separated_oxide(mol: &mut Molecule, atom: AtomIndex) -> Option<Change> {
+    if mol.atom(atom).formal_charge <= 0 {
+        return None;
+    }
+    let o = mol
+        .neighbors(atom)
+        .filter(|o| is_terminal(mol, *o, Element::O, -1))
+        .find(|o| mol.bond(atom, *o) == Some(Bond::Single))?;
+
+    mol.set_bond(atom, o, Bond::Double);
+    mol.atom_mut(atom).formal_charge -= 1;
+    mol.atom_mut(o).formal_charge = 0;
+    let description = match mol.atom(atom).element {
+        Element::S => "sulfoxide to neutral form",
+        _ => "phosphine oxide to neutral form",
+    };
+    Some(change(vec![atom, o], description))
+}
+
+/// `N=N#N` to `N=[N+]=[N-]`, where `n` is the middle nitrogen.
+fn azide(mol: &mut Molecule, n: AtomIndex) -> Option<Change> {
+    if mol.atom(n).formal_charge != 0 || mol.valence(n) != 5 {
+        return None;
+    }
+    let terminal = mol
+        .neighbors(n)
+        .filter(|t| is_terminal(mol, *t, Element::N, 0))
+        .find(|t| mol.bond(n, *t) == Some(Bond::Triple))?;
+
+    mol.set_bond(n, terminal, Bond::Double);
+    mol.atom_mut(n).formal_charge = 1;
+    mol.atom_mut(terminal).formal_charge = -1;
+    Some(change(vec![n, terminal], "azide to charge separated form"))
+}
+
+/// `[N-][N+]#N` to `N=[N+]=[N-]` for azides, and `[C-][N+]#N` to `C=[N+]=[N-]` for diazo groups,
+/// where `n` is the middle nitrogen.
+fn charged_azide(mol: &mut Molecule, n: AtomIndex) -> Option<Change> {
+    if mol.atom(n).formal_charge != 1 {
+        return None;
+    }
+    let terminal = mol
+        .neighbors(n)
+        .filter(|t| is_terminal(mol, *t, Element::N, 0))
+        .find(|t| mol.bond(n, *t) == Some(Bond::Triple))?;
+    let anion = mol.neighbors(n).find(|a| {
+        let atom = mol.atom(*a);
+        matches!(atom.element, Element::N | Element::C)
+            && atom.formal_charge == -1
+            && mol.bond(n, *a) == Some(Bond::Single)
+    })?;
+
+    mol.set_bond(n, anion, Bond::Double);
+    mol.set_bond(n, terminal, Bond::Double);
+    mol.atom_mut(anion).formal_charge = 0;
+    mol.atom_mut(terminal).formal_charge = -1;
+    let description = match mol.atom(anion).element {
+        Element::N => "azide to charge separated form",
+        _ => "diazo group to charge separated form",
+    };
+    Some(change(vec![anion, n, terminal], description))
+}
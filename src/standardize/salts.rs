@@ -0,0 +1,132 @@
+use crate::core::{AtomIndex, Molecule};
+use crate::daylight::{molecule_from_smiles, SmilesError};
+use std::sync::OnceLock;
+
+/// Common counter-ions and their conjugate acids or bases.
+#[rustfmt::skip]
+const COUNTER_IONS: &[&str] = &[
+    // Halides
+    "[F-]", "F", "[Cl-]", "Cl", "[Br-]", "Br", "[I-]", "I",
+    // Metals and ammonium
+    "[Li+]", "[Na+]", "[K+]", "[Mg+2]", "[Ca+2]", "[Zn+2]", "[NH4+]", "N",
+    "[OH-]",
+    // Inorganic oxyanions
+    "OS(=O)(=O)O", "OS(=O)(=O)[O-]", "[O-]S(=O)(=O)[O-]",
+    "OP(=O)(O)O", "OP(=O)(O)[O-]", "OP(=O)([O-])[O-]", "[O-]P(=O)([O-])[O-]",
+    "O[N+](=O)[O-]", "[O-][N+](=O)[O-]",
+    "OCl(=O)(=O)=O", "[O-]Cl(=O)(=O)=O",
+    "OC(=O)O", "OC(=O)[O-]", "[O-]C(=O)[O-]",
+    "F[B-](F)(F)F", "F[P-](F)(F)(F)(F)F",
+    // Sulfonates
+    "CS(=O)(=O)O", "CS(=O)(=O)[O-]",
+    "OS(=O)(=O)C(F)(F)F", "[O-]S(=O)(=O)C(F)(F)F",
+    "Cc1ccc(cc1)S(=O)(=O)O", "Cc1ccc(cc1)S(=O)(=O)[O-]",
+    "OS(=O)(=O)c1ccccc1", "[O-]S(=O)(=O)c1ccccc1",
+    // Carboxylates
+    "OC=O", "[O-]C=O",
+    "CC(=O)O", "CC(=O)[O-]",
+    "OC(=O)C(F)(F)F", "[O-]C(=O)C(F)(F)F",
+    "OC(=O)C(=O)O", "OC(=O)C(=O)[O-]", "[O-]C(=O)C(=O)[O-]",
+    "OC(=O)C=CC(=O)O", "OC(=O)C=CC(=O)[O-]",
+    "OC(=O)CCC(=O)O", "OC(=O)CCC(=O)[O-]",
+    "OC(=O)C(O)C(O)C(=O)O", "OC(=O)C(O)C(O)C(=O)[O-]",
+    "OC(=O)CC(O)(CC(=O)O)C(=O)O",
+    "OC(=O)c1ccccc1", "[O-]C(=O)c1ccccc1",
+];
+
+/// Common crystallization and reaction solvents. Aromatic solvents and bases such as benzene,
+/// pyridine and triethylamine are left out, as they are as likely to be the compound of interest.
+#[rustfmt::skip]
+const SOLVENTS: &[&str] = &[
+    "O", "CO", "CCO", "CCCO", "CC(C)O", "CCCCO",
+    "CC(C)=O", "CCOCC", "C1CCOC1", "C1COCCO1", "COCCOC", "CCOC(C)=O",
+    "CS(C)=O", "CN(C)C=O", "CC(=O)N(C)C", "CC#N",
+    "ClCCl", "ClC(Cl)Cl", "ClCCCl",
+    "CCCCC", "CCCCCC", "CCCCCCC", "C1CCCCC1",
+];
+
+/// Removes fragments that are known counter-ions or solvents. Fragments are matched against the
+/// library by exact graph isomorphism, including charges and hydrogen counts, so a library entry
+/// is needed for each protonation state to be stripped.
+#[derive(Clone, Debug)]
+pub struct SaltRemover {
+    salts: Vec<Molecule>,
+}
+
+impl SaltRemover {
+    /// A remover with an empty library.
+    pub fn new() -> Self {
+        SaltRemover { salts: vec![] }
+    }
+
+    /// A remover with the built in library of counter-ions and solvents, which is parsed once and
+    /// copied.
+    pub fn standard() -> Self {
+        standard_library().clone()
+    }
+
+    pub fn add(&mut self, salt: Molecule) {
+        self.salts.push(salt);
+    }
+
+    pub fn add_smiles(&mut self, smiles: &str) -> Result<(), SmilesError> {
+        self.add(molecule_from_smiles(smiles)?);
+        Ok(())
+    }
+
+    pub fn salts(&self) -> &[Molecule] {
+        &self.salts
+    }
+
+    pub fn is_salt(&self, fragment: &Molecule) -> bool {
+        self.salts.iter().any(|salt| salt == fragment)
+    }
+
+    /// The atoms of every fragment that is not in the library, in ascending order. If every
+    /// fragment is in the library, all atoms are kept.
+    pub fn kept_atoms(&self, mol: &Molecule) -> Vec<AtomIndex> {
+        let mut kept: Vec<AtomIndex> = mol
+            .components()
+            .into_iter()
+            .filter(|atoms| !self.is_salt(&mol.subgraph(atoms)))
+            .flatten()
+            .collect();
+        if kept.is_empty() {
+            mol.atoms().collect()
+        } else {
+            kept.sort();
+            kept
+        }
+    }
+
+    /// The molecule without any fragments in the library. If every fragment would be removed, the
+    /// molecule is returned unchanged.
+    pub fn strip(&self, mol: &Molecule) -> Molecule {
+        mol.subgraph(&self.kept_atoms(mol))
+    }
+}
+
+impl Default for SaltRemover {
+    fn default() -> Self {
+        SaltRemover::new()
+    }
+}
+
+/// Strips salts using the standard library. See [`SaltRemover::strip`].
+pub fn strip_salts(mol: &Molecule) -> Molecule {
+    standard_library().strip(mol)
+}
+
+/// The built in library, parsed on first use.
+fn standard_library() -> &'static SaltRemover {
+    static STANDARD: OnceLock<SaltRemover> = OnceLock::new();
+    STANDARD.get_or_init(|| {
+        let mut remover = SaltRemover::new();
+        for smiles in COUNTER_IONS.iter().chain(SOLVENTS) {
+            remover
+                .add_smiles(smiles)
+                .expect("Invalid built in salt SMILES");
+        }
+        remover
+    })
+}
@@ -0,0 +1,155 @@
+use crate::core::{AtomIndex, Molecule};
+use crate::standardize::{neutralize, normalize, reionize, SaltRemover};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Step {
+    /// See [`normalize`].
+    Normalize,
+    /// See [`neutralize`].
+    Neutralize,
+    /// See [`reionize`].
+    Reionize,
+    StripIsotopes,
+    /// Removes tetrahedral chirality from every atom.
+    StripStereo,
+    /// Strips salts and solvents, then keeps the largest remaining fragment.
+    ParentFragment,
+}
+
+/// A single modification made by a standardization step. The atoms are indices into the molecule
+/// as it was when the step ran.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Change {
+    pub step: Step,
+    pub atoms: Vec<AtomIndex>,
+    pub description: String,
+}
+
+/// The standardized molecule with a log of every change made to reach it.
+#[derive(Clone, Debug)]
+pub struct Standardized {
+    pub molecule: Molecule,
+    pub changes: Vec<Change>,
+}
+
+/// Runs an ordered list of steps to bring a molecule into a single consistent form.
+#[derive(Clone, Debug)]
+pub struct Standardizer {
+    steps: Vec<Step>,
+    salts: SaltRemover,
+}
+
+impl Standardizer {
+    /// The default pipeline: normalize, reionize, pick the parent fragment, then neutralize.
+    /// Isotopes and stereochemistry are kept.
+    pub fn new() -> Self {
+        Standardizer::with_steps(&[
+            Step::Normalize,
+            Step::Reionize,
+            Step::ParentFragment,
+            Step::Neutralize,
+        ])
+    }
+
+    pub fn with_steps(steps: &[Step]) -> Self {
+        Standardizer {
+            steps: steps.to_vec(),
+            salts: SaltRemover::standard(),
+        }
+    }
+
+    /// Replaces the salt library used when picking the parent fragment.
+    pub fn with_salt_remover(mut self, salts: SaltRemover) -> Self {
+        self.salts = salts;
+        self
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn standardize(&self, mol: &Molecule) -> Standardized {
+        let mut mol = mol.clone();
+        let mut changes = vec![];
+        for step in &self.steps {
+            match step {
+                Step::Normalize => changes.extend(normalize(&mut mol)),
+                Step::Neutralize => changes.extend(neutralize(&mut mol)),
+                Step::Reionize => changes.extend(reionize(&mut mol)),
+                Step::StripIsotopes => changes.extend(strip_isotopes(&mut mol)),
+                Step::StripStereo => changes.extend(strip_stereo(&mut mol)),
+                Step::ParentFragment => {
+                    let (parent, change) = self.parent_fragment(&mol);
+                    mol = parent;
+                    changes.extend(change);
+                }
+            }
+        }
+        Standardized {
+            molecule: mol,
+            changes,
+        }
+    }
+
+    fn parent_fragment(&self, mol: &Molecule) -> (Molecule, Option<Change>) {
+        let kept = self.salts.kept_atoms(mol);
+        let parent: Vec<AtomIndex> = match mol.subgraph(&kept).largest_component() {
+            Some(atoms) => atoms.iter().map(|a| kept[a.index()]).collect(),
+            None => return (mol.clone(), None),
+        };
+        let removed: Vec<AtomIndex> = mol.atoms().filter(|a| !parent.contains(a)).collect();
+        if removed.is_empty() {
+            return (mol.clone(), None);
+        }
+
+        let fragments = mol.components().len() - 1;
+        let change = Change {
+            step: Step::ParentFragment,
+            atoms: removed,
+            description: format!("removed {} fragment(s)", fragments),
+        };
+        (mol.subgraph(&parent), Some(change))
+    }
+}
+
+impl Default for Standardizer {
+    fn default() -> Self {
+        Standardizer::new()
+    }
+}
+
+fn strip_isotopes(mol: &mut Molecule) -> Vec<Change> {
+    let atoms: Vec<AtomIndex> = mol
+        .atoms()
+        .filter(|a| mol.atom(*a).isotope.is_some())
+        .collect();
+    for atom in &atoms {
+        mol.atom_mut(*atom).isotope = None;
+    }
+    atoms
+        .into_iter()
+        .map(|atom| Change {
+            step: Step::StripIsotopes,
+            atoms: vec![atom],
+            description: "removed isotope label".to_string(),
+        })
+        .collect()
+}
+
+fn strip_stereo(mol: &mut Molecule) -> Vec<Change> {
+    let atoms: Vec<AtomIndex> = mol
+        .atoms()
+        .filter(|a| mol.atom(*a).chirality.is_some())
+        .collect();
+    for atom in &atoms {
+        mol.atom_mut(*atom).chirality = None;
+    }
+    atoms
+        .into_iter()
+        .map(|atom| Change {
+            step: Step::StripStereo,
+            atoms: vec![atom],
+            description: "removed chirality".to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,451 @@
+use crate::core::{Atom, AtomIndex, Bond, Element, Molecule, RingInfo};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum AtomComparison {
+    /// Any atom matches any other.
+    Any,
+    /// Atoms match if they are the same element.
+    Element,
+    /// Atoms match if they are the same element with the same isotope label.
+    Isotope,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum BondComparison {
+    /// Any bond matches any other.
+    Any,
+    /// Bonds match if they have the same order.
+    Order,
+}
+
+/// A maximum common substructure of two molecules.
+#[derive(Clone, Debug)]
+pub struct Mcs {
+    /// The common substructure, with atoms and bonds taken from the first molecule. Atom `i`
+    /// corresponds to `mapping[i]`.
+    pub molecule: Molecule,
+    /// Pairs of matched atoms from the first and second molecule, in ascending order of the first.
+    pub mapping: Vec<(AtomIndex, AtomIndex)>,
+    /// Whether the search stopped at its timeout, in which case the result may not be maximum.
+    pub timed_out: bool,
+}
+
+impl Mcs {
+    pub fn bond_count(&self) -> usize {
+        self.molecule.bond_count()
+    }
+
+    pub fn atom_count(&self) -> usize {
+        self.molecule.atom_count()
+    }
+}
+
+/// Finds the largest connected substructure common to two molecules, measured by bonds, then by
+/// atoms. A bond between two matched atoms is part of the substructure only if it is present and
+/// matches in both molecules.
+#[derive(Clone, Debug)]
+pub struct McsSearch {
+    atoms: AtomComparison,
+    bonds: BondComparison,
+    ring_matches_ring: bool,
+    complete_rings: bool,
+    timeout: Option<Duration>,
+}
+
+impl McsSearch {
+    /// Compares elements and bond orders, with no ring constraints or timeout.
+    pub fn new() -> Self {
+        McsSearch {
+            atoms: AtomComparison::Element,
+            bonds: BondComparison::Order,
+            ring_matches_ring: false,
+            complete_rings: false,
+            timeout: None,
+        }
+    }
+
+    pub fn atoms(mut self, atoms: AtomComparison) -> Self {
+        self.atoms = atoms;
+        self
+    }
+
+    pub fn bonds(mut self, bonds: BondComparison) -> Self {
+        self.bonds = bonds;
+        self
+    }
+
+    /// Ring bonds only match ring bonds, and chain bonds only chain bonds.
+    pub fn ring_matches_ring(mut self, ring_matches_ring: bool) -> Self {
+        self.ring_matches_ring = ring_matches_ring;
+        self
+    }
+
+    /// Every ring bond in the result must be part of a ring that is matched completely in both
+    /// molecules.
+    pub fn complete_rings(mut self, complete_rings: bool) -> Self {
+        self.complete_rings = complete_rings;
+        self
+    }
+
+    /// Stops searching after this long and returns the best substructure found so far.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn find(&self, a: &Molecule, b: &Molecule) -> Mcs {
+        let mut labels = HashMap::new();
+        let (a_side, b_side) = (
+            Side::new(a, self, &mut labels),
+            Side::new(b, self, &mut labels),
+        );
+        let mut state = State {
+            search: self,
+            a: a_side,
+            b: b_side,
+            labels: labels.len(),
+            mapping: vec![None; a.atom_count()],
+            used: vec![false; b.atom_count()],
+            excluded: vec![false; a.atom_count()],
+            order: vec![],
+            bonds: 0,
+            best: None,
+            deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+            timed_out: false,
+        };
+
+        // Every substructure containing a root is found from it, so later roots can skip it
+        for root in a.atoms() {
+            for candidate in b.atoms() {
+                if state.atoms_match(root, candidate) {
+                    state.map(root, candidate);
+                    state.extend();
+                    state.unmap(root, candidate);
+                }
+            }
+            state.excluded[root.index()] = true;
+        }
+
+        let mapping = state.best.take().map(|(_, m)| m).unwrap_or_default();
+        Mcs {
+            molecule: state.common_substructure(&mapping),
+            mapping,
+            timed_out: state.timed_out,
+        }
+    }
+}
+
+impl Default for McsSearch {
+    fn default() -> Self {
+        McsSearch::new()
+    }
+}
+
+impl Molecule {
+    /// The maximum common substructure with this molecule, comparing elements and bond orders.
+    /// See [`McsSearch`] for more options.
+    pub fn mcs(&self, other: &Molecule) -> Mcs {
+        McsSearch::new().find(self, other)
+    }
+}
+
+type Mapping = Vec<(AtomIndex, AtomIndex)>;
+
+type AtomLabel = Option<(Element, Option<u16>)>;
+/// Atom labels in order, with the bond's order and ring membership when those are compared.
+type BondLabel = ([AtomLabel; 2], Option<Bond>, Option<bool>);
+
+struct Side<'m> {
+    mol: &'m Molecule,
+    rings: RingInfo,
+    /// Each bond with its label, which it shares with every bond it could match.
+    bonds: Vec<(AtomIndex, AtomIndex, usize)>,
+}
+
+impl<'m> Side<'m> {
+    /// Numbers bond labels in `labels`, which is shared with the other side.
+    fn new(mol: &'m Molecule, search: &McsSearch, labels: &mut HashMap<BondLabel, usize>) -> Self {
+        let rings = mol.rings();
+        let atom = |a: AtomIndex| {
+            let atom = mol.atom(a);
+            match search.atoms {
+                AtomComparison::Any => None,
+                AtomComparison::Element => Some((atom.element, None)),
+                AtomComparison::Isotope => Some((atom.element, atom.isotope)),
+            }
+        };
+        let bonds = mol
+            .bonds()
+            .map(|(x, y, bond)| {
+                let (p, q) = (atom(x), atom(y));
+                let order = match search.bonds {
+                    BondComparison::Any => None,
+                    BondComparison::Order => Some(bond),
+                };
+                let ring = if search.ring_matches_ring {
+                    Some(rings.bond_in_ring(x, y))
+                } else {
+                    None
+                };
+                let next = labels.len();
+                let label = *labels
+                    .entry(([p.min(q), p.max(q)], order, ring))
+                    .or_insert(next);
+                (x, y, label)
+            })
+            .collect();
+        Side { mol, rings, bonds }
+    }
+}
+
+/// Grows a connected mapping one atom pair at a time. Frontier atoms of the first molecule are
+/// decided in ascending order, either mapped or excluded for the rest of the branch, so that
+/// each mapping is visited once.
+struct State<'s, 'm> {
+    search: &'s McsSearch,
+    a: Side<'m>,
+    b: Side<'m>,
+    /// The number of distinct bond labels.
+    labels: usize,
+    mapping: Vec<Option<AtomIndex>>,
+    used: Vec<bool>,
+    excluded: Vec<bool>,
+    /// Mapped atoms of the first molecule, in the order they were mapped.
+    order: Vec<AtomIndex>,
+    bonds: usize,
+    /// The best mapping so far, with its bond and atom counts.
+    best: Option<((usize, usize), Mapping)>,
+    deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+impl<'s, 'm> State<'s, 'm> {
+    fn atoms_match(&self, a: AtomIndex, b: AtomIndex) -> bool {
+        let (x, y): (&Atom, &Atom) = (self.a.mol.atom(a), self.b.mol.atom(b));
+        match self.search.atoms {
+            AtomComparison::Any => true,
+            AtomComparison::Element => x.element == y.element,
+            AtomComparison::Isotope => x.element == y.element && x.isotope == y.isotope,
+        }
+    }
+
+    /// Whether the bond between two first molecule atoms matches the one between the atoms they
+    /// are mapped to. Missing bonds never match.
+    fn bonds_match(
+        &self,
+        (a1, a2): (AtomIndex, AtomIndex),
+        (b1, b2): (AtomIndex, AtomIndex),
+    ) -> bool {
+        let (x, y): (Bond, Bond) = match (self.a.mol.bond(a1, a2), self.b.mol.bond(b1, b2)) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return false,
+        };
+        let order = match self.search.bonds {
+            BondComparison::Any => true,
+            BondComparison::Order => x == y,
+        };
+        let rings = !self.search.ring_matches_ring
+            || self.a.rings.bond_in_ring(a1, a2) == self.b.rings.bond_in_ring(b1, b2);
+        order && rings
+    }
+
+    /// Common bonds between `a`, mapped to `b`, and the atoms mapped before it.
+    fn new_bonds(&self, a: AtomIndex, b: AtomIndex) -> usize {
+        self.a
+            .mol
+            .neighbors(a)
+            .filter_map(|n| self.mapping[n.index()].map(|m| (n, m)))
+            .filter(|(n, m)| self.bonds_match((a, *n), (b, *m)))
+            .count()
+    }
+
+    fn map(&mut self, a: AtomIndex, b: AtomIndex) {
+        self.bonds += self.new_bonds(a, b);
+        self.mapping[a.index()] = Some(b);
+        self.used[b.index()] = true;
+        self.order.push(a);
+    }
+
+    fn unmap(&mut self, a: AtomIndex, b: AtomIndex) {
+        self.order.pop();
+        self.used[b.index()] = false;
+        self.mapping[a.index()] = None;
+        self.bonds -= self.new_bonds(a, b);
+    }
+
+    fn timed_out(&mut self) -> bool {
+        if let Some(deadline) = self.deadline {
+            if !self.timed_out && Instant::now() >= deadline {
+                self.timed_out = true;
+            }
+        }
+        self.timed_out
+    }
+
+    fn extend(&mut self) {
+        if self.timed_out() {
+            return;
+        }
+        self.record();
+        if matches!(&self.best, Some(((bonds, _), _)) if self.bound() < *bonds) {
+            return;
+        }
+
+        // The lowest frontier atom is either mapped to a neighbour of the mapped atoms, or left
+        // out of this branch entirely
+        let frontier = self
+            .order
+            .iter()
+            .flat_map(|a| self.a.mol.neighbors(*a))
+            .filter(|n| self.mapping[n.index()].is_none() && !self.excluded[n.index()])
+            .min();
+        let next = match frontier {
+            Some(next) => next,
+            None => return,
+        };
+
+        let mut candidates: Vec<AtomIndex> = self
+            .a
+            .mol
+            .neighbors(next)
+            .filter_map(|n| self.mapping[n.index()])
+            .flat_map(|m| self.b.mol.neighbors(m))
+            .filter(|c| !self.used[c.index()] && self.atoms_match(next, *c))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        for candidate in candidates {
+            if self.new_bonds(next, candidate) == 0 {
+                continue;
+            }
+            self.map(next, candidate);
+            self.extend();
+            self.unmap(next, candidate);
+        }
+
+        self.excluded[next.index()] = true;
+        self.extend();
+        self.excluded[next.index()] = false;
+    }
+
+    /// The most bonds any extension of the current mapping could have. Bonds between mapped atoms
+    /// that did not match can never be added, nor can bonds to excluded atoms, and each remaining
+    /// bond needs a remaining bond with the same label in the other molecule.
+    fn bound(&self) -> usize {
+        let mapped_a = |x: AtomIndex| self.mapping[x.index()].is_some();
+        let mapped_b = |x: AtomIndex| self.used[x.index()];
+        let excluded = |x: AtomIndex| self.excluded[x.index()];
+
+        let mut open_a = vec![0; self.labels];
+        for (_, _, label) in self
+            .a
+            .bonds
+            .iter()
+            .filter(|(x, y, _)| !(mapped_a(*x) && mapped_a(*y)))
+            .filter(|(x, y, _)| !excluded(*x) && !excluded(*y))
+        {
+            open_a[*label] += 1;
+        }
+        let mut common = 0;
+        for (_, _, label) in self
+            .b
+            .bonds
+            .iter()
+            .filter(|(x, y, _)| !(mapped_b(*x) && mapped_b(*y)))
+        {
+            if open_a[*label] > 0 {
+                open_a[*label] -= 1;
+                common += 1;
+            }
+        }
+        self.bonds + common
+    }
+
+    fn record(&mut self) {
+        let score = (self.bonds, self.order.len());
+        if matches!(&self.best, Some((best, _)) if score <= *best) {
+            return;
+        }
+        let mut mapping: Vec<(AtomIndex, AtomIndex)> = self
+            .order
+            .iter()
+            .map(|a| (*a, self.mapping[a.index()].unwrap()))
+            .collect();
+        mapping.sort();
+        if self.search.complete_rings && !self.rings_complete(&mapping) {
+            return;
+        }
+        self.best = Some((score, mapping));
+    }
+
+    /// Pairs of mapped bonds that match, as pairs of first molecule atoms.
+    fn common_bond_pairs(&self, mapping: &[(AtomIndex, AtomIndex)]) -> Vec<(AtomIndex, AtomIndex)> {
+        let mut pairs = vec![];
+        for (i, (a1, b1)) in mapping.iter().enumerate() {
+            for (a2, b2) in &mapping[i + 1..] {
+                if self.bonds_match((*a1, *a2), (*b1, *b2)) {
+                    pairs.push((*a1, *a2));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Whether every common ring bond is part of a ring whose bonds are all common, in both
+    /// molecules.
+    fn rings_complete(&self, mapping: &[(AtomIndex, AtomIndex)]) -> bool {
+        let pairs = self.common_bond_pairs(mapping);
+        let to_b = |a: AtomIndex| mapping.iter().find(|(x, _)| *x == a).map(|(_, y)| *y);
+        let common_a = |x: AtomIndex, y: AtomIndex| {
+            pairs
+                .iter()
+                .any(|(p, q)| (*p, *q) == (x, y) || (*q, *p) == (x, y))
+        };
+        let common_b = |x: AtomIndex, y: AtomIndex| {
+            pairs.iter().any(|(p, q)| {
+                let (p, q) = (to_b(*p).unwrap(), to_b(*q).unwrap());
+                (p, q) == (x, y) || (q, p) == (x, y)
+            })
+        };
+
+        pairs.iter().all(|(x, y)| {
+            let in_a = !self.a.rings.bond_in_ring(*x, *y)
+                || self
+                    .a
+                    .rings
+                    .relevant_cycles()
+                    .iter()
+                    .filter(|ring| ring.contains_bond(*x, *y))
+                    .any(|ring| ring.bonds().all(|(p, q)| common_a(p, q)));
+            let (bx, by) = (to_b(*x).unwrap(), to_b(*y).unwrap());
+            let in_b = !self.b.rings.bond_in_ring(bx, by)
+                || self
+                    .b
+                    .rings
+                    .relevant_cycles()
+                    .iter()
+                    .filter(|ring| ring.contains_bond(bx, by))
+                    .any(|ring| ring.bonds().all(|(p, q)| common_b(p, q)));
+            in_a && in_b
+        })
+    }
+
+    fn common_substructure(&self, mapping: &[(AtomIndex, AtomIndex)]) -> Molecule {
+        let mut mol = Molecule::new();
+        for (a, _) in mapping {
+            mol.add_atom(Atom {
+                chirality: None,
+                ..*self.a.mol.atom(*a)
+            });
+        }
+        let index =
+            |a: AtomIndex| AtomIndex::new(mapping.iter().position(|(x, _)| *x == a).unwrap());
+        for (x, y) in self.common_bond_pairs(mapping) {
+            let bond = self.a.mol.bond(x, y).unwrap();
+            mol.add_bond(index(x), index(y), bond).unwrap();
+        }
+        mol
+    }
+}
@@ -0,0 +1,260 @@
+mod mcs;
+mod search;
+mod target;
+
+pub use mcs::*;
+pub use search::*;
+pub use target::Target;
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{AtomIndex, Molecule};
+    use crate::daylight::*;
+    use crate::substructure::*;
+
+    fn smiles(s: &str) -> Molecule {
+        molecule_from_smiles(s).expect("Invalid SMILES")
+    }
+
+    fn smarts(s: &str) -> Query {
+        query_from_smarts(s).expect("Invalid SMARTS")
+    }
+
+    fn atoms(indices: &[usize]) -> Vec<AtomIndex> {
+        indices.iter().map(|i| AtomIndex::new(*i)).collect()
+    }
+
+    fn count(mol: &str, query: &str) -> usize {
+        smiles(mol).substructure_matches(&smarts(query)).count()
+    }
+
+    fn unique_count(mol: &str, query: &str) -> usize {
+        let (mol, query) = (smiles(mol), smarts(query));
+        let search = SubstructureSearch::new(&query).unique(true);
+        let count = search.matches(&mol).count();
+        count
+    }
+
+    #[test]
+    fn can_find_matches() {
+        let mol = smiles("CC(=O)OCC");
+        let matches: Vec<Vec<AtomIndex>> = mol.substructure_matches(&smarts("C(=O)O")).collect();
+        assert_eq!(matches, vec![atoms(&[1, 2, 3])]);
+
+        // Matches are ordered by query atom, not molecule atom
+        let matches: Vec<Vec<AtomIndex>> = mol.substructure_matches(&smarts("O=C")).collect();
+        assert_eq!(matches, vec![atoms(&[2, 1])]);
+
+        assert!(mol.has_substructure(&smarts("COC")));
+        assert!(!mol.has_substructure(&smarts("C#C")));
+        assert!(!smiles("CC").has_substructure(&smarts("CCC")));
+        // Disconnected queries can match anywhere in the molecule
+        assert_eq!(count("CCO.O", "O.O"), 2);
+    }
+
+    #[test]
+    fn can_match_primitives() {
+        // Ethanol's oxygen, not the ether's
+        assert_eq!(count("CCO.COC", "[OX2H1]"), 1);
+        assert_eq!(count("c1ccccc1C", "[c;H1]"), 5);
+        assert_eq!(count("c1ccccc1C", "[#6;!R]"), 1);
+        assert_eq!(count("C1CC1C1CCCC1", "[r5]"), 5);
+        assert_eq!(count("C1CCC2CCCCC2C1", "[R2]"), 2);
+        assert_eq!(count("C1CCC2CCCCC2C1", "[x3]"), 2);
+        assert_eq!(count("CC(=O)[O-].[Na+]", "[+,-]"), 2);
+        assert_eq!(count("[13CH4].C", "[13C]"), 1);
+        assert_eq!(count("CC=O", "[v4]"), 2);
+        assert_eq!(count("CC(C)(C)C", "[D4]"), 1);
+        assert_eq!(count("OCC(=O)O", "[$(C=O)]"), 1);
+        assert_eq!(count("OCC(=O)O", "[O;$(OC=O)]"), 1);
+        assert_eq!(count("OCC(=O)O", "[O;$(O~C~O)]"), 2);
+        assert_eq!(count("OCC(=O)O", "[O;!$(OC=O)]"), 2);
+    }
+
+    #[test]
+    fn can_match_bonds() {
+        assert_eq!(count("c1ccccc1CC", "cc"), 12);
+        assert_eq!(count("c1ccccc1CC", "C-C"), 2);
+        assert_eq!(count("c1ccccc1CC", "*!@*"), 4);
+        assert_eq!(count("c1ccccc1CC", "*@*"), 12);
+        assert_eq!(count("C=CC#N", "*=,#*"), 4);
+        assert_eq!(count("C=CC#N", "*~*"), 6);
+    }
+
+    #[test]
+    fn can_limit_and_filter_matches() {
+        assert_eq!(count("c1ccccc1", "c1ccccc1"), 12);
+        assert_eq!(unique_count("c1ccccc1", "c1ccccc1"), 1);
+        assert_eq!(unique_count("CC(C)C", "C(C)C"), 3);
+
+        let query = smarts("cc");
+        let mol = smiles("c1ccccc1");
+        assert_eq!(
+            SubstructureSearch::new(&query)
+                .limit(3)
+                .matches(&mol)
+                .count(),
+            3
+        );
+        assert_eq!(
+            SubstructureSearch::new(&query)
+                .limit(0)
+                .matches(&mol)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn can_use_custom_predicates() {
+        let query = smarts("CO");
+        let mol = smiles("OCCO");
+        let search = SubstructureSearch::new(&query).atom_predicate(|mol, atom, _| {
+            mol.atom(atom).element != crate::core::Element::C || atom.index() == 1
+        });
+        assert_eq!(
+            search.matches(&mol).collect::<Vec<_>>(),
+            vec![atoms(&[1, 0])]
+        );
+
+        let search = SubstructureSearch::new(&query).bond_predicate(|_, (a, _), _| a.index() > 1);
+        assert_eq!(
+            search.matches(&mol).collect::<Vec<_>>(),
+            vec![atoms(&[2, 3])]
+        );
+    }
+
+    #[test]
+    fn can_search_prepared_targets() {
+        let mol = smiles("c1ccccc1CC#N");
+        let target = Target::new(&mol);
+        let nitrile = smarts("C#N");
+        let ring = smarts("[R]!@[R0]");
+        assert!(SubstructureSearch::new(&nitrile).has_target_match(&target));
+        assert_eq!(
+            SubstructureSearch::new(&ring)
+                .target_matches(&target)
+                .collect::<Vec<_>>(),
+            vec![atoms(&[5, 6])]
+        );
+        assert!(!SubstructureSearch::new(&smarts("[r3]")).has_target_match(&target));
+        assert_eq!(target.molecule(), &mol);
+    }
+
+    #[test]
+    fn can_use_molecules_as_queries() {
+        let query = Query::from(&smiles("C(=O)[O-]"));
+        assert!(smiles("CC(=O)[O-].[Na+]").has_substructure(&query));
+        assert!(!smiles("CC(=O)O").has_substructure(&query));
+        // Hydrogen counts are not compared
+        assert!(smiles("CC(C)C").has_substructure(&Query::from(&smiles("CC"))));
+        // Aromaticity is
+        assert!(!smiles("c1ccccc1").has_substructure(&Query::from(&smiles("CC"))));
+    }
+
+    #[test]
+    fn can_match_chirality() {
+        let query = smarts("N[C@@H](C)C(=O)O");
+        assert!(smiles("N[C@@H](C)C(=O)O").has_substructure(&query));
+        assert!(smiles("N[C@H](C(=O)O)C").has_substructure(&query));
+        assert!(!smiles("N[C@H](C)C(=O)O").has_substructure(&query));
+        assert!(!smiles("NC(C)C(=O)O").has_substructure(&query));
+
+        // Without the hydrogen, an unmatched neighbour takes its place
+        let query = smarts("N[C@@](C)C(=O)O");
+        assert!(smiles("N[C@@H](C)C(=O)O").has_substructure(&query));
+        assert!(smiles("N[C@@](CC)(C)C(=O)O").has_substructure(&query));
+
+        let query = smarts("N[C@H](C)C(=O)O");
+        let mol = smiles("N[C@@H](C)C(=O)O");
+        let search = SubstructureSearch::new(&query).chirality(false);
+        assert!(search.has_match(&mol));
+        assert!(!mol.has_substructure(&query));
+        // A negated centre matches the other configuration and atoms without one
+        let query = smarts("N[C;!@@](C)C(=O)O");
+        assert!(!smiles("N[C@@](Br)(C)C(=O)O").has_substructure(&query));
+        assert!(smiles("N[C@](Br)(C)C(=O)O").has_substructure(&query));
+        assert!(smiles("NC(Br)(C)C(=O)O").has_substructure(&query));
+        // Only a hydrogen the atom must have is a neighbour
+        let query = smarts("F[C@@;!H1](Cl)(Br)I");
+        assert!(smiles("F[C@@](Cl)(Br)I").has_substructure(&query));
+        assert!(!smiles("F[C@](Cl)(Br)I").has_substructure(&query));
+        let query = smarts("N[C@@;H1,H0](C)C(=O)O");
+        assert!(smiles("N[C@@](Br)(C)C(=O)O").has_substructure(&query));
+
+        // Queries from molecules keep their chirality
+        assert!(!mol.has_substructure(&Query::from(&smiles("N[C@H](C)C(=O)O"))));
+        assert!(mol.has_substructure(&Query::from(&mol)));
+    }
+
+    #[test]
+    fn can_find_mcs() {
+        // Propyl benzoate and ethyl benzoate share everything but a carbon
+        let mcs = smiles("CCCOC(=O)c1ccccc1").mcs(&smiles("CCOC(=O)c1ccccc1"));
+        assert_eq!(mcs.atom_count(), 11);
+        assert_eq!(mcs.bond_count(), 11);
+        assert!(!mcs.timed_out);
+        assert!(smiles("CCOC(=O)c1ccccc1").has_substructure(&Query::from(&mcs.molecule)));
+
+        // The mapping pairs atoms of the same element
+        let (a, b) = (smiles("OCC"), smiles("CCO"));
+        let mcs = a.mcs(&b);
+        assert_eq!(
+            mcs.mapping,
+            vec![
+                (AtomIndex::new(0), AtomIndex::new(2)),
+                (AtomIndex::new(1), AtomIndex::new(1)),
+                (AtomIndex::new(2), AtomIndex::new(0))
+            ]
+        );
+
+        assert_eq!(smiles("C").mcs(&smiles("O")).atom_count(), 0);
+        assert_eq!(smiles("C").mcs(&smiles("CO")).atom_count(), 1);
+    }
+
+    #[test]
+    fn can_compare_atoms_and_bonds_loosely() {
+        let (a, b) = (smiles("CCCC"), smiles("CCOC"));
+        assert_eq!(a.mcs(&b).bond_count(), 1);
+        let search = McsSearch::new().atoms(AtomComparison::Any);
+        assert_eq!(search.find(&a, &b).bond_count(), 3);
+
+        let (a, b) = (smiles("C=CC=C"), smiles("CCCC"));
+        assert_eq!(a.mcs(&b).bond_count(), 1);
+        let search = McsSearch::new().bonds(BondComparison::Any);
+        assert_eq!(search.find(&a, &b).bond_count(), 3);
+
+        let (a, b) = (smiles("[13CH3]C"), smiles("CC"));
+        let search = McsSearch::new().atoms(AtomComparison::Isotope);
+        assert_eq!(search.find(&a, &b).atom_count(), 1);
+    }
+
+    #[test]
+    fn can_constrain_rings() {
+        // Hexane fits along cyclohexane's ring unless ring bonds only match ring bonds
+        let (a, b) = (smiles("CCCCCC"), smiles("C1CCCCC1"));
+        assert_eq!(a.mcs(&b).bond_count(), 5);
+        let search = McsSearch::new().ring_matches_ring(true);
+        assert_eq!(search.find(&a, &b).bond_count(), 0);
+
+        // Methylcyclohexane and methylcyclopentane share a ring fragment, but no whole ring
+        let (a, b) = (smiles("CC1CCCCC1"), smiles("CC1CCCC1"));
+        assert_eq!(a.mcs(&b).bond_count(), 5);
+        let search = McsSearch::new().complete_rings(true);
+        assert_eq!(search.find(&a, &b).bond_count(), 1);
+        let search = McsSearch::new().complete_rings(true);
+        assert_eq!(
+            search
+                .find(&smiles("CCc1ccccc1"), &smiles("c1ccccc1CO"))
+                .bond_count(),
+            7
+        );
+    }
+
+    #[test]
+    fn can_time_out_mcs() {
+        let search = McsSearch::new().timeout(std::time::Duration::from_secs(0));
+        let mcs = search.find(&smiles("CCCCCCCC"), &smiles("CCCCCCCC"));
+        assert!(mcs.timed_out);
+    }
+}
@@ -0,0 +1,380 @@
+use crate::core::{AtomIndex, Molecule};
+use crate::daylight::{AtomPrimitive, Query};
+use crate::substructure::target::Target;
+use std::collections::{HashSet, VecDeque};
+
+type AtomPredicate<'q> = Box<dyn Fn(&Molecule, AtomIndex, AtomIndex) -> bool + 'q>;
+type BondPredicate<'q> =
+    Box<dyn Fn(&Molecule, (AtomIndex, AtomIndex), (AtomIndex, AtomIndex)) -> bool + 'q>;
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Options {
+    unique: bool,
+    limit: Option<usize>,
+    chirality: bool,
+    /// Only finds matches where the first query atom matches this molecule atom.
+    anchor: Option<AtomIndex>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            unique: false,
+            limit: None,
+            chirality: true,
+            anchor: None,
+        }
+    }
+}
+
+impl Options {
+    pub(crate) fn anchored(atom: AtomIndex) -> Self {
+        Options {
+            anchor: Some(atom),
+            ..Options::default()
+        }
+    }
+}
+
+/// Options for finding a query in molecules. Each match maps query atoms to molecule atoms,
+/// indexed by query atom. Query bonds must be present in the molecule, but molecule bonds between
+/// matched atoms need not be in the query.
+pub struct SubstructureSearch<'q> {
+    query: &'q Query,
+    options: Options,
+    atom_predicates: Vec<AtomPredicate<'q>>,
+    bond_predicates: Vec<BondPredicate<'q>>,
+}
+
+impl<'q> SubstructureSearch<'q> {
+    pub fn new(query: &'q Query) -> Self {
+        SubstructureSearch {
+            query,
+            options: Options::default(),
+            atom_predicates: vec![],
+            bond_predicates: vec![],
+        }
+    }
+
+    /// Only yields the first match for each set of molecule atoms, so symmetric queries such as
+    /// benzene match once rather than once per symmetry.
+    pub fn unique(mut self, unique: bool) -> Self {
+        self.options.unique = unique;
+        self
+    }
+
+    /// Stops after this many matches.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.options.limit = Some(limit);
+        self
+    }
+
+    /// Whether chirality in the query must match the molecule. On by default.
+    pub fn chirality(mut self, chirality: bool) -> Self {
+        self.options.chirality = chirality;
+        self
+    }
+
+    /// An extra test on top of the query's own, given the molecule, a molecule atom and the query
+    /// atom it would match.
+    pub fn atom_predicate(
+        mut self,
+        predicate: impl Fn(&Molecule, AtomIndex, AtomIndex) -> bool + 'q,
+    ) -> Self {
+        self.atom_predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// An extra test on top of the query's own, given the molecule, a molecule bond and the query
+    /// bond it would match. The bonds' atoms are in corresponding order.
+    pub fn bond_predicate(
+        mut self,
+        predicate: impl Fn(&Molecule, (AtomIndex, AtomIndex), (AtomIndex, AtomIndex)) -> bool + 'q,
+    ) -> Self {
+        self.bond_predicates.push(Box::new(predicate));
+        self
+    }
+
+    pub fn matches<'a>(&'a self, mol: &'a Molecule) -> Matches<'a, 'q> {
+        Matches::new(
+            self.query,
+            self.options,
+            &self.atom_predicates,
+            &self.bond_predicates,
+            TargetRef::Owned(Target::new(mol)),
+        )
+    }
+
+    pub fn has_match(&self, mol: &Molecule) -> bool {
+        self.matches(mol).next().is_some()
+    }
+
+    /// The matches in a prepared target, which can be searched again with other queries without
+    /// repeating its ring perception.
+    pub fn target_matches<'a>(&'a self, target: &'a Target<'a>) -> Matches<'a, 'q> {
+        Matches::new(
+            self.query,
+            self.options,
+            &self.atom_predicates,
+            &self.bond_predicates,
+            TargetRef::Borrowed(target),
+        )
+    }
+
+    pub fn has_target_match(&self, target: &Target) -> bool {
+        self.target_matches(target).next().is_some()
+    }
+}
+
+/// The molecule a search runs over. Recursive queries search the same molecule again, so they
+/// borrow its ring perception rather than repeat it, as do searches of a prepared target.
+pub(crate) enum TargetRef<'a> {
+    Owned(Target<'a>),
+    Borrowed(&'a Target<'a>),
+}
+
+impl<'a> std::ops::Deref for TargetRef<'a> {
+    type Target = Target<'a>;
+
+    fn deref(&self) -> &Target<'a> {
+        match self {
+            TargetRef::Owned(target) => target,
+            TargetRef::Borrowed(target) => target,
+        }
+    }
+}
+
+/// A lazy iterator over substructure matches, found by depth first search over query atoms in
+/// breadth first order, so that every atom after the first of a component is tried only against
+/// neighbours of an atom that is already matched.
+pub struct Matches<'a, 'q> {
+    query: &'q Query,
+    options: Options,
+    bond_predicates: &'a [BondPredicate<'q>],
+    target: TargetRef<'a>,
+    /// Query atoms in the order they are matched, each with an already ordered neighbour.
+    order: Vec<(AtomIndex, Option<AtomIndex>)>,
+    /// Which molecule atoms each query atom matches on its own.
+    compatible: Vec<Vec<bool>>,
+    /// Candidates for each query atom in `order` that have been matched so far.
+    stack: Vec<Vec<AtomIndex>>,
+    mapping: Vec<Option<AtomIndex>>,
+    used: Vec<bool>,
+    seen: HashSet<Vec<AtomIndex>>,
+    found: usize,
+    done: bool,
+}
+
+impl<'a, 'q> Matches<'a, 'q> {
+    pub(crate) fn new(
+        query: &'q Query,
+        options: Options,
+        atom_predicates: &'a [AtomPredicate<'q>],
+        bond_predicates: &'a [BondPredicate<'q>],
+        target: TargetRef<'a>,
+    ) -> Self {
+        let mol = target.mol;
+
+        let compatible = query
+            .atoms()
+            .map(|q| {
+                mol.atoms()
+                    .map(|a| {
+                        let query_atom = query.atom(q);
+                        mol.degree(a) >= query.degree(q)
+                            && target.atom_matches(a, &query_atom.expr, query, q, None)
+                            && atom_predicates.iter().all(|p| p(mol, a, q))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let done = query.atom_count() == 0 || options.limit == Some(0);
+        let mut matches = Matches {
+            query,
+            options,
+            bond_predicates,
+            order: match_order(query),
+            compatible,
+            stack: vec![],
+            mapping: vec![None; query.atom_count()],
+            used: vec![false; mol.atom_count()],
+            seen: HashSet::new(),
+            found: 0,
+            done,
+            target,
+        };
+        if !matches.done {
+            let candidates = matches.candidates(0);
+            matches.stack.push(candidates);
+        }
+        matches
+    }
+
+    /// Molecule atoms that could match the query atom at `depth` in the order, in reverse so that
+    /// they are popped in ascending order.
+    fn candidates(&self, depth: usize) -> Vec<AtomIndex> {
+        let (q, parent) = self.order[depth];
+        let mol = self.target.mol;
+        let mut candidates: Vec<AtomIndex> = match parent {
+            Some(parent) => {
+                let mut neighbors: Vec<AtomIndex> = mol
+                    .neighbors(self.mapping[parent.index()].unwrap())
+                    .collect();
+                neighbors.sort();
+                neighbors
+            }
+            None if depth == 0 && self.options.anchor.is_some() => {
+                vec![self.options.anchor.unwrap()]
+            }
+            None => mol.atoms().collect(),
+        };
+        candidates.retain(|a| !self.used[a.index()] && self.compatible[q.index()][a.index()]);
+        candidates.reverse();
+        candidates
+    }
+
+    /// Whether the bonds from `q` to already matched query atoms are present in the molecule.
+    fn bonds_match(&self, q: AtomIndex, atom: AtomIndex) -> bool {
+        let query = self.query;
+        let mol = self.target.mol;
+        query.neighbors(q).all(|qn| match self.mapping[qn.index()] {
+            Some(n) => {
+                let expr = query.bond(q, qn).unwrap();
+                self.target.bond_matches(atom, n, expr)
+                    && self
+                        .bond_predicates
+                        .iter()
+                        .all(|p| p(mol, (atom, n), (q, qn)))
+            }
+            None => true,
+        })
+    }
+
+    fn chirality_matches(&self, mapping: &[AtomIndex]) -> bool {
+        if !self.options.chirality {
+            return true;
+        }
+        let query = self.query;
+        query.atoms().all(|q| {
+            let expr = &query.atom(q).expr;
+            let chiral = expr
+                .primitives()
+                .iter()
+                .any(|p| matches!(p, AtomPrimitive::Chirality(_)));
+            !chiral
+                || self
+                    .target
+                    .atom_matches(mapping[q.index()], expr, query, q, Some(mapping))
+        })
+    }
+
+    fn unmap(&mut self, depth: usize) {
+        let q = self.order[depth].0;
+        if let Some(atom) = self.mapping[q.index()].take() {
+            self.used[atom.index()] = false;
+        }
+    }
+}
+
+impl<'a, 'q> Iterator for Matches<'a, 'q> {
+    type Item = Vec<AtomIndex>;
+
+    fn next(&mut self) -> Option<Vec<AtomIndex>> {
+        while !self.done {
+            let depth = match self.stack.len() {
+                0 => {
+                    self.done = true;
+                    break;
+                }
+                len => len - 1,
+            };
+            // Undo the previous candidate for this query atom, then try the next one
+            self.unmap(depth);
+            let atom = match self.stack[depth].pop() {
+                Some(atom) => atom,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let q = self.order[depth].0;
+            if !self.bonds_match(q, atom) {
+                continue;
+            }
+            self.mapping[q.index()] = Some(atom);
+            self.used[atom.index()] = true;
+
+            if depth + 1 < self.order.len() {
+                let candidates = self.candidates(depth + 1);
+                self.stack.push(candidates);
+                continue;
+            }
+
+            let mapping: Vec<AtomIndex> = self.mapping.iter().map(|a| a.unwrap()).collect();
+            if !self.chirality_matches(&mapping) {
+                continue;
+            }
+            if self.options.unique {
+                let mut atoms = mapping.clone();
+                atoms.sort();
+                if !self.seen.insert(atoms) {
+                    continue;
+                }
+            }
+            self.found += 1;
+            if Some(self.found) == self.options.limit {
+                self.done = true;
+            }
+            return Some(mapping);
+        }
+        None
+    }
+}
+
+/// Query atoms in breadth first order from the lowest index of each component, each with the
+/// neighbour it was reached from.
+fn match_order(query: &Query) -> Vec<(AtomIndex, Option<AtomIndex>)> {
+    let mut order = vec![];
+    let mut visited = vec![false; query.atom_count()];
+    for start in query.atoms() {
+        if visited[start.index()] {
+            continue;
+        }
+        visited[start.index()] = true;
+        let mut queue = VecDeque::from(vec![(start, None)]);
+        while let Some((atom, parent)) = queue.pop_front() {
+            order.push((atom, parent));
+            let mut neighbors: Vec<AtomIndex> = query.neighbors(atom).collect();
+            neighbors.sort();
+            for n in neighbors {
+                if !visited[n.index()] {
+                    visited[n.index()] = true;
+                    queue.push_back((n, Some(atom)));
+                }
+            }
+        }
+    }
+    order
+}
+
+impl Molecule {
+    /// Every match of the query in this molecule, as the molecule atom matched by each query
+    /// atom. See [`SubstructureSearch`] for more options.
+    pub fn substructure_matches<'a>(
+        &'a self,
+        query: &'a Query,
+    ) -> impl Iterator<Item = Vec<AtomIndex>> + 'a {
+        Matches::new(
+            query,
+            Options::default(),
+            &[],
+            &[],
+            TargetRef::Owned(Target::new(self)),
+        )
+    }
+
+    pub fn has_substructure(&self, query: &Query) -> bool {
+        self.substructure_matches(query).next().is_some()
+    }
+}
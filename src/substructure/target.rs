@@ -0,0 +1,187 @@
+use crate::core::{AtomIndex, Bond, Chirality, Molecule, RingInfo};
+use crate::daylight::{AtomExpr, AtomPrimitive, BondExpr, BondPrimitive, Query};
+use crate::substructure::search::{Matches, Options, TargetRef};
+use std::cell::OnceCell;
+
+/// A molecule prepared for searching. Rings are only perceived once a query needs them, and are
+/// then kept, so searching one target for many queries perceives them at most once.
+pub struct Target<'m> {
+    pub(crate) mol: &'m Molecule,
+    rings: OnceCell<RingInfo>,
+}
+
+impl<'m> Target<'m> {
+    pub fn new(mol: &'m Molecule) -> Self {
+        Target {
+            mol,
+            rings: OnceCell::new(),
+        }
+    }
+
+    pub fn molecule(&self) -> &'m Molecule {
+        self.mol
+    }
+
+    fn rings(&self) -> &RingInfo {
+        self.rings.get_or_init(|| self.mol.rings())
+    }
+
+    /// Whether an atom matches an expression. Chirality depends on the whole match, so it is
+    /// only checked when `mapping` is given; otherwise the atom matches if it would for some
+    /// chirality.
+    pub(crate) fn atom_matches(
+        &self,
+        atom: AtomIndex,
+        expr: &AtomExpr,
+        query: &Query,
+        query_atom: AtomIndex,
+        mapping: Option<&[AtomIndex]>,
+    ) -> bool {
+        match mapping {
+            Some(mapping) => expr.matches(&mut |primitive| match primitive {
+                AtomPrimitive::Chirality(chirality) => {
+                    self.chirality_matches(atom, *chirality, query, query_atom, mapping)
+                }
+                primitive => self.primitive_matches(atom, primitive),
+            }),
+            None => {
+                let matches = expr.may_match(&mut |primitive| match primitive {
+                    AtomPrimitive::Chirality(_) => None,
+                    primitive => Some(self.primitive_matches(atom, primitive)),
+                });
+                matches != Some(false)
+            }
+        }
+    }
+
+    fn primitive_matches(&self, atom: AtomIndex, primitive: &AtomPrimitive) -> bool {
+        let mol = self.mol;
+        let a = mol.atom(atom);
+        let count = |n: Option<u8>, actual: usize| match n {
+            Some(n) => actual == n as usize,
+            None => actual > 0,
+        };
+        match primitive {
+            AtomPrimitive::Any => true,
+            AtomPrimitive::Aromatic => a.aromatic,
+            AtomPrimitive::Aliphatic => !a.aromatic,
+            AtomPrimitive::Symbol { element, aromatic } => {
+                a.element == *element && a.aromatic == *aromatic
+            }
+            AtomPrimitive::AtomicNumber(n) => a.element.atomic_number() == *n,
+            AtomPrimitive::Degree(n) => mol.degree(atom) == *n as usize,
+            AtomPrimitive::Connectivity(n) => {
+                mol.degree(atom) + a.hydrogens as usize == *n as usize
+            }
+            AtomPrimitive::TotalHydrogens(n) => self.total_hydrogens(atom) == *n as usize,
+            AtomPrimitive::ImplicitHydrogens(n) => count(*n, a.hydrogens as usize),
+            AtomPrimitive::RingMembership(n) => {
+                let rings = self
+                    .rings()
+                    .sssr()
+                    .iter()
+                    .filter(|ring| ring.contains(atom))
+                    .count();
+                count(*n, rings)
+            }
+            AtomPrimitive::RingSize(Some(0)) => !self.rings().is_in_ring(atom),
+            AtomPrimitive::RingSize(Some(n)) => self.rings().is_in_ring_of_size(atom, *n as usize),
+            AtomPrimitive::RingSize(None) => self.rings().is_in_ring(atom),
+            AtomPrimitive::RingConnectivity(n) => {
+                let ring_bonds = mol
+                    .neighbors(atom)
+                    .filter(|b| self.rings().bond_in_ring(atom, *b))
+                    .count();
+                count(*n, ring_bonds)
+            }
+            AtomPrimitive::Valence(n) => mol.valence(atom) == *n,
+            AtomPrimitive::Charge(charge) => a.formal_charge == *charge,
+            AtomPrimitive::Isotope(isotope) => a.isotope == Some(*isotope),
+            AtomPrimitive::Chirality(_) => true,
+            AtomPrimitive::Recursive(inner) => Matches::new(
+                inner,
+                Options::anchored(atom),
+                &[],
+                &[],
+                TargetRef::Borrowed(self),
+            )
+            .next()
+            .is_some(),
+        }
+    }
+
+    /// Implicit hydrogens plus any hydrogens that are atoms in the graph.
+    fn total_hydrogens(&self, atom: AtomIndex) -> usize {
+        let explicit = self
+            .mol
+            .neighbors(atom)
+            .filter(|n| self.mol.atom(*n).element == crate::core::Element::H)
+            .count();
+        self.mol.atom(atom).hydrogens as usize + explicit
+    }
+
+    pub(crate) fn bond_matches(&self, a: AtomIndex, b: AtomIndex, expr: &BondExpr) -> bool {
+        let bond = match self.mol.bond(a, b) {
+            Some(bond) => bond,
+            None => return false,
+        };
+        expr.matches(&mut |primitive| match primitive {
+            BondPrimitive::Implicit => matches!(bond, Bond::Single | Bond::Aromatic),
+            BondPrimitive::Single | BondPrimitive::Up | BondPrimitive::Down => bond == Bond::Single,
+            BondPrimitive::Double => bond == Bond::Double,
+            BondPrimitive::Triple => bond == Bond::Triple,
+            BondPrimitive::Aromatic => bond == Bond::Aromatic,
+            BondPrimitive::Any => true,
+            BondPrimitive::Ring => self.rings().bond_in_ring(a, b),
+        })
+    }
+
+    /// Compares a stereocentre's configuration with the query's. Both are given relative to their
+    /// own neighbour order, so the target's neighbours are put in the query's order first. A
+    /// target neighbour with no query counterpart, such as an implicit hydrogen, takes the place
+    /// of the query's implicit hydrogen.
+    fn chirality_matches(
+        &self,
+        atom: AtomIndex,
+        chirality: Chirality,
+        query: &Query,
+        query_atom: AtomIndex,
+        mapping: &[AtomIndex],
+    ) -> bool {
+        let actual = match self.mol.atom(atom).chirality {
+            Some(actual) => actual,
+            None => return false,
+        };
+
+        // Target neighbours in the core order: implicit hydrogen first, then ascending index
+        let mut target: Vec<Option<AtomIndex>> = vec![];
+        if self.mol.atom(atom).hydrogens > 0 {
+            target.push(None);
+        }
+        let mut neighbors: Vec<AtomIndex> = self.mol.neighbors(atom).collect();
+        neighbors.sort();
+        target.extend(neighbors.into_iter().map(Some));
+
+        let mut query_neighbors: Vec<AtomIndex> = query.neighbors(query_atom).collect();
+        query_neighbors.sort();
+        let mapped: Vec<Option<AtomIndex>> = query_neighbors
+            .iter()
+            .map(|n| Some(mapping[n.index()]))
+            .collect();
+        let mut in_query_order: Vec<Option<AtomIndex>> = target
+            .iter()
+            .filter(|n| !mapped.contains(n))
+            .cloned()
+            .collect();
+        if in_query_order.len() > 1 {
+            return false;
+        }
+        in_query_order.extend(mapped);
+
+        let order: Vec<usize> = in_query_order
+            .iter()
+            .map(|n| target.iter().position(|t| t == n).unwrap())
+            .collect();
+        actual.permuted(&order) == chirality
+    }
+}